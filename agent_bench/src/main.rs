@@ -59,15 +59,24 @@ fn main() {
 
 
 
-        if msg == "start_bench" {
+        if msg == "start_bench" || msg.starts_with("start_bench|") {
             //Start the benchmark if the master asked so
             println!("Received START for Bench!");
 
+            //The master may ask for a shorter run (start_bench|fidelity=<fraction>)
+            let fidelity = parse_fidelity(&msg);
+            let bench_args = scale_bench_args(&str_bin_args, &parser.benchmark_name, fidelity);
+            println!("Benchmark Fidelity: {} - Arguments: {}", fidelity, bench_args);
+
             rep_socket.send("45.6", 0).unwrap();
 
-            /*match execute_bench(str_bin_path.clone(), str_bin_args.clone(), parser.clone()) {
-                Some(r) => {
-                    rep_socket.send(r.to_string().as_str(), 0).unwrap();
+            /*match execute_bench(str_bin_path.clone(), bench_args, parser.clone()) {
+                Some(metrics) => {
+                    let mut metrics_sequence = String::new();
+                    for (name, value) in metrics {
+                        metrics_sequence += format!("{}={}|", name, value).as_str();
+                    }
+                    rep_socket.send(metrics_sequence.as_str(), 0).unwrap();
                 }
                 None => rep_socket.send("None", 0).unwrap(),
             }*/
@@ -77,7 +86,13 @@ fn main() {
     }
 }
 
-fn execute_bench(bench_bin_path: String, bench_args: String, parser: Parser) -> Option<f64> {
+/// Execute the benchmark and extract the metrics it reports, which are sent to the tuner in
+/// the form name=value|name=value|
+fn execute_bench(
+    bench_bin_path: String,
+    bench_args: String,
+    parser: Parser,
+) -> Option<Vec<(String, f64)>> {
 
     let bench_args: Vec<&str> = bench_args.split_whitespace().collect();
 
@@ -95,12 +110,93 @@ fn execute_bench(bench_bin_path: String, bench_args: String, parser: Parser) ->
     );
 
 
-    //Extract result of energy
-    let meas_nrg = parser.parse(output);
+    //Extract the metrics
+    let metrics = parser.parse_metrics(output);
+
+
+    return metrics;
+
+}
+
+/// Fidelity requested by the master, i.e., the fraction of the full duration of the benchmark.
+/// A plain start_bench asks for the full duration.
+fn parse_fidelity(msg: &str) -> f64 {
+    msg.split('|')
+        .filter_map(|field| {
+            let mut pair = field.splitn(2, '=');
+            match (pair.next(), pair.next()) {
+                (Some("fidelity"), Some(val)) => val.trim().parse::<f64>().ok(),
+                _ => None,
+            }
+        })
+        .next()
+        .unwrap_or(1.0)
+        .max(0.0)
+        .min(1.0)
+}
+
+/// Adjust the arguments of the benchmark to the fidelity: the duration of wrk (-d/--duration)
+/// and memaslap (-t/--time), the operation count of ycsb (-p operationcount=N)
+fn scale_bench_args(bench_args: &str, bench_type: &BenchmarkName, fidelity: f64) -> String {
+    if fidelity >= 1.0 {
+        return bench_args.to_string();
+    }
+
+    let (short_opt, long_opt) = match *bench_type {
+        BenchmarkName::Wrk => ("-d", "--duration"),
+        BenchmarkName::Memaslap => ("-t", "--time"),
+        BenchmarkName::Ycsb => ("", ""),
+    };
 
+    let mut scaled_args: Vec<String> = Vec::new();
+    let mut is_duration = false;
+    for arg in bench_args.split_whitespace() {
+        let scaled = if is_duration {
+            scale_duration(arg, fidelity)
+        } else if !short_opt.is_empty() && (arg == short_opt || arg == long_opt) {
+            arg.to_string()
+        } else if !short_opt.is_empty() && arg.starts_with(&format!("{}=", long_opt)) {
+            format!("{}={}", long_opt, scale_duration(&arg[long_opt.len() + 1..], fidelity))
+        } else if !short_opt.is_empty() && arg.starts_with(short_opt) && !arg.starts_with("--") {
+            format!("{}{}", short_opt, scale_duration(&arg[short_opt.len()..], fidelity))
+        } else if arg.starts_with("operationcount=") {
+            match arg["operationcount=".len()..].parse::<u64>() {
+                Ok(count) => {
+                    let scaled_count = ((count as f64 * fidelity).round() as u64).max(1);
+                    format!("operationcount={}", scaled_count)
+                }
+                Err(_) => arg.to_string(),
+            }
+        } else {
+            arg.to_string()
+        };
+
+        is_duration = !short_opt.is_empty() && (arg == short_opt || arg == long_opt);
+        scaled_args.push(scaled);
+    }
 
-    return meas_nrg;
+    scaled_args.join(" ")
+}
 
+/// Scale a duration in the form <number>[s|m|h|d] (seconds if no unit is given). The scaled
+/// duration is given in seconds and it is at least one second.
+fn scale_duration(duration: &str, fidelity: f64) -> String {
+    let split = duration.find(|c: char| !c.is_digit(10)).unwrap_or(duration.len());
+    let seconds_per_unit = match &duration[split..] {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return duration.to_string(),
+    };
+
+    match duration[..split].parse::<u64>() {
+        Ok(val) => {
+            let seconds = ((val * seconds_per_unit) as f64 * fidelity).round() as u64;
+            format!("{}s", seconds.max(1))
+        }
+        Err(_) => duration.to_string(),
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +23,31 @@ impl Parser {
     }
 
 
+    /***
+	Extract all the metrics that the benchmark reports: the throughput and, when the
+	output contains it, the average latency in ms
+	***/
+    pub fn parse_metrics(&self, output: Output) -> Option<Vec<(String, f64)>> {
+
+        let latency = match self.benchmark_name {
+            BenchmarkName::Ycsb => self.parse_ycsb_latency(&output),
+            BenchmarkName::Wrk => self.parse_wrk_latency(&output),
+            BenchmarkName::Memaslap => self.parse_memaslap_latency(&output),
+        };
+
+        let mut metrics = Vec::new();
+        match self.parse(output) {
+            Some(throughput) => metrics.push(("throughput".to_string(), throughput)),
+            None => return None,
+        }
+        if let Some(lat) = latency {
+            metrics.push(("latency".to_string(), lat));
+        }
+
+        return Some(metrics);
+    }
+
+
     /***********************************************************************************************************
     /// **
     /// YCSB
@@ -183,4 +208,80 @@ impl Parser {
 
         return Some(fvalue * multiplier_constant);
     }
+
+
+
+    /***********************************************************************************************************
+    /// **
+    /// Latencies
+    /// *
+    	************************************************************************************************************/
+
+    // [READ], AverageLatency(us), 123.45
+    fn parse_ycsb_latency(&self, output: &Output) -> Option<f64> {
+        let output_str = match str::from_utf8(output.stdout.as_slice()) {
+            Ok(v) => v,
+            Err(e) => panic!("Invalid UTF-8 sequence: {}", e),
+        };
+
+        let mut iter = output_str.split_whitespace();
+        match iter.position(|elem| elem == "AverageLatency(us),") {
+            Some(_) => iter.next().and_then(|v| v.parse::<f64>().ok()).map(|v| v / 1000.0),
+            None => None,
+        }
+    }
+
+    // Thread Stats   Avg      Stdev     Max   +/- Stdev
+    //   Latency   635.91us    0.89ms  12.92ms   93.69%
+    fn parse_wrk_latency(&self, output: &Output) -> Option<f64> {
+        let output_str = match str::from_utf8(output.stdout.as_slice()) {
+            Ok(v) => v,
+            Err(e) => panic!("Invalid UTF-8 sequence: {}", e),
+        };
+
+        let mut iter = output_str.split_whitespace();
+        let raw_value = match iter.position(|elem| elem == "Latency") {
+            Some(_) => {
+                match iter.next() {
+                    Some(v) => v,
+                    None => return None,
+                }
+            }
+            None => return None,
+        };
+
+        let (value, multiplier_constant) = if raw_value.ends_with("us") {
+            (raw_value.trim_right_matches("us"), 0.001)
+        } else if raw_value.ends_with("ms") {
+            (raw_value.trim_right_matches("ms"), 1.0)
+        } else if raw_value.ends_with("s") {
+            (raw_value.trim_right_matches("s"), 1000.0)
+        } else {
+            return None;
+        };
+
+        value.parse::<f64>().ok().map(|v| v * multiplier_constant)
+    }
+
+    // The last statistics printed are the total ones:
+    // Total Statistics (1000 events)
+    //    Min:        24
+    //    Max:      1563
+    //    Avg:       131
+    fn parse_memaslap_latency(&self, output: &Output) -> Option<f64> {
+        let output_str = match str::from_utf8(output.stdout.as_slice()) {
+            Ok(v) => v,
+            Err(e) => panic!("Invalid UTF-8 sequence: {}", e),
+        };
+
+        let tokens: Vec<&str> = output_str.split_whitespace().collect();
+        match tokens.iter().rposition(|elem| *elem == "Avg:") {
+            Some(base) => {
+                tokens.get(base + 1).and_then(|v| v.parse::<f64>().ok()).map(
+                    |v| v / 1000.0,
+                )
+            }
+            None => None,
+        }
+    }
 }
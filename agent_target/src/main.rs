@@ -58,7 +58,8 @@ fn main() {
             println!("Received START for Target!");
 
             for param in msg_sequence.iter() {
-                let splitted_param: Vec<&str> = param.split('=').collect();
+                // The value may contain '=' (e.g. an enum value such as --with-x=y)
+                let splitted_param: Vec<&str> = param.splitn(2, '=').collect();
                 match hm_params_level.get(splitted_param[0]) {
                     Some(service_level) => {
                         match *service_level {
@@ -8,16 +8,34 @@ pub struct ScriptInfo {
     pub envfile: String,
 }
 
+/// Spacing of the values of a numeric parameter between its minimum and maximum: a constant
+/// step, or a constant ratio of (100 + percent)% between two consecutive values (log scale)
+#[derive(Debug, Clone, Copy)]
+pub enum Scale {
+    Step(f64),
+    Percent(f64),
+}
+
 #[derive(Debug, Clone)]
 pub struct IntParameter {
     pub name: String,
     pub min: usize,
     pub max: usize,
-    pub step: usize,
+    pub scale: Scale,
     pub default: usize,
     pub level: ParameterLevel,
 }
 
+#[derive(Debug, Clone)]
+pub struct FloatParameter {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub scale: Scale,
+    pub default: f64,
+    pub level: ParameterLevel,
+}
+
 #[derive(Debug, Clone)]
 pub struct BoolParameter {
     pub name: String,
@@ -26,3 +44,11 @@ pub struct BoolParameter {
     pub default: bool,
     pub level: ParameterLevel,
 }
+
+#[derive(Debug, Clone)]
+pub struct EnumParameter {
+    pub name: String,
+    pub values: Vec<String>,
+    pub default: String,
+    pub level: ParameterLevel,
+}
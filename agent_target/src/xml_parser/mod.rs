@@ -3,12 +3,14 @@ use std::io::BufReader;
 use xml::reader::{EventReader, XmlEvent};
 use std::collections::HashMap;
 use ParameterLevel;
-use shared::{IntParameter, BoolParameter, ScriptInfo};
+use shared::{IntParameter, FloatParameter, BoolParameter, EnumParameter, ScriptInfo, Scale};
 
 #[derive(Debug, Clone)]
 pub struct XMLReader {
     targ_int_param: Vec<IntParameter>,
+    targ_float_param: Vec<FloatParameter>,
     targ_bool_param: Vec<BoolParameter>,
+    targ_enum_param: Vec<EnumParameter>,
     hm_script: HashMap<String, String>,
 }
 
@@ -21,16 +23,22 @@ impl XMLReader {
         let parser = EventReader::new(file);
 
         let mut found_int_targ = false;
+        let mut found_float_targ = false;
         let mut found_bool_targ = false;
+        let mut found_enum_targ = false;
         let mut found_script = false;
 
 
         let mut _hm_int_targ: HashMap<String, String> = HashMap::new();
+        let mut _hm_float_targ: HashMap<String, String> = HashMap::new();
         let mut _hm_bool_targ: HashMap<String, String> = HashMap::new();
+        let mut _hm_enum_targ: HashMap<String, String> = HashMap::new();
         let mut _hm_script_info: HashMap<String, String> = HashMap::new();
 
         let mut _targ_int_p: Vec<IntParameter> = Vec::new();
+        let mut _targ_float_p: Vec<FloatParameter> = Vec::new();
         let mut _targ_bool_p: Vec<BoolParameter> = Vec::new();
+        let mut _targ_enum_p: Vec<EnumParameter> = Vec::new();
 
 
         let mut tag = String::new();
@@ -40,15 +48,38 @@ impl XMLReader {
                     tag = name.to_string();
                     if name.to_string() == "int-parameter" {
                         found_int_targ = true;
+                        found_float_targ = false;
                         found_bool_targ = false;
+                        found_enum_targ = false;
                         found_script = false;
+                        // The elements of the previous parameter (e.g. its step) are not inherited
+                        _hm_int_targ.clear();
+                    } else if name.to_string() == "float-parameter" {
+                        found_int_targ = false;
+                        found_float_targ = true;
+                        found_bool_targ = false;
+                        found_enum_targ = false;
+                        found_script = false;
+                        _hm_float_targ.clear();
                     } else if name.to_string() == "bool-parameter" {
                         found_int_targ = false;
+                        found_float_targ = false;
                         found_bool_targ = true;
+                        found_enum_targ = false;
+                        found_script = false;
+                        _hm_bool_targ.clear();
+                    } else if name.to_string() == "enum-parameter" {
+                        found_int_targ = false;
+                        found_float_targ = false;
+                        found_bool_targ = false;
+                        found_enum_targ = true;
                         found_script = false;
+                        _hm_enum_targ.clear();
                     } else if name.to_string() == "builder-script" {
                         found_int_targ = false;
+                        found_float_targ = false;
                         found_bool_targ = false;
+                        found_enum_targ = false;
                         found_script = true;
                     }
 
@@ -56,8 +87,12 @@ impl XMLReader {
                 Ok(XmlEvent::Characters(val)) => {
                     if found_int_targ == true {
                         _hm_int_targ.insert(tag.clone(), val.clone());
+                    } else if found_float_targ == true {
+                        _hm_float_targ.insert(tag.clone(), val.clone());
                     } else if found_bool_targ == true {
                         _hm_bool_targ.insert(tag.clone(), val.clone());
+                    } else if found_enum_targ == true {
+                        _hm_enum_targ.insert(tag.clone(), val.clone());
                     } else if found_script == true {
                         _hm_script_info.insert(tag.clone(), val.clone());
                     }
@@ -67,8 +102,12 @@ impl XMLReader {
 
                     if name.to_string() == "int-parameter" {
                         found_int_targ = false;
+                    } else if name.to_string() == "float-parameter" {
+                        found_float_targ = false;
                     } else if name.to_string() == "bool-parameter" {
                         found_bool_targ = false;
+                    } else if name.to_string() == "enum-parameter" {
+                        found_enum_targ = false;
                     }
 
 
@@ -87,12 +126,7 @@ impl XMLReader {
                                 .to_string()
                                 .parse::<usize>()
                                 .unwrap(),
-                            step: _hm_int_targ
-                                .get("step")
-                                .unwrap()
-                                .to_string()
-                                .parse::<usize>()
-                                .unwrap(),
+                            scale: parse_scale(&_hm_int_targ),
                             default: _hm_int_targ
                                 .get("default")
                                 .unwrap()
@@ -110,6 +144,39 @@ impl XMLReader {
                     }
 
 
+                    if name.to_string() == "float-parameter" {
+                        let targ_float_param = FloatParameter {
+                            name: _hm_float_targ.get("name").unwrap().to_string(),
+                            min: _hm_float_targ
+                                .get("minimum")
+                                .unwrap()
+                                .to_string()
+                                .parse::<f64>()
+                                .unwrap(),
+                            max: _hm_float_targ
+                                .get("maximum")
+                                .unwrap()
+                                .to_string()
+                                .parse::<f64>()
+                                .unwrap(),
+                            scale: parse_scale(&_hm_float_targ),
+                            default: _hm_float_targ
+                                .get("default")
+                                .unwrap()
+                                .to_string()
+                                .parse::<f64>()
+                                .unwrap(),
+                            level: _hm_float_targ
+                                .get("level")
+                                .unwrap()
+                                .to_string()
+                                .parse()
+                                .unwrap(),
+                        };
+                        _targ_float_p.push(targ_float_param);
+                    }
+
+
                     if name.to_string() == "bool-parameter" {
                         let targ_bool_param = BoolParameter {
                             name: _hm_bool_targ.get("name").unwrap().to_string(),
@@ -131,6 +198,27 @@ impl XMLReader {
                         _targ_bool_p.push(targ_bool_param);
                     }
 
+
+                    if name.to_string() == "enum-parameter" {
+                        let targ_enum_param = EnumParameter {
+                            name: _hm_enum_targ.get("name").unwrap().to_string(),
+                            values: _hm_enum_targ
+                                .get("values")
+                                .unwrap()
+                                .split(',')
+                                .map(|val| val.trim().to_string())
+                                .collect(),
+                            default: _hm_enum_targ.get("default").unwrap().trim().to_string(),
+                            level: _hm_enum_targ
+                                .get("level")
+                                .unwrap()
+                                .to_string()
+                                .parse()
+                                .unwrap(),
+                        };
+                        _targ_enum_p.push(targ_enum_param);
+                    }
+
                 }
 
                 Err(e) => {
@@ -144,7 +232,9 @@ impl XMLReader {
 
         XMLReader {
             targ_int_param: _targ_int_p,
+            targ_float_param: _targ_float_p,
             targ_bool_param: _targ_bool_p,
+            targ_enum_param: _targ_enum_p,
             hm_script: _hm_script_info,
         }
     }
@@ -160,6 +250,17 @@ impl XMLReader {
     }
 
 
+    /***********************************************************************************************************
+    /// **
+    /// Target Enum Params
+    /// *
+    	************************************************************************************************************/
+
+    pub fn get_target_enum_params(&self) -> Vec<EnumParameter> {
+        return self.targ_enum_param.clone();
+    }
+
+
     /***********************************************************************************************************
     /// **
     /// Target Int Params
@@ -182,6 +283,14 @@ impl XMLReader {
             hm_params.insert(param.clone().name, param.clone().level);
         }
 
+        for param in self.targ_float_param.iter() {
+            hm_params.insert(param.clone().name, param.clone().level);
+        }
+
+        for param in self.targ_enum_param.iter() {
+            hm_params.insert(param.clone().name, param.clone().level);
+        }
+
         return hm_params;
     }
 
@@ -199,3 +308,31 @@ impl XMLReader {
         };
     }
 }
+
+
+/// Scale of a numeric parameter: a <step> makes its values equally spaced, a <percent> makes each
+/// value percent% larger than the previous one
+fn parse_scale(hm_param: &HashMap<String, String>) -> Scale {
+    let name = hm_param.get("name").unwrap();
+    match (hm_param.get("step"), hm_param.get("percent")) {
+        (Some(step), None) => {
+            let step = step.trim().parse::<f64>().unwrap();
+            if step <= 0.0 {
+                panic!("The step of the parameter {} must be positive", name);
+            }
+            Scale::Step(step)
+        }
+        (None, Some(percent)) => {
+            let percent = percent.trim().parse::<f64>().unwrap();
+            if percent <= 0.0 {
+                panic!("The percent of the parameter {} must be positive", name);
+            }
+            let min = hm_param.get("minimum").unwrap().trim().parse::<f64>().unwrap();
+            if min <= 0.0 {
+                panic!("The minimum of the log-scale parameter {} must be positive", name);
+            }
+            Scale::Percent(percent)
+        }
+        _ => panic!("The parameter {} needs either a <step> or a <percent>", name),
+    }
+}
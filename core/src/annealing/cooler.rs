@@ -15,6 +15,10 @@
 ///  See the License for the specific language governing permissions and
 ///  limitations under the License.
 /// ///////////////////////////////////////////////////////////////////////////
+use shared::TunerParameter;
+use std::fmt::Debug;
+use std::sync::Arc;
+
 #[derive(Debug, Clone)]
 pub struct TimeCooler {
     pub max_time: usize,
@@ -29,26 +33,133 @@ pub struct StepsCooler {
     pub max_temp: f64,
 }
 
-pub trait Cooler {
+pub trait Cooler: Debug + Send + Sync {
     fn exponential_cooling(&self, metric: usize) -> f64;
     fn linear_cooling(&self, metric: usize) -> f64;
     fn basic_exp_cooling(&self, current_temp: f64) -> f64;
+    fn lam_cooling(&self, metric: usize, current_temp: f64, acceptance_rate: f64) -> f64;
+    /// Select the metric on which the cooler works, i.e. the elapsed steps or the elapsed seconds
+    fn metric(&self, elapsed_steps: usize, elapsed_time: usize) -> usize;
+    /// Check if the budget of the cooler (steps or seconds) has been consumed
+    fn is_expired(&self, elapsed_steps: usize, elapsed_time: usize) -> bool;
+    /// Fraction of the budget of the cooler consumed so far
+    fn progress(&self, elapsed_steps: usize, elapsed_time: usize) -> f64;
+    /// Estimated seconds to the end of the budget, given the duration of a step in seconds
+    fn time_to_complete(&self, elapsed_steps: usize, elapsed_time: usize, step_time: f64) -> f64;
+}
+
+
+/// Build the cooler that fits the budget given by the user: if a maximum time has been set,
+/// the temperature will decrease according to the wall-clock time, otherwise to the steps.
+pub fn new_cooler(tuner_params: &TunerParameter) -> Arc<Cooler> {
+    match tuner_params.max_time {
+        Some(max_time) => Arc::new(TimeCooler {
+            max_time: max_time,
+            min_temp: tuner_params.min_temp.unwrap(),
+            max_temp: tuner_params.max_temp.unwrap(),
+        }),
+        None => Arc::new(StepsCooler {
+            max_steps: tuner_params.max_step,
+            min_temp: tuner_params.min_temp.unwrap(),
+            max_temp: tuner_params.max_temp.unwrap(),
+        }),
+    }
 }
 
 
+/// Modified Lam schedule: the temperature is raised or lowered so that the observed acceptance
+/// rate follows a target rate. The target starts close to 100%, stays at 44% in the central part of
+/// the annealing and then drops towards zero. The progress is the fraction of the budget consumed.
+fn lam_temperature(
+    progress: f64,
+    current_temp: f64,
+    acceptance_rate: f64,
+    min_temp: f64,
+    max_temp: f64,
+) -> f64 {
+    let target_rate = if progress < 0.15 {
+        0.44 + 0.56 * 560.0f64.powf(-progress / 0.15)
+    } else if progress < 0.65 {
+        0.44
+    } else {
+        0.44 * 440.0f64.powf(-(progress - 0.65) / 0.35)
+    };
+
+    let new_temp = if acceptance_rate > target_rate {
+        current_temp * 0.999
+    } else {
+        current_temp / 0.999
+    };
+
+    return new_temp.max(min_temp).min(max_temp);
+}
+
 
 impl Cooler for TimeCooler {
     fn exponential_cooling(&self, elapsed_time: usize) -> f64 {
-        return 0.0;
+        if self.min_temp <= 0.0 {
+            panic!("Exponential cooling requires a minimum temperature greater than zero");
+        }
+
+        let reduction_factor = -(self.max_temp / self.min_temp).ln();
+
+        return self.max_temp *
+               (reduction_factor * (elapsed_time as f64) / (self.max_time as f64)).exp();
     }
 
     fn linear_cooling(&self, elapsed_time: usize) -> f64 {
-        return 0.0;
+        if self.min_temp <= 0.0 {
+            panic!("Linear cooling requires a minimum temperature greater than zero");
+        }
+
+        let reduction_factor = -(self.max_temp / self.min_temp).ln();
+        return self.max_temp *
+               (1.0 / 1.0 +
+                    (0.7 * (reduction_factor * (elapsed_time as f64) / (self.max_time as f64))));
 
     }
 
     fn basic_exp_cooling(&self, current_temp: f64) -> f64 {
-        return 0.0;
+        if self.min_temp <= 0.0 {
+            panic!("The minimum temperature must be greater than zero");
+        }
+
+        if current_temp <= self.min_temp {
+            return self.min_temp;
+        } else {
+            return current_temp * 0.999;
+        }
+    }
+
+    fn lam_cooling(&self, elapsed_time: usize, current_temp: f64, acceptance_rate: f64) -> f64 {
+        if self.min_temp <= 0.0 {
+            panic!("The minimum temperature must be greater than zero");
+        }
+
+        let progress = elapsed_time as f64 / self.max_time as f64;
+        return lam_temperature(
+            progress,
+            current_temp,
+            acceptance_rate,
+            self.min_temp,
+            self.max_temp,
+        );
+    }
+
+    fn metric(&self, elapsed_steps: usize, elapsed_time: usize) -> usize {
+        return elapsed_time;
+    }
+
+    fn is_expired(&self, elapsed_steps: usize, elapsed_time: usize) -> bool {
+        return elapsed_time >= self.max_time;
+    }
+
+    fn progress(&self, elapsed_steps: usize, elapsed_time: usize) -> f64 {
+        return elapsed_time as f64 / self.max_time as f64;
+    }
+
+    fn time_to_complete(&self, elapsed_steps: usize, elapsed_time: usize, step_time: f64) -> f64 {
+        return self.max_time.saturating_sub(elapsed_time) as f64;
     }
 }
 
@@ -86,4 +197,35 @@ impl Cooler for StepsCooler {
             return current_temp * 0.999;
         }
     }
+
+    fn lam_cooling(&self, step: usize, current_temp: f64, acceptance_rate: f64) -> f64 {
+        if self.min_temp <= 0.0 {
+            panic!("The minimum temperature must be greater than zero");
+        }
+
+        let progress = step as f64 / self.max_steps as f64;
+        return lam_temperature(
+            progress,
+            current_temp,
+            acceptance_rate,
+            self.min_temp,
+            self.max_temp,
+        );
+    }
+
+    fn metric(&self, elapsed_steps: usize, elapsed_time: usize) -> usize {
+        return elapsed_steps;
+    }
+
+    fn is_expired(&self, elapsed_steps: usize, elapsed_time: usize) -> bool {
+        return elapsed_steps >= self.max_steps;
+    }
+
+    fn progress(&self, elapsed_steps: usize, elapsed_time: usize) -> f64 {
+        return elapsed_steps as f64 / self.max_steps as f64;
+    }
+
+    fn time_to_complete(&self, elapsed_steps: usize, elapsed_time: usize, step_time: f64) -> f64 {
+        return self.max_steps.saturating_sub(elapsed_steps) as f64 * step_time;
+    }
 }
@@ -1,12 +1,32 @@
 pub mod problem;
 pub mod solver;
 pub mod cooler;
+pub mod refine;
+pub mod validate;
 
 use annealing::problem::Problem;
 use ansi_term::Colour::Green;
 use shared::TunerParameter;
 
 
+/// Temperatures of the solvers that are not annealings (bo, random, grid and hyperband), which
+/// only use them to track their budget: Tmin and Tmax are not evaluated, they default to 1 when
+/// not given by the user
+pub fn default_temperature(tuner_params: &mut TunerParameter) {
+    let min_temp = match tuner_params.min_temp {
+        Some(val) => val,
+        None => 1.0,
+    };
+    let max_temp = match tuner_params.max_temp {
+        Some(val) => val,
+        None => min_temp,
+    };
+
+    tuner_params.min_temp = Some(min_temp);
+    tuner_params.max_temp = Some(max_temp);
+}
+
+
 /// Check if the temperature is given by the user or if Tmin and Tmax need to be evaluated
 pub fn eval_temperature(tuner_params: &mut TunerParameter, problem: &mut Problem) {
     let num_exec = 10;
@@ -18,16 +18,21 @@
 
 use states_gen;
 use energy_eval;
+use energy_eval::cache::EvalCache;
+use energy_eval::Distribution;
 use EnergyType;
+use CachePolicy;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use rustc_serialize::Encodable;
 use State;
 use rand;
 use rand::{Rng, thread_rng};
+use shared::TunerRng;
 use std::f64::consts;
 use rand::distributions::Range;
 use rand::distributions::IndependentSample;
+use ansi_term::Colour::Green;
 
 
 /***
@@ -39,6 +44,8 @@ use rand::distributions::IndependentSample;
 pub struct Problem {
     pub params_configurator: states_gen::ParamsConfigurator,
     pub energy_evaluator: energy_eval::EnergyEval,
+    pub eval_cache: EvalCache,
+    pub cache_policy: CachePolicy,
 }
 
 
@@ -51,6 +58,15 @@ impl Problem {
     }
 
 
+    /***
+	Return the configurations one step away from a specific state given in input, i.e., a single
+	parameter moved to the adjacent value of its grid
+	***/
+    pub fn step_neighbours(&self, state: &State) -> Vec<State> {
+        return self.params_configurator.get_neigh_steps(state);
+    }
+
+
     /***
 	Start Extraction of Initial State: it takes the Parameters Configuration 
     given in input
@@ -62,30 +78,249 @@ impl Problem {
 
     /***
 	Start Energy Evaluation: it starts the execution of the benchmark for the 
-    specific parameter configuration and evaluate the performance result
+    specific parameter configuration and evaluate the performance result. With the reuse
+    cache policy, a configuration already measured is not executed again, unless it only has
+    the brief measurements of the racing evaluation (less than num_iter runs). With the replay
+    cache policy, the benchmark is never executed: the search is driven by the cache only.
 	***/
     pub fn energy(&mut self, state: &State, id_thread: usize) -> Option<f64> {
+        return self.evaluate(state, id_thread).map(|d| d.mean);
+    }
 
-        return self.energy_evaluator.execute_test_instance(
-            state,
+
+    /***
+	Distribution of the energy over the repetitions of the benchmark (mean, variance and
+	number of repetitions), with the same cache policy of the energy
+	***/
+    pub fn evaluate(&mut self, state: &State, id_thread: usize) -> Option<Distribution> {
+
+        self.eval_cache.visit(state);
+
+        let min_runs = self.energy_evaluator.min_repetitions();
+        if let Some(dist) = self.eval_cache.serve(state, &self.cache_policy, min_runs) {
+            println!(
+                "{} TID [{}] - Cached Evaluation of: {:?} - Energy: {:?}",
+                Green.paint("====>"),
+                id_thread,
+                state,
+                dist.as_ref().map(|d| d.mean)
+            );
+            return dist;
+        }
+
+        if let CachePolicy::replay = self.cache_policy {
+            println!(
+                "{} TID [{}] - No Cached Evaluation of: {:?} - Skip!",
+                Green.paint("====>"),
+                id_thread,
+                state
+            );
+            return None;
+        }
+
+        return self.measure(state, id_thread);
+    }
+
+
+    /***
+	Execute the benchmark for the configuration even if it has already been measured.
+    The new measurement is added to the evaluation cache.
+	***/
+    pub fn measure(&mut self, state: &State, id_thread: usize) -> Option<Distribution> {
+
+        let measurement = self.energy_evaluator.execute_test_instance(
+            &self.params_configurator.active_state(state),
+            id_thread,
+        );
+        self.eval_cache.record(state, measurement.clone());
+
+        return measurement.distribution();
+    }
+
+
+    /***
+	Brief measurement of the configuration (num_runs repetitions of the benchmark), which is
+	added to the evaluation cache. It returns the distribution of the energy over all the
+	cached measurements, so that successive brief measurements accumulate. With the replay cache
+	policy, the benchmark is not executed and only the cached measurements are used.
+	***/
+    pub fn measure_runs(&mut self, state: &State, id_thread: usize, num_runs: u8) -> Option<Distribution> {
+
+        self.eval_cache.visit(state);
+
+        if let CachePolicy::replay = self.cache_policy {
+            return self.distribution(state);
+        }
+
+        let measurement = self.energy_evaluator.execute_brief_instance(
+            &self.params_configurator.active_state(state),
+            id_thread,
+            num_runs,
+        );
+        self.eval_cache.record(state, measurement.clone());
+
+        if measurement.valid {
+            return self.distribution(state);
+        } else {
+            return None;
+        }
+    }
+
+
+    /***
+	Measurement of the configuration at a fidelity (fraction of the full duration of the benchmark).
+	A full-fidelity measurement is an ordinary evaluation. The shorter ones are not added to the
+	evaluation cache, which only holds full-length runs: with the replay cache policy, they are
+	approximated by the cached distribution.
+	***/
+    pub fn measure_fidelity(&mut self, state: &State, id_thread: usize, fidelity: f64) -> Option<Distribution> {
+
+        if fidelity >= 1.0 {
+            return self.evaluate(state, id_thread);
+        }
+
+        if let CachePolicy::replay = self.cache_policy {
+            return self.distribution(state);
+        }
+
+        return self.energy_evaluator
+            .execute_fidelity_instance(
+                &self.params_configurator.active_state(state),
+                id_thread,
+                fidelity,
+            )
+            .distribution();
+    }
+
+
+    /***
+	Single execution of the benchmark for the configuration, regardless of the cache policy and of
+	the repetition mode. The measurement is added to the evaluation cache, but the energy returned
+	is the one of this run only.
+	***/
+    pub fn measure_once(&mut self, state: &State, id_thread: usize) -> Option<f64> {
+
+        let measurement = self.energy_evaluator.execute_brief_instance(
+            &self.params_configurator.active_state(state),
             id_thread,
+            1,
         );
+        self.eval_cache.record(state, measurement.clone());
+
+        return measurement.energy();
+    }
 
 
+    /***
+	Configurations evaluated during the current tuning run
+	***/
+    pub fn visited_states(&self) -> Vec<State> {
+        return self.eval_cache.visited();
     }
 
 
     /***
-	Start Extraction of New Neighborhood State 
+	Number of repetitions of the benchmark of the last measurement of a configuration
 	***/
-    pub fn new_state(&mut self, state: &State, max_steps: usize, current_step: usize) -> State {
+    pub fn repetitions(&self, state: &State) -> usize {
+        return self.eval_cache
+            .lookup(state)
+            .and_then(|m| m.last().map(|m| m.values.len()))
+            .unwrap_or(0);
+    }
+
+
+    /***
+	Distribution of the energy of a configuration over all its cached measurements, without
+	executing the benchmark
+	***/
+    pub fn distribution(&self, state: &State) -> Option<Distribution> {
+        return self.eval_cache.cached_distribution(state).and_then(|d| d);
+    }
+
+
+    /***
+	Multi-objective counterpart of the energy: it evaluates the value of each objective
+	for the state, with the same cache policy.
+	***/
+    pub fn objectives(
+        &mut self,
+        state: &State,
+        objectives: &Vec<EnergyType>,
+        id_thread: usize,
+    ) -> Option<Vec<f64>> {
+
+        match self.cache_policy {
+            CachePolicy::reuse | CachePolicy::replay => {
+                if let Some(objs) = self.eval_cache.cached_objectives(state, objectives) {
+                    println!(
+                        "{} TID [{}] - Cached Evaluation of: {:?} - Objectives: {:?}",
+                        Green.paint("====>"),
+                        id_thread,
+                        state,
+                        objs
+                    );
+                    return objs;
+                }
+            }
+            CachePolicy::remeasure => {}
+        }
+
+        if let CachePolicy::replay = self.cache_policy {
+            println!(
+                "{} TID [{}] - No Cached Evaluation of: {:?} - Skip!",
+                Green.paint("====>"),
+                id_thread,
+                state
+            );
+            return None;
+        }
+
+        let measurement = self.energy_evaluator.execute_test_instance(
+            &self.params_configurator.active_state(state),
+            id_thread,
+        );
+        self.eval_cache.record(state, measurement.clone());
+
+        return measurement.objectives(objectives);
+    }
+
+
+    /***
+	Start Extraction of New Neighborhood State. The relative temperature (the current temperature
+	over the initial one) drives the width of the gaussian moves.
+	***/
+    pub fn new_state(
+        &mut self,
+        state: &State,
+        max_steps: usize,
+        current_step: usize,
+        relative_temp: f64,
+    ) -> State {
         return self.params_configurator.get_neighborhood(
             state,
             max_steps,
             current_step,
+            relative_temp,
         );
     }
 
+    /***
+	Check whether a state satisfies the constraints on the parameters
+	***/
+    pub fn is_valid(&self, state: &State) -> bool {
+        return self.params_configurator.is_valid(state);
+    }
+
+    /***
+	Canonical form of a state, in which the parameters that are inactive (their activation
+	condition does not hold) take their default value. Only the active parameters are sent to
+	the target agent.
+	***/
+    pub fn canonical(&self, state: &State) -> State {
+        return self.params_configurator.canonical(state);
+    }
+
     /***
 	Return a random state
 	***/
@@ -95,6 +330,46 @@ impl Problem {
 
 
 
+    /***
+	Random number generator of the problem. Each worker working on its own copy of the problem
+	draws from its own stream, given by reseed: with a seed, the whole search is reproducible.
+	***/
+    pub fn rng(&mut self) -> &mut TunerRng {
+        return self.params_configurator.rng();
+    }
+
+    pub fn reseed(&mut self, stream: &[usize]) {
+        self.params_configurator.reseed(stream);
+    }
+
+    /***
+	Number of draws from the random number generator, saved in the checkpoints so that a resumed
+	run continues the stream of the interrupted one (see restore_rng)
+	***/
+    pub fn rng_draws(&mut self) -> u64 {
+        return self.params_configurator.rng().draws();
+    }
+
+    pub fn restore_rng(&mut self, draws: u64) {
+        self.params_configurator.rng().restore(draws);
+    }
+
+
+
+    /***
+	Return the number of configurations composing the space state and the configuration
+	at the given position of its enumeration
+	***/
+    pub fn space_size(&self) -> usize {
+        return self.params_configurator.get_space_size();
+    }
+
+    pub fn grid_state(&self, index: usize) -> State {
+        return self.params_configurator.get_grid_param(index);
+    }
+
+
+
     /***
 	Return random population
 	***/
@@ -0,0 +1,163 @@
+/// ///////////////////////////////////////////////////////////////////////////
+///  File: annealing/refine.rs
+/// ///////////////////////////////////////////////////////////////////////////
+///  Copyright 2017 Giovanni Mazzeo
+///
+///  Licensed under the Apache License, Version 2.0 (the "License");
+///  you may not use this file except in compliance with the License.
+///  You may obtain a copy of the License at
+///
+///      http://www.apache.org/licenses/LICENSE-2.0
+///
+///  Unless required by applicable law or agreed to in writing, software
+///  distributed under the License is distributed on an "AS IS" BASIS,
+///  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+///  See the License for the specific language governing permissions and
+///  limitations under the License.
+/// ///////////////////////////////////////////////////////////////////////////
+
+/// ****************************************************************************
+/// *****************************************************************************
+/// **
+/// Local Refinement of the Best Configuration
+/// *
+/// *****************************************************************************
+/// ****************************************************************************
+use annealing::problem::Problem;
+use annealing::solver::common;
+use annealing::solver::common::MrResult;
+use res_emitters::Emitter;
+
+use shared::TunerParameter;
+
+use time;
+use State;
+
+use ansi_term::Colour::Green;
+use std::collections::HashSet;
+
+
+/// Deterministic pattern search around the configuration found by the solver. At each round
+/// all the configurations one step away (a single parameter moved to the adjacent value of its
+/// grid) are evaluated, as many at a time as the number of workers, and the best improving one
+/// becomes the new center. The refinement stops when no single-parameter change improves the
+/// energy, according to the acceptance test of the tuner, or after refine_steps evaluations.
+pub fn refine(
+    tuner_params: &TunerParameter,
+    res_emitter: &mut Emitter,
+    problem: &mut Problem,
+    start: MrResult,
+    num_workers: usize,
+) -> MrResult {
+
+    println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+    println!(
+        "{} Refinement Phase: Pattern Search around the Best Configuration",
+        Green.paint("[TUNER]")
+    );
+    println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+    let start_time = time::precise_time_ns();
+    let acceptance = common::Acceptance::new(tuner_params);
+
+    let mut best = start;
+    let mut visited: HashSet<State> = HashSet::new();
+    visited.insert(best.state.clone());
+    let mut elapsed_steps = 0;
+
+    loop {
+        // The configurations already evaluated by the refinement are not evaluated again
+        let neighbours: Vec<State> = problem
+            .step_neighbours(&best.state)
+            .into_iter()
+            .filter(|state| visited.insert(state.clone()))
+            .collect();
+
+        let mut round_best: Option<(f64, MrResult)> = None;
+        let mut is_exhausted = false;
+        for batch in neighbours.chunks(num_workers.max(1)) {
+            if elapsed_steps >= tuner_params.refine_steps {
+                is_exhausted = true;
+                break;
+            }
+
+            let remaining = tuner_params.refine_steps - elapsed_steps;
+            let batch: Vec<State> = batch.iter().take(remaining).cloned().collect();
+
+            for (_, state, nrg) in common::evaluate_batch(tuner_params, problem, batch) {
+                elapsed_steps += 1;
+
+                let new_energy = match nrg {
+                    Some(nrg) => nrg,
+                    None => {
+                        println!(
+                            "{} The current configuration parameters cannot be evaluated. Skip!",
+                            Green.paint("[TUNER]")
+                        );
+                        continue;
+                    }
+                };
+
+                let de = acceptance.delta(problem, &state, new_energy, &best.state, best.energy);
+                let is_round_best = match round_best {
+                    Some((best_de, _)) => de > best_de,
+                    None => de > 0.0,
+                };
+                if is_round_best {
+                    round_best = Some((
+                        de,
+                        MrResult {
+                            energy: new_energy,
+                            state: state.clone(),
+                        },
+                    ));
+                }
+
+                let elapsed_time = (time::precise_time_ns() - start_time) as f64 /
+                    1000000000.0f64;
+                res_emitter.send_refinement(
+                    elapsed_time,
+                    new_energy,
+                    &state,
+                    problem.repetitions(&state),
+                    best.energy,
+                    &best.state,
+                    elapsed_steps,
+                );
+            }
+        }
+
+        match round_best {
+            Some((_, improved)) => {
+                best = improved;
+                println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+                println!(
+                    "{} Refinement Steps: {} - Improved State: {:?}",
+                    Green.paint("[TUNER]"),
+                    elapsed_steps,
+                    best.state
+                );
+                println!("{} Improved Energy: {:.4}", Green.paint("[TUNER]"), best.energy);
+                println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+                if is_exhausted {
+                    println!("{} Refinement Budget Exhausted!!!", Green.paint("[TUNER]"));
+                    break;
+                }
+            }
+            None if is_exhausted => {
+                println!("{} Refinement Budget Exhausted!!!", Green.paint("[TUNER]"));
+                break;
+            }
+            None => {
+                println!(
+                    "{} No single-parameter change improves the energy. Refinement completed!",
+                    Green.paint("[TUNER]")
+                );
+                break;
+            }
+        }
+    }
+
+    best
+}
@@ -0,0 +1,607 @@
+/// ///////////////////////////////////////////////////////////////////////////
+///  File: annealing/solver/bo.rs
+/// ///////////////////////////////////////////////////////////////////////////
+///  Copyright 2017 Giovanni Mazzeo
+///
+///  Licensed under the Apache License, Version 2.0 (the "License");
+///  you may not use this file except in compliance with the License.
+///  You may obtain a copy of the License at
+///
+///      http://www.apache.org/licenses/LICENSE-2.0
+///
+///  Unless required by applicable law or agreed to in writing, software
+///  distributed under the License is distributed on an "AS IS" BASIS,
+///  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+///  See the License for the specific language governing permissions and
+///  limitations under the License.
+/// ///////////////////////////////////////////////////////////////////////////
+
+/// ****************************************************************************
+/// *****************************************************************************
+/// **
+/// Bayesian Optimization (BO) with a Gaussian-Process surrogate
+/// *
+/// *****************************************************************************
+/// ****************************************************************************
+use annealing::solver::Solver;
+use annealing::problem::Problem;
+use annealing::cooler;
+use annealing::solver::common;
+use annealing::solver::common::MrResult;
+use annealing::solver::checkpoint::{Checkpointer, WorkerCheckpoint};
+use energy_eval::cache::EvalCache;
+use res_emitters::Emitter;
+use states_gen::ParamsConfigurator;
+
+use shared::{TunerParameter, Scale};
+
+use time;
+use EnergyType;
+use {State, ParamValue};
+
+use ansi_term::Colour::Green;
+use std::collections::HashSet;
+use std::f64::consts::PI;
+
+
+/// Number of random configurations (and as many neighbours of the best one) among which the
+/// acquisition function selects the next configurations to evaluate
+static num_candidates: usize = 500;
+
+/// Grids explored when fitting the hyper-parameters of the Gaussian process
+static length_scales: [f64; 6] = [0.05, 0.1, 0.2, 0.3, 0.5, 1.0];
+static noise_levels: [f64; 3] = [0.001, 0.01, 0.1];
+
+
+#[derive(Debug, Clone)]
+pub struct Bo {
+    pub tuner_params: TunerParameter,
+    pub res_emitter: Emitter,
+    pub checkpointer: Checkpointer,
+}
+
+impl Solver for Bo {
+    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+
+        println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+        println!(
+            "{} Initialization Phase: Evaluation of the Initial Design",
+            Green.paint("[TUNER]")
+        );
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+        let mut start_time = time::precise_time_ns();
+        let nrg_type = self.tuner_params.energy;
+        let encoder = SpaceEncoder::new(&problem.params_configurator);
+
+        // The temperature is not used by the optimization, but it keeps track of the budget
+        let mut temperature = common::Temperature::new(
+            self.tuner_params.max_temp.unwrap(),
+            cooler::new_cooler(&self.tuner_params),
+            self.tuner_params.cooling.clone(),
+        );
+
+        let mut history: Vec<MrResult> = Vec::new();
+        let mut evaluated: HashSet<String> = HashSet::new();
+        let mut best: Option<MrResult> = None;
+        let mut elapsed_steps = 0;
+
+        // The initial design is composed by the default configuration and by random ones
+        let mut initial_design: Vec<State> = Vec::new();
+
+        match self.checkpointer.resumed_worker(0) {
+            Some(ckpt) => {
+                temperature.restore(&ckpt.temperature);
+                start_time -= ckpt.temperature.elapsed_time_ns;
+                history = self.checkpointer.resumed_history();
+                elapsed_steps = ckpt.elapsed_steps;
+                best = Some(ckpt.best);
+                problem.restore_rng(ckpt.rng_draws);
+            }
+            None => {
+                initial_design.push(problem.initial_state());
+                for _ in 1..self.tuner_params.initial_samples {
+                    initial_design.push(problem.rand_state());
+                }
+            }
+        };
+        for res in history.iter() {
+            evaluated.insert(EvalCache::key(&res.state));
+        }
+        let mut last_checkpoint = elapsed_steps;
+
+
+        // *********************************************************************************************************
+        loop {
+
+            if elapsed_steps >= self.tuner_params.max_step {
+                break;
+            }
+
+            if temperature.is_expired(elapsed_steps) {
+                println!("{} Time Budget Exhausted!!!", Green.paint("[TUNER]"));
+                break;
+            }
+
+            // Propose as many configurations as the number of workers, so that all the agents
+            // are kept busy
+            let batch_size = num_workers.min(self.tuner_params.max_step - elapsed_steps);
+            let batch: Vec<State> = if !initial_design.is_empty() {
+                let len = initial_design.len();
+                initial_design.drain(..batch_size.min(len)).collect()
+            } else {
+                let best_state = best.clone().unwrap().state;
+                let candidates =
+                    self.get_candidates(problem, &best_state, &evaluated, elapsed_steps);
+                propose_batch(&encoder, &history, &candidates, nrg_type, batch_size)
+            };
+
+            if batch.is_empty() {
+                println!("{} Space State Completely Explored!!!", Green.paint("[TUNER]"));
+                break;
+            }
+
+            // Evaluate the batch in parallel, one configuration per agent
+            for (tid, state, nrg) in common::evaluate_batch(&self.tuner_params, problem, batch) {
+                evaluated.insert(EvalCache::key(&state));
+                elapsed_steps += 1;
+
+                match nrg {
+                    Some(new_energy) => {
+                        let res = MrResult {
+                            energy: new_energy,
+                            state: state.clone(),
+                        };
+                        best = match best {
+                            Some(b) => {
+                                if fitness(nrg_type, new_energy) > fitness(nrg_type, b.energy) {
+                                    Some(res.clone())
+                                } else {
+                                    Some(b)
+                                }
+                            }
+                            None => Some(res.clone()),
+                        };
+                        history.push(res);
+
+                        let best_c = best.clone().unwrap();
+                        let elapsed_time = (time::precise_time_ns() - start_time) as f64 /
+                            1000000000.0f64;
+                        self.res_emitter.send_update(
+                            0.0,
+                            elapsed_time,
+                            0.0,
+                            new_energy,
+                            &state,
+                            problem.repetitions(&state),
+                            best_c.energy,
+                            &best_c.state,
+                            elapsed_steps,
+                            tid,
+                        );
+                    }
+                    None => {
+                        println!(
+                            "{} The current configuration parameters cannot be evaluated. \
+                                  Skip!",
+                            Green.paint("[TUNER]")
+                        );
+                    }
+                }
+            }
+
+            let best_c = match best {
+                Some(ref b) => b.clone(),
+                None => panic!("The initial configuration does not allow to calculate the energy"),
+            };
+
+            let elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
+            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+            println!(
+                "{} Completed Steps: {:.2} - Percentage of Completion: {:.2}% - Elapsed \
+                      Time: {:.2} s",
+                Green.paint("[TUNER]"),
+                elapsed_steps,
+                (elapsed_steps as f64 / self.tuner_params.max_step as f64) * 100.0,
+                elapsed_time
+            );
+            println!(
+                "{} Evaluated Configurations: {:?} - Valid: {:?}",
+                Green.paint("[TUNER]"),
+                evaluated.len(),
+                history.len()
+            );
+            println!("{} Best State: {:?}", Green.paint("[TUNER]"), best_c.state);
+            println!("{} Best Energy: {:.4}", Green.paint("[TUNER]"), best_c.energy);
+            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+            if self.checkpointer.is_due(last_checkpoint, elapsed_steps) {
+                self.checkpointer.save_history(
+                    WorkerCheckpoint {
+                        current: None,
+                        best: best_c,
+                        elapsed_steps: elapsed_steps,
+                        accepted: 0,
+                        subsequent_rejected: 0,
+                        temperature: temperature.snapshot(),
+                        rng_draws: problem.rng_draws(),
+                    },
+                    history.clone(),
+                );
+                last_checkpoint = elapsed_steps;
+            }
+        }
+
+        match best {
+            Some(b) => b,
+            None => panic!("The initial configuration does not allow to calculate the energy"),
+        }
+    }
+}
+
+impl Bo {
+    /// Candidate configurations: random ones, to explore the whole space state, and neighbours
+    /// of the best configuration, to refine it. Configurations already evaluated are discarded.
+    fn get_candidates(
+        &self,
+        problem: &mut Problem,
+        best_state: &State,
+        evaluated: &HashSet<String>,
+        elapsed_steps: usize,
+    ) -> Vec<State> {
+        let mut keys: HashSet<String> = HashSet::new();
+        let mut candidates: Vec<State> = Vec::with_capacity(2 * num_candidates);
+
+        for i in 0..2 * num_candidates {
+            let state = if i % 2 == 0 {
+                problem.rand_state()
+            } else {
+                // There is no cooling: the gaussian moves shrink with the remaining budget
+                problem.new_state(
+                    best_state,
+                    self.tuner_params.max_step,
+                    elapsed_steps,
+                    1.0 - elapsed_steps as f64 / self.tuner_params.max_step as f64,
+                )
+            };
+
+            let key = EvalCache::key(&state);
+            if !evaluated.contains(&key) && !keys.contains(&key) {
+                keys.insert(key);
+                candidates.push(state);
+            }
+        }
+
+        candidates
+    }
+}
+
+
+/// Select batch_size candidates by maximizing the expected improvement. After each selection the
+/// surrogate is updated with its predicted mean ("kriging believer"), so that the following
+/// selections move away from the configurations already in the batch.
+fn propose_batch(
+    encoder: &SpaceEncoder,
+    history: &Vec<MrResult>,
+    candidates: &Vec<State>,
+    nrg_type: EnergyType,
+    batch_size: usize,
+) -> Vec<State> {
+
+    let mut x: Vec<Vec<f64>> = history.iter().map(|r| encoder.encode(&r.state)).collect();
+    let fit: Vec<f64> = history.iter().map(|r| fitness(nrg_type, r.energy)).collect();
+
+    // Normalize the observations to zero mean and unit variance
+    let mean = fit.iter().sum::<f64>() / fit.len() as f64;
+    let var = fit.iter().map(|f| (f - mean).powi(2)).sum::<f64>() / fit.len() as f64;
+    let std = if var > 0.0 { var.sqrt() } else { 1.0 };
+    let mut y: Vec<f64> = fit.iter().map(|f| (f - mean) / std).collect();
+
+    // Without a surrogate the candidates are proposed as they are, as in random search
+    let mut gp = match GaussianProcess::fit(x.clone(), y.clone()) {
+        Some(gp) => gp,
+        None => {
+            println!(
+                "{} The surrogate model cannot be fitted. Random candidates are proposed!",
+                Green.paint("[TUNER]")
+            );
+            return candidates.iter().take(batch_size).cloned().collect();
+        }
+    };
+    let y_best = y.iter().cloned().fold(::std::f64::NEG_INFINITY, f64::max);
+
+    let encoded: Vec<Vec<f64>> = candidates.iter().map(|s| encoder.encode(s)).collect();
+    let mut chosen: Vec<usize> = Vec::with_capacity(batch_size);
+
+    for _ in 0..batch_size {
+        let mut best_idx: Option<usize> = None;
+        let mut best_ei = ::std::f64::NEG_INFINITY;
+
+        for (idx, xc) in encoded.iter().enumerate() {
+            if chosen.contains(&idx) {
+                continue;
+            }
+            let (mu, sigma) = gp.predict(xc);
+            let ei = expected_improvement(mu, sigma, y_best);
+            if ei > best_ei {
+                best_ei = ei;
+                best_idx = Some(idx);
+            }
+        }
+
+        let idx = match best_idx {
+            Some(idx) => idx,
+            None => break,
+        };
+        chosen.push(idx);
+
+        let (mu, _) = gp.predict(&encoded[idx]);
+        x.push(encoded[idx].clone());
+        y.push(mu);
+        gp = match GaussianProcess::new(x.clone(), y.clone(), gp.length_scale, gp.noise) {
+            Some(new_gp) => new_gp,
+            None => break,
+        };
+    }
+
+    chosen.iter().map(|idx| candidates[*idx].clone()).collect()
+}
+
+
+/// Fitness to maximize: the throughput, or the opposite of the latency
+fn fitness(nrg_type: EnergyType, energy: f64) -> f64 {
+    match nrg_type {
+        EnergyType::throughput => energy,
+        EnergyType::latency => -energy,
+    }
+}
+
+
+/// *********************************************************************************************************
+
+/// Encoding of the configurations in the unit hypercube. Numeric parameters are scaled between
+/// their minimum and maximum (the logarithms of them for the log-scale ones), boolean parameters
+/// are mapped to 0 and 1, enum parameters are one-hot encoded.
+#[derive(Debug, Clone)]
+struct SpaceEncoder {
+    numeric_params: Vec<(String, f64, f64, bool)>,
+    bool_params: Vec<(String, ParamValue)>,
+    enum_params: Vec<(String, Vec<ParamValue>)>,
+}
+
+impl SpaceEncoder {
+    fn new(params_configurator: &ParamsConfigurator) -> Self {
+        let is_log = |scale: Scale| match scale {
+            Scale::Step(_) => false,
+            Scale::Percent(_) => true,
+        };
+        let numeric_params = params_configurator
+            .targ_int_params
+            .iter()
+            .map(|p| (p.name.clone(), p.min as f64, p.max as f64, is_log(p.scale)))
+            .chain(params_configurator.targ_float_params.iter().map(|p| {
+                (p.name.clone(), p.min, p.max, is_log(p.scale))
+            }))
+            .map(|(name, min, max, log)| if log {
+                (name, min.ln(), max.ln(), log)
+            } else {
+                (name, min, max, log)
+            })
+            .collect();
+        let bool_params = params_configurator
+            .targ_bool_params
+            .iter()
+            .map(|p| (p.name.clone(), ParamValue::Text(p.true_val.clone())))
+            .collect();
+        let enum_params = params_configurator
+            .targ_enum_params
+            .iter()
+            .map(|p| {
+                (
+                    p.name.clone(),
+                    p.values.iter().map(|val| ParamValue::Text(val.clone())).collect(),
+                )
+            })
+            .collect();
+
+        SpaceEncoder {
+            numeric_params: numeric_params,
+            bool_params: bool_params,
+            enum_params: enum_params,
+        }
+    }
+
+    fn encode(&self, state: &State) -> Vec<f64> {
+        let mut x = Vec::with_capacity(self.numeric_params.len() + self.bool_params.len());
+
+        for &(ref name, min, max, log) in self.numeric_params.iter() {
+            let mut val: f64 = state.get(name).unwrap().as_f64().unwrap();
+            if log {
+                val = val.ln();
+            }
+            if max > min {
+                x.push((val - min) / (max - min));
+            } else {
+                x.push(0.0);
+            }
+        }
+
+        for &(ref name, ref true_val) in self.bool_params.iter() {
+            if state.get(name).unwrap() == true_val {
+                x.push(1.0);
+            } else {
+                x.push(0.0);
+            }
+        }
+
+        for &(ref name, ref values) in self.enum_params.iter() {
+            let current_val = state.get(name).unwrap();
+            for val in values.iter() {
+                if current_val == val {
+                    x.push(1.0);
+                } else {
+                    x.push(0.0);
+                }
+            }
+        }
+
+        x
+    }
+}
+
+
+/// *********************************************************************************************************
+
+/// Gaussian process with a squared exponential kernel over normalized observations
+#[derive(Debug, Clone)]
+struct GaussianProcess {
+    x: Vec<Vec<f64>>,
+    length_scale: f64,
+    noise: f64,
+    chol: Vec<Vec<f64>>,
+    alpha: Vec<f64>,
+    log_likelihood: f64,
+}
+
+impl GaussianProcess {
+    /// Fit the process choosing the hyper-parameters that maximize the marginal likelihood. None
+    /// if the covariance matrix is not positive definite for any of them
+    fn fit(x: Vec<Vec<f64>>, y: Vec<f64>) -> Option<Self> {
+        let mut best: Option<GaussianProcess> = None;
+
+        for length_scale in length_scales.iter() {
+            for noise in noise_levels.iter() {
+                if let Some(gp) = GaussianProcess::new(x.clone(), y.clone(), *length_scale, *noise) {
+                    let better = match best {
+                        Some(ref b) => gp.log_likelihood > b.log_likelihood,
+                        None => true,
+                    };
+                    if better {
+                        best = Some(gp);
+                    }
+                }
+            }
+        }
+
+        best
+    }
+
+    fn new(x: Vec<Vec<f64>>, y: Vec<f64>, length_scale: f64, noise: f64) -> Option<Self> {
+        let n = x.len();
+        let mut k = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in 0..n {
+                k[i][j] = kernel(&x[i], &x[j], length_scale);
+            }
+            k[i][i] += noise;
+        }
+
+        let chol = match cholesky(&k) {
+            Some(l) => l,
+            None => return None,
+        };
+        let alpha = solve_upper(&chol, &solve_lower(&chol, &y));
+
+        let data_fit: f64 = y.iter().zip(alpha.iter()).map(|(a, b)| a * b).sum();
+        let log_det: f64 = (0..n).map(|i| chol[i][i].ln()).sum();
+        let log_likelihood = -0.5 * data_fit - log_det - 0.5 * n as f64 * (2.0 * PI).ln();
+
+        Some(GaussianProcess {
+            x: x,
+            length_scale: length_scale,
+            noise: noise,
+            chol: chol,
+            alpha: alpha,
+            log_likelihood: log_likelihood,
+        })
+    }
+
+    /// Mean and standard deviation of the prediction for the point given in input
+    fn predict(&self, xs: &Vec<f64>) -> (f64, f64) {
+        let ks: Vec<f64> = self.x
+            .iter()
+            .map(|xi| kernel(xi, xs, self.length_scale))
+            .collect();
+
+        let mu: f64 = ks.iter().zip(self.alpha.iter()).map(|(a, b)| a * b).sum();
+        let v = solve_lower(&self.chol, &ks);
+        let var = 1.0 - v.iter().map(|a| a * a).sum::<f64>();
+
+        (mu, var.max(1e-12).sqrt())
+    }
+}
+
+fn kernel(a: &Vec<f64>, b: &Vec<f64>, length_scale: f64) -> f64 {
+    let sq_dist: f64 = a.iter().zip(b.iter()).map(|(x, y)| (x - y).powi(2)).sum();
+    (-0.5 * sq_dist / (length_scale * length_scale)).exp()
+}
+
+/// Cholesky decomposition of a symmetric positive definite matrix (lower triangular factor)
+fn cholesky(m: &Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let n = m.len();
+    let mut l = vec![vec![0.0; n]; n];
+
+    for i in 0..n {
+        for j in 0..i + 1 {
+            let sum: f64 = (0..j).map(|k| l[i][k] * l[j][k]).sum();
+            if i == j {
+                let d = m[i][i] - sum;
+                if d <= 0.0 {
+                    return None;
+                }
+                l[i][j] = d.sqrt();
+            } else {
+                l[i][j] = (m[i][j] - sum) / l[j][j];
+            }
+        }
+    }
+
+    Some(l)
+}
+
+/// Solve L x = b, with L lower triangular
+fn solve_lower(l: &Vec<Vec<f64>>, b: &Vec<f64>) -> Vec<f64> {
+    let n = b.len();
+    let mut x = vec![0.0; n];
+    for i in 0..n {
+        let sum: f64 = (0..i).map(|k| l[i][k] * x[k]).sum();
+        x[i] = (b[i] - sum) / l[i][i];
+    }
+    x
+}
+
+/// Solve L^T x = b, with L lower triangular
+fn solve_upper(l: &Vec<Vec<f64>>, b: &Vec<f64>) -> Vec<f64> {
+    let n = b.len();
+    let mut x = vec![0.0; n];
+    for i in (0..n).rev() {
+        let sum: f64 = (i + 1..n).map(|k| l[k][i] * x[k]).sum();
+        x[i] = (b[i] - sum) / l[i][i];
+    }
+    x
+}
+
+fn expected_improvement(mu: f64, sigma: f64, y_best: f64) -> f64 {
+    let z = (mu - y_best) / sigma;
+    (mu - y_best) * norm_cdf(z) + sigma * norm_pdf(z)
+}
+
+fn norm_pdf(z: f64) -> f64 {
+    (-0.5 * z * z).exp() / (2.0 * PI).sqrt()
+}
+
+/// Cumulative distribution of the standard normal, by means of the Abramowitz-Stegun
+/// approximation of the error function
+fn norm_cdf(z: f64) -> f64 {
+    let x = z.abs() / 2.0f64.sqrt();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t *
+        (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
@@ -0,0 +1,243 @@
+/// ///////////////////////////////////////////////////////////////////////////
+///  File: annealing/solver/checkpoint.rs
+/// ///////////////////////////////////////////////////////////////////////////
+///  Copyright 2017 Giovanni Mazzeo
+///
+///  Licensed under the Apache License, Version 2.0 (the "License");
+///  you may not use this file except in compliance with the License.
+///  You may obtain a copy of the License at
+///
+///      http://www.apache.org/licenses/LICENSE-2.0
+///
+///  Unless required by applicable law or agreed to in writing, software
+///  distributed under the License is distributed on an "AS IS" BASIS,
+///  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+///  See the License for the specific language governing permissions and
+///  limitations under the License.
+/// ///////////////////////////////////////////////////////////////////////////
+
+/// ****************************************************************************
+/// *****************************************************************************
+/// **
+/// Checkpoint and Resume of the Solvers
+/// *
+/// *****************************************************************************
+/// ****************************************************************************
+use rustc_serialize::json;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use ansi_term::Colour::{Green, Red};
+use annealing::solver::common::MrResult;
+use shared::TunerParameter;
+use State;
+
+
+/// Snapshot of a common::Temperature
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct TemperatureState {
+    pub temp: f64,
+    pub elapsed_time_ns: u64,
+    pub acceptance_rate: f64,
+    pub last_accepted: usize,
+    pub last_steps: usize,
+    pub metric_offset: usize,
+}
+
+/// State of an annealing worker. The sequential and the master-driven solvers (seqsa, spisa
+/// and prsa) save a single worker, while mir saves one worker per thread.
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct WorkerCheckpoint {
+    pub current: Option<MrResult>,
+    pub best: MrResult,
+    pub elapsed_steps: usize,
+    pub accepted: usize,
+    pub subsequent_rejected: usize,
+    pub temperature: TemperatureState,
+    pub rng_draws: u64,
+}
+
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct Checkpoint {
+    pub version: String,
+    pub min_temp: f64,
+    pub max_temp: f64,
+    pub workers: Vec<Option<WorkerCheckpoint>>,
+    pub population: Vec<State>,
+    pub history: Vec<MrResult>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Checkpointer {
+    path: Option<String>,
+    period: usize,
+    resumed: Option<Checkpoint>,
+    last: Arc<Mutex<Checkpoint>>,
+}
+
+
+/// Load the checkpoint from which the tuning run has to be resumed. It returns None if the resume
+/// has not been requested or if no checkpoint has been written yet.
+pub fn load(tuner_params: &TunerParameter) -> Option<Checkpoint> {
+    if !tuner_params.resume {
+        return None;
+    }
+
+    let path = match tuner_params.checkpoint {
+        Some(ref path) => path.clone(),
+        None => panic!("The resume of the tuning run requires a checkpoint file"),
+    };
+
+    let mut content = String::new();
+    match File::open(&path) {
+        Ok(mut f) => {
+            f.read_to_string(&mut content).unwrap();
+        }
+        Err(_) => {
+            println!(
+                "{} Checkpoint {} not found. Start the tuning run from scratch",
+                Green.paint("[TUNER]"),
+                path
+            );
+            return None;
+        }
+    };
+
+    let checkpoint: Checkpoint = match json::decode(&content) {
+        Ok(ckpt) => ckpt,
+        Err(e) => panic!("The checkpoint {} cannot be decoded: {:?}", path, e),
+    };
+
+    if checkpoint.version != format!("{:?}", tuner_params.version) {
+        panic!(
+            "The checkpoint {} has been written by the {} solver",
+            path,
+            checkpoint.version
+        );
+    }
+
+    println!(
+        "{} Resume the tuning run from the checkpoint {}",
+        Green.paint("[TUNER]"),
+        path
+    );
+
+    Some(checkpoint)
+}
+
+
+impl Checkpointer {
+    pub fn new(tuner_params: &TunerParameter, resumed: Option<Checkpoint>) -> Self {
+        let last = match resumed {
+            Some(ref ckpt) => ckpt.clone(),
+            None => Checkpoint {
+                version: format!("{:?}", tuner_params.version),
+                min_temp: tuner_params.min_temp.unwrap(),
+                max_temp: tuner_params.max_temp.unwrap(),
+                workers: Vec::new(),
+                population: Vec::new(),
+                history: Vec::new(),
+            },
+        };
+
+        Checkpointer {
+            path: tuner_params.checkpoint.clone(),
+            period: tuner_params.checkpoint_period,
+            resumed: resumed,
+            last: Arc::new(Mutex::new(last)),
+        }
+    }
+
+    /// State of the worker tid at the time of the checkpoint from which the run has been resumed
+    pub fn resumed_worker(&self, tid: usize) -> Option<WorkerCheckpoint> {
+        match self.resumed {
+            Some(ref ckpt) => {
+                match ckpt.workers.get(tid) {
+                    Some(worker) => worker.clone(),
+                    None => None,
+                }
+            }
+            None => None,
+        }
+    }
+
+    pub fn resumed_population(&self) -> Option<Vec<State>> {
+        match self.resumed {
+            Some(ref ckpt) if !ckpt.population.is_empty() => Some(ckpt.population.clone()),
+            _ => None,
+        }
+    }
+
+    /// Configurations evaluated before the interruption, used by the model-based solvers
+    pub fn resumed_history(&self) -> Vec<MrResult> {
+        match self.resumed {
+            Some(ref ckpt) => ckpt.history.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Check if checkpoint_period steps have been executed since the last checkpoint
+    pub fn is_due(&self, last_checkpoint_step: usize, elapsed_steps: usize) -> bool {
+        self.path.is_some() && elapsed_steps >= last_checkpoint_step + self.period
+    }
+
+    /// Record the state of the worker tid (and the population, if any) and write the whole
+    /// checkpoint on disk.
+    pub fn save(&self, tid: usize, worker: WorkerCheckpoint, population: Vec<State>) {
+        if self.path.is_none() {
+            return;
+        }
+
+        let mut last = self.last.lock().unwrap();
+        while last.workers.len() <= tid {
+            last.workers.push(None);
+        }
+        last.workers[tid] = Some(worker);
+        if !population.is_empty() {
+            last.population = population;
+        }
+
+        self.write(&last);
+    }
+
+    /// Record the state of the solver together with all the configurations evaluated so far
+    /// and write the whole checkpoint on disk.
+    pub fn save_history(&self, worker: WorkerCheckpoint, history: Vec<MrResult>) {
+        if self.path.is_none() {
+            return;
+        }
+
+        let mut last = self.last.lock().unwrap();
+        if last.workers.is_empty() {
+            last.workers.push(None);
+        }
+        last.workers[0] = Some(worker);
+        last.history = history;
+
+        self.write(&last);
+    }
+
+    /// The file is replaced atomically, so that a crash during the write does not corrupt the
+    /// previous checkpoint.
+    fn write(&self, checkpoint: &Checkpoint) {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return,
+        };
+
+        let encoded = json::encode(checkpoint).unwrap();
+        let tmp_path = format!("{}.tmp", path);
+        let res = File::create(&tmp_path)
+            .and_then(|mut f| f.write_all(encoded.as_bytes()))
+            .and_then(|_| fs::rename(&tmp_path, &path));
+
+        if let Err(e) = res {
+            println!(
+                "{} The checkpoint {} cannot be written: {}",
+                Red.paint("[TUNER]"),
+                path,
+                e
+            );
+        }
+    }
+}
@@ -21,11 +21,24 @@ use rand;
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
 use CoolingSchedule;
+use StagnationPolicy;
+use AcceptanceTest;
+use EvaluationMode;
+use EnergyType;
+use shared::TunerParameter;
+use annealing::problem::Problem;
+use energy_eval::{self, Distribution};
+use energy_eval::cache::EvalCache;
+use res_emitters::Emitter;
+use ansi_term::Colour::Green;
 use rand::{thread_rng, Rng};
-use annealing::cooler::{Cooler, StepsCooler, TimeCooler};
+use annealing::cooler::Cooler;
+use annealing::solver::checkpoint::TemperatureState;
+use time;
+use std::thread;
 use State;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
 pub struct MrResult {
     pub energy: f64,
     pub state: State,
@@ -36,6 +49,7 @@ pub struct MrResult {
 pub struct IntermediateResults {
     pub last_nrg: f64,
     pub last_state: State,
+    pub last_reps: usize,
     pub best_nrg: f64,
     pub best_state: State,
     pub tid: usize,
@@ -55,13 +69,62 @@ pub struct SharedGenericCounter(Arc<Mutex<usize>>);
 #[derive(Debug, Clone)]
 pub struct Temperature {
     temp: Arc<Mutex<f64>>,
-    cooler: StepsCooler,
+    cooler: Arc<Cooler>,
     cooling_schedule: CoolingSchedule,
+    start_time: u64,
+    acceptance: Arc<Mutex<AcceptanceRate>>,
+    metric_offset: Arc<Mutex<usize>>,
+}
+
+/// Moving average of the acceptance rate, evaluated from the counters of accepted solutions
+/// and elapsed steps kept by the solvers
+#[derive(Debug, Clone)]
+struct AcceptanceRate {
+    rate: f64,
+    last_accepted: usize,
+    last_steps: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct ThreadsResults(Arc<Mutex<Vec<MrResult>>>);
 
+/// Configuration evaluated by the multi-objective solvers, with the value of each objective
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct ParetoPoint {
+    pub objectives: Vec<f64>,
+    pub state: State,
+}
+
+/// Archive of the non-dominated configurations found so far, shared among the workers
+#[derive(Debug, Clone)]
+pub struct ParetoArchive(Arc<Mutex<Vec<ParetoPoint>>>);
+
+/// Reaction of the solvers when too many subsequent solutions have been rejected
+#[derive(Debug, Clone)]
+pub struct Stagnation {
+    pub policy: StagnationPolicy,
+    pub max_rejected: usize,
+    pub reheat_temp: f64,
+}
+
+/// Solution from which the annealing continues after a stagnation
+#[derive(Debug, Clone)]
+pub enum Restart {
+    /// An already evaluated solution (the current or the best one)
+    Evaluated(MrResult),
+    /// A configuration to be evaluated first, as a candidate of the solver
+    Candidate(State),
+}
+
+/// Comparison of a new configuration with the incumbent one, which takes into account the noise
+/// of their measurements
+#[derive(Debug, Clone)]
+pub struct Acceptance {
+    pub test: AcceptanceTest,
+    pub confidence: f64,
+    pub energy: EnergyType,
+}
+
 /// *********************************************************************************************************
 
 impl StatesPool {
@@ -83,18 +146,21 @@ impl StatesPool {
         (*pool).pop()
     }
 
-    pub fn shuffle(&self) {
+    /// The pool is sorted before being shuffled, so that the result does not depend on the order
+    /// in which the workers pushed their states
+    pub fn shuffle<R: Rng>(&self, rng: &mut R) {
         let mut pool = self.0.lock().unwrap();
-        rand::thread_rng().shuffle(&mut pool);
+        pool.sort_by_key(|state| EvalCache::key(state));
+        rng.shuffle(&mut pool);
     }
 
-    pub fn remove_one(&self) -> Option<State> {
+    pub fn remove_one<R: Rng>(&self, rng: &mut R) -> Option<State> {
         let mut pool = self.0.lock().unwrap();
         if pool.len() == 0 {
             return None;
         } else {
             let len = pool.len();
-            return Some(pool.swap_remove(rand::thread_rng().gen_range(0, len)));
+            return Some(pool.swap_remove(rng.gen_range(0, len)));
         }
     }
     pub fn size(&self) -> u64 {
@@ -106,6 +172,11 @@ impl StatesPool {
         let mut pool = self.0.lock().unwrap();
         (*pool).append(&mut v_1)
     }
+
+    pub fn get_coll(&self) -> Vec<State> {
+        let pool = self.0.lock().unwrap();
+        (*pool).clone()
+    }
 }
 
 /// *********************************************************************************************************
@@ -115,14 +186,14 @@ impl NeighborhoodsPool {
         NeighborhoodsPool(Arc::new(Mutex::new(neighs)))
     }
 
-    pub fn remove_one(&self) -> Option<State> {
+    pub fn remove_one<R: Rng>(&self, rng: &mut R) -> Option<State> {
         let mut neighs = self.0.lock().unwrap();
 
         if neighs.len() == 0 {
             return None;
         } else {
             let len = neighs.len();
-            return Some(neighs.swap_remove(rand::thread_rng().gen_range(0, len)));
+            return Some(neighs.swap_remove(rng.gen_range(0, len)));
         }
     }
     pub fn size(&self) -> u64 {
@@ -138,6 +209,10 @@ impl SharedGenericCounter {
     pub fn new() -> Self {
         SharedGenericCounter(Arc::new(Mutex::new(0)))
     }
+
+    pub fn new_with_val(val: usize) -> Self {
+        SharedGenericCounter(Arc::new(Mutex::new(val)))
+    }
     pub fn increment(&self) {
         let mut rejected = self.0.lock().unwrap();
         *rejected = *rejected + 1;
@@ -160,28 +235,142 @@ impl SharedGenericCounter {
 /// *********************************************************************************************************
 
 impl Temperature {
-    pub fn new(start_temp: f64, c: StepsCooler, cs: CoolingSchedule) -> Self {
+    pub fn new(start_temp: f64, c: Arc<Cooler>, cs: CoolingSchedule) -> Self {
         Temperature {
             temp: Arc::new(Mutex::new(start_temp)),
             cooler: c,
             cooling_schedule: cs,
+            start_time: time::precise_time_ns(),
+            acceptance: Arc::new(Mutex::new(AcceptanceRate {
+                rate: 0.5,
+                last_accepted: 0,
+                last_steps: 0,
+            })),
+            metric_offset: Arc::new(Mutex::new(0)),
         }
     }
 
-    pub fn update(&self, elapsed_steps: usize) {
+    pub fn update(&self, elapsed_steps: usize, accepted: usize) {
         let mut temperature = self.temp.lock().unwrap();
+        let metric = self.cooler
+            .metric(elapsed_steps, self.elapsed_time())
+            .saturating_sub(*self.metric_offset.lock().unwrap());
+        let acceptance_rate = self.update_acceptance_rate(elapsed_steps, accepted);
 
         *temperature = match self.cooling_schedule {
-            CoolingSchedule::linear => self.cooler.linear_cooling(elapsed_steps),
-            CoolingSchedule::exponential => self.cooler.exponential_cooling(elapsed_steps),
+            CoolingSchedule::linear => self.cooler.linear_cooling(metric),
+            CoolingSchedule::exponential => self.cooler.exponential_cooling(metric),
             CoolingSchedule::basic_exp_cooling => self.cooler.basic_exp_cooling(*temperature),
+            CoolingSchedule::lam => {
+                self.cooler.lam_cooling(metric, *temperature, acceptance_rate)
+            }
         };
     }
 
+    /// Fold the solutions accepted since the last update into the moving average of the
+    /// acceptance rate (the window is of about 500 steps)
+    fn update_acceptance_rate(&self, elapsed_steps: usize, accepted: usize) -> f64 {
+        let mut acceptance = self.acceptance.lock().unwrap();
+
+        if elapsed_steps > acceptance.last_steps {
+            let new_steps = elapsed_steps - acceptance.last_steps;
+            let new_accepted = accepted.saturating_sub(acceptance.last_accepted);
+            let observed_rate = (new_accepted as f64 / new_steps as f64).min(1.0);
+
+            acceptance.rate = (499.0 * acceptance.rate + observed_rate) / 500.0;
+            acceptance.last_steps = elapsed_steps;
+            acceptance.last_accepted = accepted;
+        }
+
+        acceptance.rate
+    }
+
     pub fn get(&self) -> f64 {
         let temperature = self.temp.lock().unwrap();
         *temperature
     }
+
+    /// Raise the temperature to the value given in input. The linear and exponential schedules
+    /// are moved back to the point in which they reach such a temperature, so that the cooling
+    /// restarts from there.
+    pub fn reheat(&self, elapsed_steps: usize, new_temp: f64) {
+        let mut temperature = self.temp.lock().unwrap();
+        let mut metric_offset = self.metric_offset.lock().unwrap();
+        let metric = self.cooler.metric(elapsed_steps, self.elapsed_time());
+
+        let schedule_temp = |m: usize| match self.cooling_schedule {
+            CoolingSchedule::linear => Some(self.cooler.linear_cooling(m)),
+            CoolingSchedule::exponential => Some(self.cooler.exponential_cooling(m)),
+            _ => None,
+        };
+
+        if schedule_temp(0).is_some() {
+            // Binary search of the first metric value at which the schedule gives new_temp
+            let (mut low, mut high) = (0, metric);
+            while low < high {
+                let mid = (low + high) / 2;
+                if schedule_temp(mid).unwrap() > new_temp {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            *metric_offset = metric - low;
+        }
+
+        *temperature = new_temp;
+    }
+
+    /// Check if the budget (steps or wall-clock time) given to the annealing has been consumed
+    pub fn is_expired(&self, elapsed_steps: usize) -> bool {
+        self.cooler.is_expired(elapsed_steps, self.elapsed_time())
+    }
+
+    /// Percentage of the budget (steps or wall-clock time) consumed so far
+    pub fn progress(&self, elapsed_steps: usize) -> f64 {
+        self.cooler.progress(elapsed_steps, self.elapsed_time()) * 100.0
+    }
+
+    /// Estimated seconds to the end of the budget (steps or wall-clock time), given the duration
+    /// of a step in seconds
+    pub fn time_to_complete(&self, elapsed_steps: usize, step_time: f64) -> f64 {
+        self.cooler.time_to_complete(elapsed_steps, self.elapsed_time(), step_time)
+    }
+
+    /// Snapshot of the temperature, of the acceptance rate and of the elapsed time, written
+    /// into the checkpoints of the solvers
+    pub fn snapshot(&self) -> TemperatureState {
+        let temperature = self.temp.lock().unwrap();
+        let acceptance = self.acceptance.lock().unwrap();
+        let metric_offset = self.metric_offset.lock().unwrap();
+
+        TemperatureState {
+            temp: *temperature,
+            elapsed_time_ns: time::precise_time_ns() - self.start_time,
+            acceptance_rate: acceptance.rate,
+            last_accepted: acceptance.last_accepted,
+            last_steps: acceptance.last_steps,
+            metric_offset: *metric_offset,
+        }
+    }
+
+    /// Restore a snapshot taken by a previous run. The start time is moved back by the time
+    /// elapsed in that run, so that time-based schedules and budgets continue from there.
+    pub fn restore(&mut self, saved: &TemperatureState) {
+        *self.temp.lock().unwrap() = saved.temp;
+        *self.metric_offset.lock().unwrap() = saved.metric_offset;
+        *self.acceptance.lock().unwrap() = AcceptanceRate {
+            rate: saved.acceptance_rate,
+            last_accepted: saved.last_accepted,
+            last_steps: saved.last_steps,
+        };
+        self.start_time = time::precise_time_ns() - saved.elapsed_time_ns;
+    }
+
+    /// Seconds elapsed since the creation of the temperature
+    fn elapsed_time(&self) -> usize {
+        ((time::precise_time_ns() - self.start_time) / 1000000000) as usize
+    }
 }
 
 /// *********************************************************************************************************
@@ -202,3 +391,348 @@ impl ThreadsResults {
         r
     }
 }
+
+/// *********************************************************************************************************
+
+/// Evaluate a batch of configurations in parallel, one per agent. The results are returned in
+/// the order of the batch, together with the tid of the worker that evaluated them. With the
+/// racing evaluation the batch is raced (see race_batch).
+pub fn evaluate_batch(
+    tuner_params: &TunerParameter,
+    problem: &Problem,
+    batch: Vec<State>,
+) -> Vec<(usize, State, Option<f64>)> {
+    if let EvaluationMode::racing = tuner_params.evaluation {
+        if batch.len() > 1 {
+            return race_batch(tuner_params, problem, batch);
+        }
+    }
+
+    let handles: Vec<_> = batch
+        .into_iter()
+        .enumerate()
+        .map(|(tid, state)| {
+            let mut problem_c = problem.clone();
+            thread::spawn(move || {
+                let nrg = problem_c.energy(&state, tid);
+                (tid, state, nrg)
+            })
+        })
+        .collect();
+
+    handles.into_iter().map(|h| h.join().unwrap()).collect()
+}
+
+/// Racing evaluation of a batch of configurations (successive halving). At first every
+/// configuration is run once. Then, at each round, the configurations statistically worse than
+/// the leading one are dropped, at most half of them survive and the survivors are run twice as
+/// many times as in the previous round, until a single one is left or the survivors reached
+/// max_iter runs. The energy of a configuration is the average of all its runs, so that the
+/// dropped ones are only roughly evaluated.
+pub fn race_batch(
+    tuner_params: &TunerParameter,
+    problem: &Problem,
+    batch: Vec<State>,
+) -> Vec<(usize, State, Option<f64>)> {
+    let max_runs = (tuner_params.max_iter as usize).max(1);
+    let fitness = |nrg: f64| match tuner_params.energy {
+        EnergyType::throughput => nrg,
+        EnergyType::latency => -nrg,
+    };
+
+    let mut dists: Vec<Option<Distribution>> = vec![None; batch.len()];
+    let mut runs_done = 0;
+    let mut num_runs = 1;
+    let mut alive: Vec<usize> = (0..batch.len()).collect();
+
+    loop {
+        println!(
+            "{} Racing Round: {} Configurations - {} Runs each",
+            Green.paint("[TUNER]"),
+            alive.len(),
+            num_runs
+        );
+
+        let handles: Vec<_> = alive
+            .iter()
+            .enumerate()
+            .map(|(tid, &i)| {
+                let mut problem_c = problem.clone();
+                let state = batch[i].clone();
+                thread::spawn(move || {
+                    (i, problem_c.measure_runs(&state, tid, num_runs as u8))
+                })
+            })
+            .collect();
+        for h in handles {
+            let (i, dist) = h.join().unwrap();
+            dists[i] = dist;
+        }
+        runs_done += num_runs;
+
+        // A configuration that cannot be evaluated, or whose mean energy is not finite, is out
+        // of the race
+        for &i in alive.iter() {
+            if dists[i].as_ref().map_or(false, |d| !d.mean.is_finite()) {
+                dists[i] = None;
+            }
+        }
+        alive.retain(|&i| dists[i].is_some());
+        if alive.len() <= 1 || runs_done >= max_runs {
+            break;
+        }
+
+        alive.sort_by(|&a, &b| {
+            fitness(dists[b].as_ref().unwrap().mean)
+                .partial_cmp(&fitness(dists[a].as_ref().unwrap().mean))
+                .unwrap()
+        });
+
+        let leader = dists[alive[0]].clone().unwrap();
+        let survivors = (alive.len() + 1) / 2;
+        alive = alive
+            .into_iter()
+            .enumerate()
+            .filter(|&(rank, i)| {
+                if rank >= survivors {
+                    return false;
+                }
+                let dist = dists[i].as_ref().unwrap();
+                match energy_eval::welch(&leader, dist) {
+                    Some((se, df)) => {
+                        (fitness(leader.mean) - fitness(dist.mean)) / se <
+                            energy_eval::t_quantile(tuner_params.confidence, df)
+                    }
+                    None => true,
+                }
+            })
+            .map(|(_, i)| i)
+            .collect();
+
+        num_runs = (num_runs * 2).min(max_runs - runs_done);
+    }
+
+    batch
+        .into_iter()
+        .enumerate()
+        .map(|(i, state)| (i, state, dists[i].as_ref().map(|d| d.mean)))
+        .collect()
+}
+
+/// *********************************************************************************************************
+
+impl Acceptance {
+    pub fn new(tuner_params: &TunerParameter) -> Self {
+        Acceptance {
+            test: tuner_params.acceptance.clone(),
+            confidence: tuner_params.confidence,
+            energy: tuner_params.energy,
+        }
+    }
+
+    /// Improvement of the new energy over the current one, to be used by the Metropolis
+    /// criterion. With the welch test a difference which is not statistically significant is
+    /// taken as null, with the bound test the improvement is its lower confidence bound. The
+    /// distributions are taken from the cached measurements of the states: with less than two
+    /// repetitions each, the difference of the means is used.
+    pub fn delta(
+        &self,
+        problem: &Problem,
+        new_state: &State,
+        new_energy: f64,
+        current_state: &State,
+        current_energy: f64,
+    ) -> f64 {
+        let de = match self.energy {
+            EnergyType::throughput => new_energy - current_energy,
+            EnergyType::latency => -(new_energy - current_energy), 
+        };
+
+        if let AcceptanceTest::mean = self.test {
+            return de;
+        }
+
+        let (se, df) =
+            match (problem.distribution(new_state), problem.distribution(current_state)) {
+                (Some(n), Some(c)) => {
+                    match energy_eval::welch(&n, &c) {
+                        Some(res) => res,
+                        None => return de,
+                    }
+                }
+                _ => return de,
+            };
+
+        match self.test {
+            AcceptanceTest::welch => {
+                let t_crit = energy_eval::t_quantile((1.0 + self.confidence) / 2.0, df);
+                if de.abs() / se >= t_crit {
+                    de
+                } else {
+                    0.0
+                }
+            }
+            AcceptanceTest::bound => de - energy_eval::t_quantile(self.confidence, df) * se,
+            AcceptanceTest::mean => de,
+        }
+    }
+}
+
+/// *********************************************************************************************************
+
+impl Stagnation {
+    pub fn new(tuner_params: &TunerParameter, default_max_rejected: usize) -> Self {
+        Stagnation {
+            policy: tuner_params.stagnation_policy.clone(),
+            max_rejected: match tuner_params.max_rejected {
+                Some(val) => val,
+                None => default_max_rejected,
+            },
+            reheat_temp: tuner_params.reheat_ratio * tuner_params.max_temp.unwrap(),
+        }
+    }
+
+    pub fn is_reached(&self, subsequent_rejected: usize) -> bool {
+        subsequent_rejected > self.max_rejected
+    }
+
+    /// Apply the stagnation policy. It returns the solution from which the annealing has to
+    /// continue, or None if the annealing has to stop. A random restart is not evaluated here:
+    /// the solver proposes it as a candidate, so that only the scheduler runs the benchmarks.
+    pub fn react(
+        &self,
+        problem: &mut Problem,
+        temperature: &Temperature,
+        res_emitter: &mut Emitter,
+        current: MrResult,
+        best: &MrResult,
+        elapsed_steps: usize,
+        elapsed_time: f64,
+        tid: usize,
+    ) -> Option<Restart> {
+        match self.policy {
+            StagnationPolicy::stop => {
+                println!(
+                    "{} TID[{}] - Convergence Reached!!!",
+                    Green.paint("[TUNER]"),
+                    tid
+                );
+                None
+            }
+            StagnationPolicy::reheat => {
+                temperature.reheat(elapsed_steps, self.reheat_temp);
+                println!(
+                    "{} TID[{}] - Stagnation Reached! Reheat to Temperature: {:.2}",
+                    Green.paint("[TUNER]"),
+                    tid,
+                    self.reheat_temp
+                );
+                res_emitter.send_event("reheat", self.reheat_temp, elapsed_time, elapsed_steps, tid);
+                Some(Restart::Evaluated(current))
+            }
+            StagnationPolicy::restart_best => {
+                println!(
+                    "{} TID[{}] - Stagnation Reached! Restart from the Best State: {:?}",
+                    Green.paint("[TUNER]"),
+                    tid,
+                    best.state
+                );
+                res_emitter.send_event(
+                    "restart_best",
+                    temperature.get(),
+                    elapsed_time,
+                    elapsed_steps,
+                    tid,
+                );
+                Some(Restart::Evaluated(best.clone()))
+            }
+            StagnationPolicy::restart_random => {
+                let state = problem.rand_state();
+                println!(
+                    "{} TID[{}] - Stagnation Reached! Restart from a Random State: {:?}",
+                    Green.paint("[TUNER]"),
+                    tid,
+                    state
+                );
+                res_emitter.send_event(
+                    "restart_random",
+                    temperature.get(),
+                    elapsed_time,
+                    elapsed_steps,
+                    tid,
+                );
+                Some(Restart::Candidate(state))
+            }
+        }
+    }
+
+    /// Solution after the evaluation of a random restart: the current one is kept if the random
+    /// configuration cannot be evaluated
+    pub fn restarted(&self, current: MrResult, state: State, energy: Option<f64>) -> MrResult {
+        match energy {
+            Some(nrg) => MrResult {
+                energy: nrg,
+                state: state,
+            },
+            None => {
+                println!(
+                    "{} The random configuration parameters cannot be evaluated. \
+                          Keep the current one!",
+                    Green.paint("[TUNER]")
+                );
+                current
+            }
+        }
+    }
+}
+
+/// *********************************************************************************************************
+
+/// Check if the objectives a dominate the objectives b: a is not worse than b in any objective
+/// and it is strictly better in at least one of them
+pub fn dominates(a: &Vec<f64>, b: &Vec<f64>, objectives: &Vec<EnergyType>) -> bool {
+    let mut strictly_better = false;
+    for ((val_a, val_b), objective) in a.iter().zip(b.iter()).zip(objectives.iter()) {
+        let improvement = match *objective {
+            EnergyType::throughput => val_a - val_b,
+            EnergyType::latency => val_b - val_a,
+        };
+        if improvement < 0.0 {
+            return false;
+        }
+        if improvement > 0.0 {
+            strictly_better = true;
+        }
+    }
+    strictly_better
+}
+
+impl ParetoArchive {
+    pub fn new() -> Self {
+        ParetoArchive(Arc::new(Mutex::new(Vec::new())))
+    }
+
+    /// Add the point to the archive, unless it is dominated by (or equal to) a point already
+    /// archived. The archived points dominated by the new one are removed. It returns true if
+    /// the point has been added.
+    pub fn insert(&self, point: ParetoPoint, objectives: &Vec<EnergyType>) -> bool {
+        let mut front = self.0.lock().unwrap();
+
+        if front.iter().any(|p| {
+            dominates(&p.objectives, &point.objectives, objectives) ||
+                p.objectives == point.objectives
+        })
+        {
+            return false;
+        }
+
+        front.retain(|p| !dominates(&point.objectives, &p.objectives, objectives));
+        front.push(point);
+        true
+    }
+
+    pub fn get_coll(&self) -> Vec<ParetoPoint> {
+        let front = self.0.lock().unwrap();
+        (*front).clone()
+    }
+}
@@ -0,0 +1,263 @@
+/// ///////////////////////////////////////////////////////////////////////////
+///  File: annealing/solver/hyperband.rs
+/// ///////////////////////////////////////////////////////////////////////////
+///  Copyright 2017 Giovanni Mazzeo
+///
+///  Licensed under the Apache License, Version 2.0 (the "License");
+///  you may not use this file except in compliance with the License.
+///  You may obtain a copy of the License at
+///
+///      http://www.apache.org/licenses/LICENSE-2.0
+///
+///  Unless required by applicable law or agreed to in writing, software
+///  distributed under the License is distributed on an "AS IS" BASIS,
+///  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+///  See the License for the specific language governing permissions and
+///  limitations under the License.
+/// ///////////////////////////////////////////////////////////////////////////
+
+/// ****************************************************************************
+/// *****************************************************************************
+/// **
+/// Multi-Fidelity Search (Hyperband)
+/// *
+/// *****************************************************************************
+/// ****************************************************************************
+use annealing::solver::Solver;
+use annealing::problem::Problem;
+use annealing::cooler;
+use annealing::solver::common;
+use annealing::solver::common::MrResult;
+use res_emitters::Emitter;
+
+use shared::TunerParameter;
+
+use time;
+use EnergyType;
+use State;
+
+use ansi_term::Colour::Green;
+use std::thread;
+
+
+/// The duration of the benchmark is the fidelity of an evaluation. Each bracket screens many
+/// random configurations with short runs, then only the best 1/eta of them is evaluated again
+/// with eta times longer runs (successive halving), up to the full duration. The brackets trade
+/// the number of configurations for their initial fidelity, from min_fidelity to a bracket of
+/// full-length runs only, and they are repeated until the budget is exhausted.
+#[derive(Debug, Clone)]
+pub struct Hyperband {
+    pub tuner_params: TunerParameter,
+    pub res_emitter: Emitter,
+}
+
+impl Solver for Hyperband {
+    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+
+        let eta = self.tuner_params.eta.max(2);
+        let min_fidelity = self.tuner_params.min_fidelity.max(0.0001).min(1.0);
+        // Number of brackets besides the full-fidelity one
+        let s_max = ((1.0 / min_fidelity).ln() / (eta as f64).ln() + 1e-9).floor() as u32;
+
+        println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+        println!(
+            "{} Multi-Fidelity Search: {} Brackets - Eta: {} - Min Fidelity: {:.4}",
+            Green.paint("[TUNER]"),
+            s_max + 1,
+            eta,
+            (eta as f64).powi(-(s_max as i32))
+        );
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+        let start_time = time::precise_time_ns();
+
+        // The temperature is not used by the search, but it keeps track of the budget
+        let temperature = common::Temperature::new(
+            self.tuner_params.max_temp.unwrap(),
+            cooler::new_cooler(&self.tuner_params),
+            self.tuner_params.cooling.clone(),
+        );
+
+        // The best configuration is the best one among those evaluated at the highest fidelity
+        let mut best: Option<(f64, MrResult)> = None;
+        let mut elapsed_steps = 0;
+        // Budget spent in full-length runs: an evaluation at fidelity f costs f
+        let mut used_budget = 0.0;
+        let mut first_bracket = true;
+
+        'search: loop {
+            for s in (0..s_max + 1).rev() {
+                let num_configs = (((s_max + 1) as f64 / (s + 1) as f64) *
+                                       (eta as f64).powi(s as i32))
+                    .ceil() as usize;
+
+                // The default configuration is evaluated first, as reference for the comparison
+                let mut configs: Vec<State> = (0..num_configs)
+                    .map(|i| if first_bracket && i == 0 {
+                        problem.initial_state()
+                    } else {
+                        problem.rand_state()
+                    })
+                    .collect();
+                first_bracket = false;
+
+                for i in 0..s + 1 {
+                    let fidelity = (eta as f64).powi(i as i32 - s as i32);
+                    println!(
+                        "{} Bracket {} - Rung {}: {} Configurations at Fidelity {:.4}",
+                        Green.paint("[TUNER]"),
+                        s,
+                        i,
+                        configs.len(),
+                        fidelity
+                    );
+
+                    let mut results: Vec<(State, f64)> = Vec::new();
+                    for batch in configs.chunks(num_workers) {
+                        let max_budget = self.tuner_params.max_step as f64;
+                        if used_budget >= max_budget {
+                            break 'search;
+                        }
+                        if temperature.is_expired(used_budget as usize) {
+                            println!("{} Time Budget Exhausted!!!", Green.paint("[TUNER]"));
+                            break 'search;
+                        }
+
+                        let remaining = ((max_budget - used_budget) / fidelity + 1e-9).floor()
+                            as usize;
+                        if remaining == 0 {
+                            break 'search;
+                        }
+                        let batch: Vec<State> = batch.iter().take(remaining).cloned().collect();
+
+                        for (tid, state, nrg) in evaluate_at_fidelity(problem, batch, fidelity) {
+                            elapsed_steps += 1;
+                            used_budget += fidelity;
+
+                            // A NaN energy cannot be ranked, hence it is skipped as well
+                            let new_energy = match nrg {
+                                Some(nrg) if !nrg.is_nan() => nrg,
+                                _ => {
+                                    println!(
+                                        "{} The current configuration parameters cannot be \
+                                         evaluated. Skip!",
+                                        Green.paint("[TUNER]")
+                                    );
+                                    continue;
+                                }
+                            };
+
+                            let is_best = match best {
+                                Some((best_fidelity, ref b)) => {
+                                    fidelity > best_fidelity ||
+                                        (fidelity == best_fidelity &&
+                                             self.fitness(new_energy) > self.fitness(b.energy))
+                                }
+                                None => true,
+                            };
+                            if is_best {
+                                best = Some((
+                                    fidelity,
+                                    MrResult {
+                                        energy: new_energy,
+                                        state: state.clone(),
+                                    },
+                                ));
+                            }
+
+                            let best_c = best.clone().unwrap().1;
+                            let elapsed_time = (time::precise_time_ns() - start_time) as f64 /
+                                1000000000.0f64;
+                            self.res_emitter.send_update(
+                                0.0,
+                                elapsed_time,
+                                0.0,
+                                new_energy,
+                                &state,
+                                problem.repetitions(&state),
+                                best_c.energy,
+                                &best_c.state,
+                                elapsed_steps,
+                                tid,
+                            );
+
+                            results.push((state, new_energy));
+                        }
+                    }
+
+                    let elapsed_time = (time::precise_time_ns() - start_time) as f64 /
+                        1000000000.0f64;
+                    println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+                    println!(
+                        "{} Completed Steps: {:.2} - Elapsed Time: {:.2} s",
+                        Green.paint("[TUNER]"),
+                        used_budget,
+                        elapsed_time
+                    );
+                    if let Some((best_fidelity, ref b)) = best {
+                        println!(
+                            "{} Best State (Fidelity {:.4}): {:?}",
+                            Green.paint("[TUNER]"),
+                            best_fidelity,
+                            b.state
+                        );
+                        println!("{} Best Energy: {:.4}", Green.paint("[TUNER]"), b.energy);
+                    }
+                    println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+                    // Only the best 1/eta of the configurations is promoted to the next rung
+                    results.sort_by(|a, b| {
+                        self.fitness(b.1).partial_cmp(&self.fitness(a.1)).unwrap()
+                    });
+                    configs = results
+                        .into_iter()
+                        .take(configs.len() / eta)
+                        .map(|(state, _)| state)
+                        .collect();
+                    if configs.is_empty() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        match best {
+            Some((_, b)) => b,
+            None => panic!("None of the configurations allowed to calculate the energy"),
+        }
+    }
+}
+
+impl Hyperband {
+    /// Energy to maximize
+    fn fitness(&self, nrg: f64) -> f64 {
+        match self.tuner_params.energy {
+            EnergyType::throughput => nrg,
+            EnergyType::latency => -nrg,
+        }
+    }
+}
+
+
+/// Evaluate a batch of configurations in parallel at the given fidelity, one per agent. The
+/// results are returned in the order of the batch, together with the tid of the worker that
+/// evaluated them.
+fn evaluate_at_fidelity(
+    problem: &Problem,
+    batch: Vec<State>,
+    fidelity: f64,
+) -> Vec<(usize, State, Option<f64>)> {
+    let handles: Vec<_> = batch
+        .into_iter()
+        .enumerate()
+        .map(|(tid, state)| {
+            let mut problem_c = problem.clone();
+            thread::spawn(move || {
+                let nrg = problem_c.measure_fidelity(&state, tid, fidelity).map(|d| d.mean);
+                (tid, state, nrg)
+            })
+        })
+        .collect();
+
+    handles.into_iter().map(|h| h.join().unwrap()).collect()
+}
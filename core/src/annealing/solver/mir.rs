@@ -24,269 +24,399 @@
 /// *****************************************************************************
 /// ****************************************************************************
 use annealing::solver::Solver;
+use annealing::solver::scheduler::{self, AskTell, Candidate};
 use annealing::problem::Problem;
-use annealing::cooler::{Cooler, StepsCooler, TimeCooler};
+use annealing::cooler;
 use annealing::solver::common;
 use annealing::solver::common::MrResult;
 use res_emitters;
 use res_emitters::Emitter;
-use annealing::solver::common::IntermediateResults;
+use annealing::solver::checkpoint::{Checkpointer, WorkerCheckpoint};
 
 use shared::TunerParameter;
 
 use time;
-use CoolingSchedule;
 use EnergyType;
-use pbr;
-use rand;
-use libc;
-use num_cpus;
+use State;
 
-use rand::{Rng, thread_rng};
 use rand::distributions::{Range, IndependentSample};
 use ansi_term::Colour::Green;
-use std::collections::HashMap;
-use pbr::{ProgressBar, MultiBar};
-use std::thread;
-use std::sync::mpsc::channel;
 
 
 #[derive(Debug, Clone)]
 pub struct Mir {
     pub tuner_params: TunerParameter,
     pub res_emitter: Emitter,
+    pub checkpointer: Checkpointer,
+    stagnation: common::Stagnation,
+    acceptance: common::Acceptance,
+    chains: Vec<Chain>,
+}
+
+/// Independent annealing run of a worker. Each chain works on its own copy of the problem, so
+/// that it draws from its own stream of random numbers.
+#[derive(Debug, Clone)]
+struct Chain {
+    problem: Problem,
+    temperature: common::Temperature,
+    initial_state: State,
+    current: Option<MrResult>,
+    best: Option<MrResult>,
+    accepted: usize,
+    rejected: usize,
+    elapsed_steps: usize,
+    last_checkpoint: usize,
+    last_nrg: f64,
+    start_time: u64,
+    pending: bool,
+    restarting: bool,
+    finished: bool,
+}
+
+impl Mir {
+    pub fn new(tuner_params: TunerParameter, res_emitter: Emitter, checkpointer: Checkpointer) -> Self {
+        let stagnation = common::Stagnation::new(&tuner_params, 300);
+        let acceptance = common::Acceptance::new(&tuner_params);
+        Mir {
+            tuner_params: tuner_params,
+            res_emitter: res_emitter,
+            checkpointer: checkpointer,
+            stagnation: stagnation,
+            acceptance: acceptance,
+            chains: Vec::new(),
+        }
+    }
 }
 
 impl Solver for Mir {
     fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+        scheduler::run(self, problem, num_workers)
+    }
+}
 
-        let cooler = StepsCooler {
-            max_steps: self.tuner_params.max_step,
-            min_temp: self.tuner_params.min_temp.unwrap(),
-            max_temp: self.tuner_params.max_temp.unwrap(),
-        };
-
+impl AskTell for Mir {
+    fn start(&mut self, problem: &mut Problem, num_workers: usize) {
 
-        ("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+        println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
         println!(
             "{} Initialization Phase: Evaluation of Energy for Default Parameters",
             Green.paint("[TUNER]")
         );
         println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
 
+        // Creation of the Initial States. The first chain starts from the initial default state
+        // given by the user and the other num_workers-1 from states generated in a random way
+        let initial_state = problem.initial_state();
+
+        self.chains = (0..num_workers)
+            .map(|worker_nr| {
+                let mut problem_c = problem.clone();
+                problem_c.reseed(&[worker_nr]);
+
+                let chain_initial_state = if worker_nr == 0 {
+                    initial_state.clone()
+                } else {
+                    problem.rand_state()
+                };
+
+                let mut chain = Chain {
+                    problem: problem_c,
+                    temperature: common::Temperature::new(
+                        self.tuner_params.max_temp.unwrap(),
+                        cooler::new_cooler(&self.tuner_params),
+                        self.tuner_params.cooling.clone(),
+                    ),
+                    initial_state: chain_initial_state,
+                    current: None,
+                    best: None,
+                    accepted: 0,
+                    rejected: 0,
+                    elapsed_steps: 0,
+                    last_checkpoint: 0,
+                    last_nrg: 0.0,
+                    start_time: time::precise_time_ns(),
+                    pending: false,
+                    restarting: false,
+                    finished: false,
+                };
+
+                // A resumed chain continues from its last checkpoint
+                if let Some(ckpt) = self.checkpointer.resumed_worker(worker_nr) {
+                    chain.temperature.restore(&ckpt.temperature);
+                    chain.start_time -= ckpt.temperature.elapsed_time_ns;
+                    let current = ckpt.current.unwrap();
+                    chain.last_nrg = current.energy;
+                    chain.current = Some(current);
+                    chain.best = Some(ckpt.best);
+                    chain.accepted = ckpt.accepted;
+                    chain.rejected = ckpt.subsequent_rejected;
+                    chain.elapsed_steps = ckpt.elapsed_steps;
+                    chain.last_checkpoint = ckpt.elapsed_steps;
+                    chain.problem.restore_rng(ckpt.rng_draws);
+                }
+
+                chain
+            })
+            .collect();
+    }
 
-        let mut elapsed_steps = common::SharedGenericCounter::new();
+    /// Each chain which is not waiting for a result proposes its next configuration
+    fn ask(&mut self, _problem: &mut Problem, max_candidates: usize) -> Vec<Candidate> {
+        let mut candidates = Vec::new();
+        let max_steps = self.tuner_params.max_step;
 
-        // Creation of the pool of Initial States. It will be composed by the initial default state
-        // given by the user and by other num_workers-1 states generated in a random way
-        let mut initial_state = problem.initial_state();
-        let mut initial_states_pool = common::StatesPool::new();
-        initial_states_pool.push(initial_state.clone());
-        for i in 1..num_workers {
-            initial_states_pool.push(problem.rand_state());
+        for (worker_nr, chain) in self.chains.iter_mut().enumerate() {
+            if candidates.len() >= max_candidates {
+                break;
+            }
+            if chain.pending || chain.finished {
+                continue;
+            }
+
+            let (mut worker_state, mut worker_nrg) = match chain.current {
+                Some(ref current) => (current.state.clone(), current.energy),
+                None => {
+                    chain.pending = true;
+                    candidates.push(Candidate {
+                        state: chain.initial_state.clone(),
+                        slot: worker_nr,
+                    });
+                    continue;
+                }
+            };
+
+            // With a wall-clock budget, the chains are not limited by the steps
+            if self.tuner_params.max_time.is_none() && chain.elapsed_steps >= max_steps {
+                chain.finished = true;
+                continue;
+            }
+
+            if chain.temperature.is_expired(chain.elapsed_steps) {
+                println!("{} TID[{}] - Time Budget Exhausted!!!", Green.paint("[TUNER]"), worker_nr);
+                chain.finished = true;
+                continue;
+            }
+
+            let elapsed_time = (time::precise_time_ns() - chain.start_time) as f64 / 1000000000.0f64;
+
+            if self.stagnation.is_reached(chain.rejected) {
+                let current = MrResult {
+                    energy: worker_nrg,
+                    state: worker_state,
+                };
+                match self.stagnation.react(
+                    &mut chain.problem,
+                    &chain.temperature,
+                    &mut self.res_emitter,
+                    current,
+                    chain.best.as_ref().unwrap(),
+                    chain.elapsed_steps,
+                    elapsed_time,
+                    worker_nr,
+                ) {
+                    Some(common::Restart::Evaluated(res)) => {
+                        worker_nrg = res.energy;
+                        worker_state = res.state;
+                    }
+                    Some(common::Restart::Candidate(restart_state)) => {
+                        chain.rejected = 0;
+                        chain.pending = true;
+                        chain.restarting = true;
+                        candidates.push(Candidate {
+                            state: restart_state,
+                            slot: worker_nr,
+                        });
+                        continue;
+                    }
+                    None => {
+                        chain.finished = true;
+                        continue;
+                    }
+                }
+                chain.rejected = 0;
+                chain.current = Some(MrResult {
+                    energy: worker_nrg,
+                    state: worker_state.clone(),
+                });
+            }
+
+            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------------------------------------------"));
+            println!(
+                "{} TID[{}] - Completed Steps: {:.2} - Percentage of Completion: {:.2}% - \
+                      Elapsed Time: {:.2} s",
+                Green.paint("[TUNER]"),
+                worker_nr,
+                chain.elapsed_steps,
+                chain.temperature.progress(chain.elapsed_steps),
+                elapsed_time
+            );
+            println!(
+                "{} Total Accepted Solutions: {:?} - Current Temperature: {:.2}",
+                Green.paint("[TUNER]"),
+                chain.accepted,
+                chain.temperature.get()
+            );
+            println!("{} Accepted State: {:?}", Green.paint("[TUNER]"), worker_state);
+            println!(
+                "{} Accepted Energy: {:.4} - Last Measured Energy: {:.4}",
+                Green.paint("[TUNER]"),
+                worker_nrg,
+                chain.last_nrg
+            );
+            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------------------------------------------"));
+
+            let relative_temp = chain.temperature.get() / self.tuner_params.max_temp.unwrap();
+            let next_state = chain.problem.new_state(
+                &worker_state,
+                max_steps,
+                chain.elapsed_steps,
+                relative_temp,
+            );
+            chain.pending = true;
+            candidates.push(Candidate {
+                state: next_state,
+                slot: worker_nr,
+            });
         }
 
-        // Create a muti-bar
-        let mut mb = MultiBar::new();
-
-        let threads_res = common::ThreadsResults::new();
-
-        let mut overall_start_time = time::precise_time_ns();
-
-
-        let handles: Vec<_> = (0..num_workers).map(|worker_nr| {
- 				
-				let mut pb=mb.create_bar((self.tuner_params.max_step) as u64);
- 			    pb.show_message = true;
-		        
-		       
-		        				
-				let (mut master_state_c, mut problem_c) = (initial_state.clone(), problem.clone());
-	        	let (elapsed_steps_c,
-	        		initial_states_pool_c,
-            		threads_res_c) = 	(elapsed_steps.clone(),
-            		 					initial_states_pool.clone(),
-    		 							threads_res.clone());
-
-				let nrg_type = self.clone().tuner_params.energy;
-				let max_steps= self.clone().tuner_params.max_step;
-				let cooling_sched= self.clone().tuner_params.cooling;
-				let max_temp=self.tuner_params.max_temp.unwrap().clone();
-				let cooler_c=cooler.clone();
-				let is=initial_state.clone();
-				let mut res_emitter=self.clone().res_emitter;
-
- 	 			/************************************************************************************************************/
- 				thread::spawn(move || {
-				 	
-			        let mut attempted = 0;
-			        let mut total_improves = 0;
-			        let mut subsequent_improves = 0;
-					let mut accepted = 0;
-			        let mut rejected = 0;
-					let mut temperature = common::Temperature::new(max_temp, cooler_c.clone(), cooling_sched);
-					let mut worker_elapsed_steps=0;
-							
-					let mut rng = thread_rng();
-        			let mut start_time = time::precise_time_ns(); 
-        			
-					let mut worker_state=initial_states_pool_c.remove_one().unwrap();
-				 	let mut worker_nrg = match problem_c.energy(&worker_state.clone(), worker_nr) {
-			            Some(nrg) => nrg,
-			            None => panic!("The initial configuration does not allow to calculate the energy"),
-			        };
-					
-					let mut last_nrg=worker_nrg;
-					let mut elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
-        			let time_2_complete_hrs = ((elapsed_time as f64) * max_steps as f64) / 3600.0;
-  					
-  					let range = Range::new(0.0, 1.0);
-  					
-  					let temperature_c=temperature.clone();
-  					
-					let (tx, rx) = channel::<IntermediateResults>();
-			        // Spawn the thread that will take care of writing results into a CSV file
-			        thread::spawn(move || loop {
-						let mut elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
-			            match rx.recv() {
-			                Ok(res) => {
-			                    res_emitter.send_update(temperature_c.get(),
-			                                                elapsed_time,
-			                                                0.0,
-			                                                res.last_nrg,
-			                                                &res.last_state,
-			                                                res.best_nrg,
-			                                                &res.best_state,
-			                                                worker_elapsed_steps,res.tid);
-			                }
-			                Err(e) => {} 
-			            }
-			        });
-	 				
-			 		
-			 		/************************************************************************************************************/			    	
-			    	
-					let threads_res=common::ThreadsResults::new();
-
-		            loop{	            	
-
-						if worker_elapsed_steps > max_steps || rejected>300{
-							break;
-						}
-				
-			            elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
-						//let time_2_complete_mins=exec_time*(((max_steps/num_workers) - worker_elapsed_steps) as f64) / 60.0;
-
-			            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------------------------------------------"));
-			            println!("{} TID[{}] - Completed Steps: {:.2} - Percentage of Completion: {:.2}% - Estimated \
-			                      time to Complete: {:.2} Hrs",
-			                     Green.paint("[TUNER]"),
-			                     worker_nr,
-			                     worker_elapsed_steps,
-			                     (worker_elapsed_steps as f64 / (cooler_c.max_steps/num_workers) as f64) * 100.0,
-			                     elapsed_time);
-			            println!("{} Total Accepted Solutions: {:?} - Current Temperature: {:.2} - Elapsed \
-			                      Time: {:.2} s",
-			                     Green.paint("[TUNER]"),
-			                     accepted,
-			                     temperature.get(),
-			                     elapsed_time);
-			            println!("{} Accepted State: {:?}", Green.paint("[TUNER]"), worker_state);
-			            println!("{} Accepted Energy: {:.4} - Last Measured Energy: {:.4}",
-			                     Green.paint("[TUNER]"),
-			                     worker_nrg,
-			                     last_nrg);
-			            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------------------------------------------"));
-
-		            	pb.message(&format!("TID [{}] - Status - ", worker_nr));
-						pb.inc();
-						let mut last_state=worker_state.clone();
-		            	worker_state = {
-        	
-				                let next_state = problem_c.new_state(&worker_state,max_steps,worker_elapsed_steps);
-				                
-								let accepted_state = match problem_c.energy(&next_state.clone(), worker_nr) {
-				                    Some(new_energy) => {
-            	                        last_nrg = new_energy;
-
-				            			println!("Thread : {:?} - Step: {:?} - State: {:?} - Energy: {:?}",worker_nr,worker_elapsed_steps,next_state,new_energy);
-				 
-				                        let de = match nrg_type {
-				                            EnergyType::throughput => new_energy - worker_nrg,
-				                            EnergyType::latency => -(new_energy - worker_nrg), 
-				                        }; 
-				
-				                        if de > 0.0 || range.ind_sample(&mut rng) <= (de / temperature.get()).exp() {
-				                            accepted+=1;
-				                        	
-				                            worker_nrg = new_energy;
-				
-				                            if de > 0.0 {
-					                        	rejected=0;
-				                                total_improves = total_improves + 1;
-				                                subsequent_improves = subsequent_improves + 1;
-				                            }
-				
-				                           
-				                            next_state
-				
-				                        } else {
-				                        	rejected+=1;
-				                        		
-				                            worker_state
-				                        }
-				                    }
-				                    None => {
-				                        println!("{} The current configuration parameters cannot be evaluated. \
-				                                  Skip!",
-				                                 Green.paint("[TUNER]"));
-				                        worker_state
-				                    }
-				                };
-				                
-				                accepted_state
-				            };
-							
-							
-							let intermediate_res=IntermediateResults{
-				            			last_nrg: last_nrg,
-				            			last_state:last_state.clone(),
-				            			best_nrg: worker_nrg,
-				            			best_state: worker_state.clone(),
-				            			tid: worker_nr
-				            		};
-		            		tx.send(intermediate_res);
-				            		
-							
-			            	worker_elapsed_steps+=1;
-			            	
-							temperature.update(worker_elapsed_steps);	
-					}
-		            
-		            let res=common::MrResult{
-		            	energy: worker_nrg,
-		            	state: worker_state,
-		            };
-		            threads_res_c.push(res);	
-	            	pb.finish_print(&format!("Child Thread [{}] Terminated the Execution", worker_nr));
-            	
-	            })
-
-	        }).collect();
-
-        mb.listen();
-        // Wait for all threads to complete before start a search in a new set of neighborhoods.
-        for h in handles {
-            h.join().unwrap();
+        candidates
+    }
+
+    fn tell(&mut self, _problem: &mut Problem, candidate: Candidate, new_energy: Option<f64>) {
+        let worker_nr = candidate.slot;
+        let next_state = candidate.state;
+        let chain = &mut self.chains[worker_nr];
+        chain.pending = false;
+
+        let current = match chain.current.clone() {
+            Some(current) => current,
+            None => {
+                // Evaluation of the initial state of the chain
+                let worker_nrg = match new_energy {
+                    Some(nrg) => nrg,
+                    None => panic!("The initial configuration does not allow to calculate the energy"),
+                };
+                chain.last_nrg = worker_nrg;
+                chain.current = Some(MrResult {
+                    energy: worker_nrg,
+                    state: next_state.clone(),
+                });
+                chain.best = Some(MrResult {
+                    energy: worker_nrg,
+                    state: next_state,
+                });
+                return;
+            }
+        };
+
+        if chain.restarting {
+            // Evaluation of the random restart of the chain
+            chain.restarting = false;
+            chain.current = Some(self.stagnation.restarted(current, next_state, new_energy));
+            return;
         }
 
+        let range = Range::new(0.0, 1.0);
+
+        match new_energy {
+            Some(new_energy) => {
+                chain.last_nrg = new_energy;
+
+                println!(
+                    "Thread : {:?} - Step: {:?} - State: {:?} - Energy: {:?}",
+                    worker_nr,
+                    chain.elapsed_steps,
+                    next_state,
+                    new_energy
+                );
+
+                let de = self.acceptance.delta(
+                    &chain.problem,
+                    &next_state,
+                    new_energy,
+                    &current.state,
+                    current.energy,
+                );
+
+                if de > 0.0 || range.ind_sample(chain.problem.rng()) <= (de / chain.temperature.get()).exp() {
+                    chain.accepted += 1;
+
+                    if de > 0.0 {
+                        chain.rejected = 0;
+                    }
+
+                    let de_best = match self.tuner_params.energy {
+                        EnergyType::throughput => new_energy - chain.best.as_ref().unwrap().energy,
+                        EnergyType::latency => -(new_energy - chain.best.as_ref().unwrap().energy),
+                    };
+                    if de_best > 0.0 {
+                        chain.best = Some(MrResult {
+                            energy: new_energy,
+                            state: next_state.clone(),
+                        });
+                    }
+
+                    chain.current = Some(MrResult {
+                        energy: new_energy,
+                        state: next_state.clone(),
+                    });
+                } else {
+                    chain.rejected += 1;
+                }
+
+                let worker_current = chain.current.clone().unwrap();
+                let elapsed_time = (time::precise_time_ns() - chain.start_time) as f64 /
+                    1000000000.0f64;
+                self.res_emitter.send_update(
+                    chain.temperature.get(),
+                    elapsed_time,
+                    0.0,
+                    new_energy,
+                    &next_state,
+                    chain.problem.repetitions(&next_state),
+                    worker_current.energy,
+                    &worker_current.state,
+                    chain.elapsed_steps,
+                    worker_nr,
+                );
+            }
+            None => {
+                println!(
+                    "{} The current configuration parameters cannot be evaluated. \
+                          Skip!",
+                    Green.paint("[TUNER]")
+                );
+            }
+        };
 
-        /// *********************************************************************************************************
+        chain.elapsed_steps += 1;
+        chain.temperature.update(chain.elapsed_steps, chain.accepted);
+
+        if self.checkpointer.is_due(chain.last_checkpoint, chain.elapsed_steps) {
+            self.checkpointer.save(
+                worker_nr,
+                WorkerCheckpoint {
+                    current: chain.current.clone(),
+                    best: chain.best.clone().unwrap(),
+                    elapsed_steps: chain.elapsed_steps,
+                    accepted: chain.accepted,
+                    subsequent_rejected: chain.rejected,
+                    temperature: chain.temperature.snapshot(),
+                    rng_draws: chain.problem.rng_draws(),
+                },
+                Vec::new(),
+            );
+            chain.last_checkpoint = chain.elapsed_steps;
+        }
+    }
 
-        // Get results of worker threads (each one will put its best evaluated energy) and
-        // choose between them which one will be the best one.
-        let mut workers_res = threads_res.get_coll();
-        let first_elem = workers_res.pop().unwrap();
+    /// The best configuration among the ones found by the chains
+    fn best(&self) -> MrResult {
+        let mut workers_res: Vec<MrResult> = self.chains
+            .iter()
+            .filter_map(|chain| chain.best.clone())
+            .collect();
+        let first_elem = workers_res.remove(0);
 
         let mut best_energy = first_elem.energy;
         let mut best_state = first_elem.state;
@@ -302,8 +432,6 @@ impl Solver for Mir {
             }
         }
 
-
-
         MrResult {
             energy: best_energy,
             state: best_state,
@@ -4,8 +4,15 @@ use annealing::solver::common::MrResult;
 pub mod seqsa;
 pub mod mir;
 pub mod spisa;
-//pub mod prsa;
+pub mod prsa;
+pub mod ptsa;
+pub mod bo;
+pub mod search;
+pub mod mosa;
+pub mod hyperband;
 pub mod common;
+pub mod checkpoint;
+pub mod scheduler;
 
 pub trait Solver {
     fn solve(&mut self, &mut Problem, usize) -> MrResult;
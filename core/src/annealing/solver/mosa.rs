@@ -0,0 +1,320 @@
+/// ///////////////////////////////////////////////////////////////////////////
+///  File: annealing/solver/mosa.rs
+/// ///////////////////////////////////////////////////////////////////////////
+///  Copyright 2017 Giovanni Mazzeo
+///
+///  Licensed under the Apache License, Version 2.0 (the "License");
+///  you may not use this file except in compliance with the License.
+///  You may obtain a copy of the License at
+///
+///      http://www.apache.org/licenses/LICENSE-2.0
+///
+///  Unless required by applicable law or agreed to in writing, software
+///  distributed under the License is distributed on an "AS IS" BASIS,
+///  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+///  See the License for the specific language governing permissions and
+///  limitations under the License.
+/// ///////////////////////////////////////////////////////////////////////////
+
+/// ****************************************************************************
+/// *****************************************************************************
+/// **
+/// Multi-Objective Simulated Annealing (MOSA)
+/// *
+/// *****************************************************************************
+/// ****************************************************************************
+use annealing::solver::Solver;
+use annealing::problem::Problem;
+use annealing::cooler;
+use annealing::solver::common;
+use annealing::solver::common::{MrResult, ParetoPoint, ParetoArchive};
+use res_emitters::Emitter;
+
+use shared::TunerParameter;
+
+use time;
+use EnergyType;
+
+use rand::distributions::{Range, IndependentSample};
+use ansi_term::Colour::{Green, Yellow};
+use std::thread;
+use std::sync::mpsc::channel;
+
+
+/// Each worker anneals its own configuration, accepting the moves by Pareto dominance. All the
+/// non-dominated configurations found by the workers are collected into a shared archive, which
+/// is the result of the tuning run.
+#[derive(Debug, Clone)]
+pub struct Mosa {
+    pub tuner_params: TunerParameter,
+    pub res_emitter: Emitter,
+}
+
+impl Solver for Mosa {
+    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+
+        let objectives = self.tuner_params.objectives.clone();
+
+        println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+        println!(
+            "{} Multi-Objective Annealing of the Objectives: {:?}",
+            Green.paint("[TUNER]"),
+            objectives
+        );
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+        let start_time = time::precise_time_ns();
+        let elapsed_steps = common::SharedGenericCounter::new();
+        let archive = ParetoArchive::new();
+
+        // The front is emitted by a single thread, since all the workers share its file
+        let (front_tx, front_rx) = channel::<(Vec<ParetoPoint>, usize)>();
+        let mut front_emitter = self.res_emitter.clone();
+        let objectives_c = objectives.clone();
+        let front_handle = thread::spawn(move || loop {
+            match front_rx.recv() {
+                Ok((front, step)) => {
+                    let elapsed_time = (time::precise_time_ns() - start_time) as f64 /
+                        1000000000.0f64;
+                    front_emitter.send_front(&front, &objectives_c, elapsed_time, step);
+                }
+                Err(_) => break,
+            }
+        });
+
+        let handles: Vec<_> = (0..num_workers)
+            .map(|worker_nr| {
+                // The first worker starts from the default configuration, the others from
+                // random ones
+                let initial_state = if worker_nr == 0 {
+                    problem.initial_state()
+                } else {
+                    problem.rand_state()
+                };
+
+                let mut problem_c = problem.clone();
+                problem_c.reseed(&[worker_nr]);
+                let tuner_params = self.tuner_params.clone();
+                let mut res_emitter = self.res_emitter.clone();
+                let (objectives, archive, elapsed_steps, front_tx) = (
+                    objectives.clone(),
+                    archive.clone(),
+                    elapsed_steps.clone(),
+                    front_tx.clone(),
+                );
+
+                thread::spawn(move || {
+                    let max_temp = tuner_params.max_temp.unwrap();
+                    let temperature = common::Temperature::new(
+                        max_temp,
+                        cooler::new_cooler(&tuner_params),
+                        tuner_params.cooling.clone(),
+                    );
+                    let range = Range::new(0.0, 1.0);
+
+                    let mut worker_state = initial_state;
+                    let mut worker_objs =
+                        match problem_c.objectives(&worker_state, &objectives, worker_nr) {
+                            Some(objs) => objs,
+                            None => {
+                                panic!(
+                                    "The initial configuration does not allow to calculate the \
+                                     objectives"
+                                )
+                            }
+                        };
+                    if archive.insert(
+                        ParetoPoint {
+                            objectives: worker_objs.clone(),
+                            state: worker_state.clone(),
+                        },
+                        &objectives,
+                    )
+                    {
+                        front_tx.send((archive.get_coll(), 0)).unwrap();
+                    }
+
+                    let mut accepted = 0;
+
+                    loop {
+                        let step = elapsed_steps.get();
+                        // With a wall-clock budget, the search is not limited by the steps
+                        if tuner_params.max_time.is_none() && step >= tuner_params.max_step {
+                            break;
+                        }
+
+                        if temperature.is_expired(step) {
+                            println!(
+                                "{} TID[{}] - Time Budget Exhausted!!!",
+                                Green.paint("[TUNER]"),
+                                worker_nr
+                            );
+                            break;
+                        }
+
+                        let next_state = problem_c.new_state(
+                            &worker_state,
+                            tuner_params.max_step,
+                            step,
+                            temperature.get() / max_temp,
+                        );
+
+                        let new_objs = match problem_c.objectives(
+                            &next_state,
+                            &objectives,
+                            worker_nr,
+                        ) {
+                            Some(objs) => objs,
+                            None => {
+                                println!(
+                                    "{} The current configuration parameters cannot be \
+                                     evaluated. Skip!",
+                                    Green.paint("[TUNER]")
+                                );
+                                elapsed_steps.increment();
+                                continue;
+                            }
+                        };
+                        elapsed_steps.increment();
+
+                        // A move to a dominated configuration is accepted with a probability
+                        // which depends on the overall relative worsening of the objectives and
+                        // on the temperature relative to the initial one
+                        let is_dominated = common::dominates(&worker_objs, &new_objs, &objectives);
+                        let is_accepted = if is_dominated {
+                            let worsening =
+                                relative_worsening(&new_objs, &worker_objs, &objectives);
+                            let relative_temp = temperature.get() / max_temp;
+                            range.ind_sample(problem_c.rng()) <= (-worsening / relative_temp).exp()
+                        } else {
+                            true
+                        };
+
+                        if archive.insert(
+                            ParetoPoint {
+                                objectives: new_objs.clone(),
+                                state: next_state.clone(),
+                            },
+                            &objectives,
+                        )
+                        {
+                            front_tx.send((archive.get_coll(), step + 1)).unwrap();
+                        }
+
+                        // The single-objective results report the first objective, the best value
+                        // being the best one within the front
+                        let front_best = best_of_front(&archive.get_coll(), &objectives);
+                        let elapsed_time = (time::precise_time_ns() - start_time) as f64 /
+                            1000000000.0f64;
+                        res_emitter.send_update(
+                            temperature.get(),
+                            elapsed_time,
+                            0.0,
+                            new_objs[0],
+                            &next_state,
+                            problem_c.repetitions(&next_state),
+                            front_best.energy,
+                            &front_best.state,
+                            step + 1,
+                            worker_nr,
+                        );
+
+                        if is_accepted {
+                            accepted += 1;
+                            worker_state = next_state;
+                            worker_objs = new_objs;
+                        }
+
+                        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+                        println!(
+                            "{} TID[{}] - Completed Steps: {:.2} - Current Temperature: {:.2} - \
+                             Elapsed Time: {:.2} s",
+                            Green.paint("[TUNER]"),
+                            worker_nr,
+                            step + 1,
+                            temperature.get(),
+                            elapsed_time
+                        );
+                        println!(
+                            "{} Accepted Objectives: {:?} - Size of the Pareto Front: {}",
+                            Green.paint("[TUNER]"),
+                            worker_objs,
+                            archive.get_coll().len()
+                        );
+                        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+                        temperature.update(step + 1, accepted);
+                    }
+                })
+            })
+            .collect();
+
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        let front = archive.get_coll();
+        front_tx.send((front.clone(), elapsed_steps.get())).unwrap();
+        drop(front_tx);
+        front_handle.join().unwrap();
+
+        println!("{}",Yellow.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+        println!("{} {} configurations", Yellow.paint("Pareto Front: "), front.len());
+        for point in front.iter() {
+            println!(
+                "{} {:?} - {} {:?}",
+                Yellow.paint("Objectives ==> "),
+                point.objectives,
+                Yellow.paint("Configuration ==> "),
+                point.state
+            );
+        }
+        println!("{}",Yellow.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+        best_of_front(&front, &objectives)
+    }
+}
+
+
+/// Sum of the relative worsening of each objective moving from old_objs to new_objs
+fn relative_worsening(
+    new_objs: &Vec<f64>,
+    old_objs: &Vec<f64>,
+    objectives: &Vec<EnergyType>,
+) -> f64 {
+    new_objs
+        .iter()
+        .zip(old_objs.iter())
+        .zip(objectives.iter())
+        .map(|((new_val, old_val), objective)| {
+            let worsening = match *objective {
+                EnergyType::throughput => old_val - new_val,
+                EnergyType::latency => new_val - old_val,
+            };
+            if worsening > 0.0 && *old_val != 0.0 {
+                worsening / old_val.abs()
+            } else {
+                0.0
+            }
+        })
+        .sum()
+}
+
+/// Configuration of the front with the best value of the first objective
+fn best_of_front(front: &Vec<ParetoPoint>, objectives: &Vec<EnergyType>) -> MrResult {
+    let mut best = front[0].clone();
+    for point in front.iter().skip(1) {
+        let de = match objectives[0] {
+            EnergyType::throughput => point.objectives[0] - best.objectives[0],
+            EnergyType::latency => -(point.objectives[0] - best.objectives[0]),
+        };
+        if de > 0.0 {
+            best = point.clone();
+        }
+    }
+
+    MrResult {
+        energy: best.objectives[0],
+        state: best.state,
+    }
+}
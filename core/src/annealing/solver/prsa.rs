@@ -24,10 +24,12 @@
 /// *****************************************************************************
 /// ****************************************************************************
 use annealing::solver::Solver;
+use annealing::solver::scheduler::{self, AskTell, Candidate};
 use annealing::problem::Problem;
-use annealing::cooler::{Cooler, StepsCooler, TimeCooler};
+use annealing::cooler;
 use annealing::solver::common;
-use annealing::solver::common::{MrResult, IntermediateResults};
+use annealing::solver::common::MrResult;
+use annealing::solver::checkpoint::{Checkpointer, WorkerCheckpoint};
 use res_emitters;
 use res_emitters::Emitter;
 
@@ -35,392 +37,639 @@ use shared::TunerParameter;
 
 
 use time;
-use CoolingSchedule;
 use EnergyType;
-use pbr;
-use rand;
-use libc;
-use num_cpus;
+use EvaluationMode;
 
-use rand::{Rng, thread_rng};
+use rand::Rng;
 use rand::distributions::{Range, IndependentSample};
 use ansi_term::Colour::Green;
-use std::collections::HashMap;
-use pbr::{ProgressBar, MultiBar};
-use std::thread;
-use std::thread::JoinHandle;
 use State;
-use std::sync::mpsc::channel;
 
 
+/// Number of draws of a mutation of a child that satisfies the constraints on the parameters
+static max_mutation_draws: usize = 100;
+
 #[derive(Debug, Clone)]
 pub struct Prsa {
     pub tuner_params: TunerParameter,
     pub res_emitter: Emitter,
-    pub population_size: usize,
+    pub checkpointer: Checkpointer,
+    temperature: common::Temperature,
+    stagnation: common::Stagnation,
+    acceptance: common::Acceptance,
+    population: common::StatesPool,
+    master: Option<MrResult>,
+    final_best_res: Option<MrResult>,
+    subsequent_rejected: usize,
+    elapsed_steps: usize,
+    accepted: usize,
+    last_checkpoint: usize,
+    start_time: u64,
+    num_workers: usize,
+    generation: Option<Generation>,
+    pending: bool,
+    finished: bool,
 }
 
-impl Solver for Prsa {
-    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
-
-        let cooler = StepsCooler {
-            max_steps: self.tuner_params.max_step,
-            min_temp: self.tuner_params.min_temp,
-            max_temp: self.tuner_params.max_temp.unwrap(),
-        };
-
+/// A generation of the population. Each worker recombines its own chunk of the population.
+#[derive(Debug, Clone)]
+struct Generation {
+    families: Vec<Family>,
+    results: Vec<MrResult>,
+}
 
-        // Generate a Population of specified size with different configurations randomly selected
-        // from the space state
-        let mut population =
-            common::StatesPool::new_with_val(problem.get_population(self.population_size));
+/// Evaluation in progress of a worker: the two parents and then the two children of the current
+/// pair, which are compared in a Boltzmann tournament once all of them have been evaluated
+#[derive(Debug, Clone, PartialEq)]
+enum Phase {
+    Pairing,
+    Parent1,
+    Parent2,
+    Child1,
+    Child2,
+}
 
-        let mut subsequent_rejected = 0;
+#[derive(Debug, Clone)]
+struct Family {
+    problem: Problem,
+    sub_population: Vec<State>,
+    new_sub_population: Vec<State>,
+    pairs_left: usize,
+    phase: Phase,
+    parents: (State, State),
+    children: (State, State),
+    cost_parent_1: f64,
+    cost_parent_2: f64,
+    cost_child_1: f64,
+    cost_child_2: f64,
+    parent_died: bool,
+    child_died: bool,
+    pending: bool,
+    done: bool,
+}
 
-        let mut elapsed_steps = common::SharedGenericCounter::new();
+impl Prsa {
+    pub fn new(tuner_params: TunerParameter, res_emitter: Emitter, checkpointer: Checkpointer) -> Self {
         let mut temperature = common::Temperature::new(
-            self.max_temp,
-            cooler.clone(),
-            self.tuner_params.cooling.clone(),
+            tuner_params.max_temp.unwrap(),
+            cooler::new_cooler(&tuner_params),
+            tuner_params.cooling.clone(),
         );
-        let threads_res = common::ThreadsResults::new();
-
-        /// *********************************************************************************************************
-        let mut start_time = time::precise_time_ns();
-        let mut rng = rand::thread_rng();
-        let mut initial_state = problem.initial_state();
-        let mut nrg = match problem.energy(&initial_state.clone(), 0) {
-            Some(nrg) => nrg,
-            None => panic!("The initial configuration does not allow to calculate the energy"),
+        let stagnation = common::Stagnation::new(&tuner_params, 20);
+        let acceptance = common::Acceptance::new(&tuner_params);
+
+        let mut prsa = Prsa {
+            tuner_params: tuner_params,
+            res_emitter: res_emitter,
+            checkpointer: checkpointer,
+            temperature: temperature.clone(),
+            stagnation: stagnation,
+            acceptance: acceptance,
+            population: common::StatesPool::new(),
+            master: None,
+            final_best_res: None,
+            subsequent_rejected: 0,
+            elapsed_steps: 0,
+            accepted: 0,
+            last_checkpoint: 0,
+            start_time: time::precise_time_ns(),
+            num_workers: 1,
+            generation: None,
+            pending: false,
+            finished: false,
         };
 
-        let mut elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
+        if let Some(ckpt) = prsa.checkpointer.resumed_worker(0) {
+            temperature.restore(&ckpt.temperature);
+            prsa.temperature = temperature;
+            prsa.start_time -= ckpt.temperature.elapsed_time_ns;
+            prsa.population =
+                common::StatesPool::new_with_val(prsa.checkpointer.resumed_population().unwrap());
+            prsa.master = match ckpt.current {
+                Some(master) => Some(master),
+                None => Some(ckpt.best.clone()),
+            };
+            prsa.final_best_res = Some(ckpt.best);
+            prsa.subsequent_rejected = ckpt.subsequent_rejected;
+            prsa.elapsed_steps = ckpt.elapsed_steps;
+            prsa.accepted = ckpt.accepted;
+            prsa.last_checkpoint = ckpt.elapsed_steps;
+        }
 
-        let mut final_best_res = MrResult {
-            energy: nrg,
-            state: initial_state,
-        };
+        prsa
+    }
+
+
+    /// Shuffle the population and divide it among the workers. None if the search is over: the
+    /// search stops when the master state has been rejected more than max_rejected subsequent
+    /// generations.
+    fn start_generation(&mut self, problem: &mut Problem) -> Option<Generation> {
+        // With a wall-clock budget, the search is not limited by the steps
+        if self.tuner_params.max_time.is_none() &&
+            self.elapsed_steps >= self.tuner_params.max_step
+        {
+            return None;
+        }
 
-        // Channel for receiving results from worker threads and send them to the file writer.
-        let (tx, rx) = channel::<IntermediateResults>();
-        let mut results_emitter = Emitter2File::new("0".to_string(), &initial_state);
-        // Spawn the thread that will take care of writing results into a CSV file
-        let (elapsed_steps_c, temperature_c) = (elapsed_steps.clone(), temperature.clone());
-        thread::spawn(move || loop {
-            elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
-            match rx.recv() {
-                Ok(res) => {
-                    results_emitter.send_update(
-                        temperature_c.get(),
-                        elapsed_time,
-                        0.0,
-                        res.last_nrg,
-                        &res.last_state,
-                        res.best_nrg,
-                        &res.best_state,
-                        elapsed_steps_c.get(),
-                    );
+        if self.stagnation.is_reached(self.subsequent_rejected) {
+            println!("{} Convergence Reached!!!", Green.paint("[TUNER]"));
+            return None;
+        }
+
+        if self.temperature.is_expired(self.elapsed_steps) {
+            println!("{} Time Budget Exhausted!!!", Green.paint("[TUNER]"));
+            return None;
+        }
+
+        let elapsed_time = (time::precise_time_ns() - self.start_time) as f64 / 1000000000.0f64;
+        let final_best_res = self.final_best_res.clone().unwrap();
+
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------------------------------------------"));
+        println!(
+            "{} Completed Steps: {:.2} - Percentage of Completion: {:.2}% - Elapsed \
+                  Time: {:.2} s",
+            Green.paint("[TUNER]"),
+            self.elapsed_steps,
+            self.temperature.progress(self.elapsed_steps),
+            elapsed_time
+        );
+        println!(
+            "{} Population Size: {:?} - Subsequent Rejected: {:?} - Current Temperature: \
+                  {:.2}",
+            Green.paint("[TUNER]"),
+            self.population.size(),
+            self.subsequent_rejected,
+            self.temperature.get()
+        );
+        println!(
+            "{} Best State: {:?}",
+            Green.paint("[TUNER]"),
+            final_best_res.state
+        );
+        println!(
+            "{} Best Energy: {:.4}",
+            Green.paint("[TUNER]"),
+            final_best_res.energy
+        );
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------------------------------------------"));
+
+
+        // Shuffle pointers of population elements
+        self.population.shuffle(problem.rng());
+        let generation_step = self.elapsed_steps;
+        let num_workers = self.num_workers;
+
+        // Divide the population in num_workers chunks. The last chunk takes also the
+        // remainder of the division, so that no element of the population gets lost.
+        let chunk_size = (self.population.size() as f64 / num_workers as f64).floor() as usize;
+        let mut chunks: Vec<Vec<State>> = (0..num_workers)
+            .map(|_| Vec::with_capacity(chunk_size))
+            .collect();
+        for i in 0..num_workers {
+            loop {
+                if chunks[i].len() == chunk_size && i < num_workers - 1 {
+                    break;
                 }
-                Err(e) => {} 
+                match self.population.pop() {
+                    Some(v) => {
+                        chunks[i].push(v);
+                    }
+                    None => break,
+                };
             }
-        });
+        }
+
+        let families = chunks
+            .into_iter()
+            .enumerate()
+            .map(|(core, sub_population)| {
+                let mut problem_c = problem.clone();
+                // Each worker of each generation draws from its own stream of random numbers
+                problem_c.reseed(&[generation_step, core]);
+                Family {
+                    problem: problem_c,
+                    pairs_left: sub_population.len() / 2,
+                    new_sub_population: Vec::with_capacity(sub_population.len()),
+                    sub_population: sub_population,
+                    phase: Phase::Pairing,
+                    parents: (State::new(), State::new()),
+                    children: (State::new(), State::new()),
+                    cost_parent_1: -1.0,
+                    cost_parent_2: -1.0,
+                    cost_child_1: -1.0,
+                    cost_child_2: -1.0,
+                    parent_died: false,
+                    child_died: false,
+                    pending: false,
+                    done: false,
+                }
+            })
+            .collect();
+
+        Some(Generation {
+            families: families,
+            results: Vec::new(),
+        })
+    }
+
+
+    /// The best result of the generation replaces the overall best one if it improves it, and
+    /// the master state moves to it with the Metropolis criterion
+    fn end_generation(&mut self, problem: &mut Problem, generation: Generation) {
+        let mut workers_res = generation.results;
 
+        if workers_res.is_empty() {
+            self.subsequent_rejected += 1;
+        } else {
+            let best_subpop_res = eval_best_res(&mut workers_res, self.tuner_params.energy);
+            let final_best_res = self.final_best_res.clone().unwrap();
+            let master = self.master.clone().unwrap();
 
-        'outer: loop {
+            let de_best = match self.tuner_params.energy {
+                EnergyType::throughput => best_subpop_res.energy - final_best_res.energy,
+                EnergyType::latency => -(best_subpop_res.energy - final_best_res.energy), 
+            };
+            if de_best > 0.0 {
+                self.final_best_res = Some(best_subpop_res.clone());
+            }
 
-            let mut mb = MultiBar::new();
+            let de = self.acceptance.delta(
+                problem,
+                &best_subpop_res.state,
+                best_subpop_res.energy,
+                &master.state,
+                master.energy,
+            );
+            let range = Range::new(0.0, 1.0);
+
+            if de > 0.0 || range.ind_sample(problem.rng()) <= (de / self.temperature.get()).exp() {
+                self.master = Some(best_subpop_res);
+                if de > 0.0 {
+                    self.subsequent_rejected = 0;
+                }
+            } else {
+                self.subsequent_rejected += 1;
+            }
+        }
 
+        if self.checkpointer.is_due(self.last_checkpoint, self.elapsed_steps) {
+            self.checkpointer.save(
+                0,
+                WorkerCheckpoint {
+                    current: self.master.clone(),
+                    best: self.final_best_res.clone().unwrap(),
+                    elapsed_steps: self.elapsed_steps,
+                    accepted: self.accepted,
+                    subsequent_rejected: self.subsequent_rejected,
+                    temperature: self.temperature.snapshot(),
+                    rng_draws: problem.rng_draws(),
+                },
+                self.population.get_coll(),
+            );
+            self.last_checkpoint = self.elapsed_steps;
+        }
+    }
+}
 
-            if elapsed_steps.get() > self.tuner_params.max_step || subsequent_rejected > 20 {
-                break 'outer;
+impl Solver for Prsa {
+    /// With the racing evaluation the candidates of each ask are raced against each other
+    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+        match self.tuner_params.evaluation {
+            EvaluationMode::racing => {
+                let tuner_params = self.tuner_params.clone();
+                scheduler::run_batches(self, problem, &tuner_params, num_workers)
             }
+            EvaluationMode::full => scheduler::run(self, problem, num_workers),
+        }
+    }
+}
 
-            elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
+impl AskTell for Prsa {
+    fn start(&mut self, problem: &mut Problem, num_workers: usize) {
+        self.num_workers = num_workers;
 
-            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------------------------------------------"));
+        if self.final_best_res.is_none() {
+            println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
             println!(
-                "{} Completed Steps: {:.2} - Percentage of Completion: {:.2}% - Estimated \
-                      time to Complete: {:.2} Hrs",
-                Green.paint("[TUNER]"),
-                elapsed_steps.get(),
-                elapsed_steps.get() as f64 / (self.tuner_params.max_step as f64) * 100.0,
-                elapsed_time
+                "{} Initialization Phase: Evaluation of Energy for Default Parameters",
+                Green.paint("[TUNER]")
             );
-            println!(
-                "{} Best Energy: {:.4}",
-                Green.paint("[TUNER]"),
-                final_best_res.energy
+            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+            // Generate a Population of specified size with different configurations
+            // randomly selected from the space state
+            self.population = common::StatesPool::new_with_val(
+                problem.get_population(self.tuner_params.population_size),
             );
-            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------------------------------------------"));
+        }
 
+        // A resumed run continues the random stream of the interrupted one
+        if let Some(ckpt) = self.checkpointer.resumed_worker(0) {
+            problem.restore_rng(ckpt.rng_draws);
+        }
+    }
 
-            // Shuffle pointers of population elements
-            population.shuffle();
+    /// Each free worker proposes the next member of its family to evaluate. Once all the
+    /// workers recombined their sub-population, a new generation is started.
+    fn ask(&mut self, problem: &mut Problem, max_candidates: usize) -> Vec<Candidate> {
+        if self.finished || self.pending {
+            return Vec::new();
+        }
 
-            let mut th_handlers: Vec<JoinHandle<_>> = Vec::with_capacity(num_workers);
+        if self.final_best_res.is_none() {
+            self.pending = true;
+            return vec![
+                Candidate {
+                    state: problem.initial_state(),
+                    slot: 0,
+                },
+            ];
+        }
 
-            // Divide the population in num_workers chunks
-            let chunk_size = (self.population_size as f64 / num_workers as f64).floor() as usize;
-            let mut chunks: Vec<Vec<State>> = (0..num_workers)
-                .map(|_| Vec::with_capacity(num_workers))
-                .collect();
-            for i in 0..num_workers {
-                for j in 0..chunk_size {
-                    match population.pop() {
-                        Some(v) => {
-                            chunks[i].push(v);
-                        }
-                        None => break,
-                    };
+        loop {
+            if self.generation.is_none() {
+                match self.start_generation(problem) {
+                    Some(generation) => self.generation = Some(generation),
+                    None => {
+                        self.finished = true;
+                        return Vec::new();
+                    }
                 }
             }
 
+            let mut candidates = Vec::new();
+            let mut generation_over = true;
+            {
+                let generation = self.generation.as_mut().unwrap();
+                for (core, family) in generation.families.iter_mut().enumerate() {
+                    if family.pending {
+                        generation_over = false;
+                        continue;
+                    }
+                    if family.done {
+                        continue;
+                    }
+                    if candidates.len() >= max_candidates {
+                        generation_over = false;
+                        continue;
+                    }
+
+                    if family.phase == Phase::Pairing {
+                        if family.pairs_left == 0 ||
+                            self.temperature.is_expired(self.elapsed_steps)
+                        {
+                            // An odd-sized sub-population leaves one element without a partner:
+                            // it survives to the next generation as it is.
+                            family.new_sub_population.append(&mut family.sub_population);
+                            self.population.push_bulk(&mut family.new_sub_population);
+                            family.done = true;
+                            continue;
+                        }
 
-            for core in 0..num_workers {
+                        family.parents = get_parents(&mut family.sub_population, family.problem.rng());
+                        family.parent_died = false;
+                        family.child_died = false;
+                        family.phase = Phase::Parent1;
+                    }
 
-                let sub_population = chunks[core].clone();
+                    let state = match family.phase {
+                        Phase::Parent1 => family.parents.0.clone(),
+                        Phase::Parent2 => family.parents.1.clone(),
+                        Phase::Child1 => family.children.0.clone(),
+                        Phase::Child2 => family.children.1.clone(),
+                        Phase::Pairing => unreachable!(),
+                    };
 
+                    family.pending = true;
+                    generation_over = false;
+                    candidates.push(Candidate {
+                        state: state,
+                        slot: core,
+                    });
+                }
+            }
 
-                let mut problem_c = problem.clone();
-                let elapsed_steps_c = elapsed_steps.clone();
-
-                let nrg_type = self.clone().tuner_params.energy;
-                let max_steps = self.clone().tuner_params.max_steps;
-                let cooling_sched = self.clone().cooling;
-                let cooler_c = cooler.clone();
-                let population_c = population.clone();
-                let temperature_c = temperature.clone();
-                let sub_population_c = sub_population.clone();
-                let threads_res_c = threads_res.clone();
-                let len_subpop = sub_population_c.len();
-
-
-
-                let mut pb = mb.create_bar((len_subpop / 2) as u64);
-                let tx_c = tx.clone();
-                let mut final_best_res_c = final_best_res.clone();
-                /*********************************************************************************************************/
-                th_handlers.push(thread::spawn(move || {
-                    pb.show_message = true;
-                    let mut rng_c = rand::thread_rng();
-
-                    let mut new_sub_population: Vec<State> = Vec::with_capacity(len_subpop);
-
-                    let mut results: Vec<common::MrResult> = Vec::new();
-
-                    for step in 0..len_subpop / 2 {
-                        pb.message(&format!(
-                            "TID [{}] - Sub-Population Exploration Status - ",
-                            core
-                        ));
-
-                        let mut parent_died = false;
-                        let mut child_died = false;
-
-                        let (parent_1, parent_2) =
-                            get_parents(&mut sub_population_c.to_vec(), rng_c.clone());
-
-                        let (cost_parent_1, cost_parent_2) =
-                            match problem_c.energy(&parent_1, core) {
-                                Some(c_parent_1) => {
-                                    let intermediate_res = IntermediateResults {
-                                        last_nrg: c_parent_1,
-                                        last_state: parent_1.clone(),
-                                        best_nrg: final_best_res_c.clone().energy,
-                                        best_state: final_best_res_c.clone().state,
-                                    };
-                                    tx_c.send(intermediate_res);
-
-                                    let c_parent_2 = match problem_c.energy(&parent_2, core) {
-                                        Some(c_p_2) => {
-                                            let intermediate_res = IntermediateResults {
-                                                last_nrg: c_p_2,
-                                                last_state: parent_2.clone(),
-                                                best_nrg: final_best_res_c.clone().energy,
-                                                best_state: final_best_res_c.clone().state,
-                                            };
-                                            tx_c.send(intermediate_res);
-
-                                            c_p_2
-                                        }
-                                        None => {
-                                            parent_died = true;
-                                            -1.0
-                                        }
-                                    };
-
-                                    (c_parent_1, c_parent_2)
-                                }
-                                None => {
-                                    parent_died = true;
-                                    (-1.0, -1.0)
-                                }
-                            };
-
-
-
-                        let (mut child_1, mut child_2) =
-                            generate_children(&mut problem_c, &parent_1, &parent_2, rng_c.clone());
-
-                        let (cost_child_1, cost_child_2) = match problem_c.energy(&child_1, core) {
-                            Some(c_child_1) => {
-                                let intermediate_res = IntermediateResults {
-                                    last_nrg: c_child_1,
-                                    last_state: child_1.clone(),
-                                    best_nrg: final_best_res_c.clone().energy,
-                                    best_state: final_best_res_c.clone().state,
-                                };
-                                tx_c.send(intermediate_res);
-
-                                let c_child_2 = match problem_c.energy(&child_2, core) {
-                                    Some(c_c_2) => {
-                                        let intermediate_res = IntermediateResults {
-                                            last_nrg: c_c_2,
-                                            last_state: child_2.clone(),
-                                            best_nrg: final_best_res_c.clone().energy,
-                                            best_state: final_best_res_c.clone().state,
-                                        };
-                                        tx_c.send(intermediate_res);
-
-                                        c_c_2
-                                    }
-                                    None => {
-                                        child_died = true;
-                                        -1.0
-                                    }
-                                };
-
-                                (c_child_1, c_child_2)
-                            }
-                            None => {
-                                child_died = true;
-                                (-1.0, -1.0)
-                            }
-                        };
-
-                        // Compare cost of parent_1 with cost of child_2
-                        let range = Range::new(0.0, 1.0);
-                        let (best_state_1, best_cost_1) = {
-                            if parent_died == false && child_died == false {
-                                let de_p1_c2 = match nrg_type {
-                                    EnergyType::throughput => cost_parent_1 - cost_child_2,
-                                    EnergyType::latency => -(cost_parent_1 - cost_child_2), 
-                                };
-                                if range.ind_sample(&mut rng_c) <
-                                    1.0 / (1.0 + (de_p1_c2 / temperature_c.get()).exp())
-                                {
-                                    (parent_1.clone(), cost_parent_1)
-                                } else {
-                                    (child_2.clone(), cost_child_2)
-                                }
-                            } else if parent_died == true {
-                                (child_1.clone(), cost_child_1)
-                            } else {
-                                (parent_1.clone(), cost_parent_1)
-                            }
-
-                        };
-
-
-                        let (best_state_2, best_cost_2) = {
-                            if parent_died == false && child_died == false {
-                                // Compare cost of parent_2 with cost of child_1
-                                let de_p2_c1 = match nrg_type {
-                                    EnergyType::throughput => cost_parent_2 - cost_child_1,
-                                    EnergyType::latency => -(cost_parent_2 - cost_child_1), 
-                                };
-                                if range.ind_sample(&mut rng_c) <
-                                    1.0 / (1.0 + (de_p2_c1 / temperature_c.get()).exp())
-                                {
-                                    (parent_2.clone(), cost_parent_2)
-                                } else {
-                                    (child_1.clone(), cost_child_1)
-                                }
-                            } else if parent_died == true {
-                                (child_2.clone(), cost_child_2)
-                            } else {
-                                (parent_2.clone(), cost_parent_2)
-                            }
-
-                        };
-
-
-                        new_sub_population.push(best_state_1.clone());
-                        new_sub_population.push(best_state_2.clone());
-
-                        let (iter_best_state, iter_best_cost) = match nrg_type {
-                            EnergyType::throughput => {
-                                if best_cost_1 > best_cost_2 {
-                                    (best_state_1, best_cost_1)
-                                } else {
-                                    (best_state_2, best_cost_2)
-                                }
-                            }
-                            EnergyType::latency => {
-                                if best_cost_1 > best_cost_2 {
-                                    (best_state_2, best_cost_2)
-                                } else {
-                                    (best_state_1, best_cost_1)
-                                }
-                            } 
-                        };
-
-                        results.push(common::MrResult {
-                            energy: iter_best_cost,
-                            state: iter_best_state.clone(),
-                        });
-
-
-
-                        temperature_c.update(elapsed_steps_c.get());
-                        elapsed_steps_c.increment();
-                        pb.inc();
+            if !generation_over {
+                return candidates;
+            }
 
-                    }
+            let generation = self.generation.take().unwrap();
+            self.end_generation(problem, generation);
+        }
+    }
 
-                    let chunk_best_res = eval_best_res(&mut results, nrg_type);
-                    threads_res_c.push(chunk_best_res);
+    fn tell(&mut self, problem: &mut Problem, candidate: Candidate, nrg: Option<f64>) {
 
-                    pb.finish_print(&format!("Child Thread [{}] Terminated the Execution", core));
+        if self.final_best_res.is_none() {
+            // Evaluation of the initial state
+            self.pending = false;
+            let nrg = match nrg {
+                Some(nrg) => nrg,
+                None => {
+                    panic!("The initial configuration does not allow to calculate the energy")
+                }
+            };
+            self.start_time = time::precise_time_ns();
+            self.master = Some(MrResult {
+                energy: nrg,
+                state: candidate.state.clone(),
+            });
+            self.final_best_res = Some(MrResult {
+                energy: nrg,
+                state: candidate.state,
+            });
+            return;
+        }
 
-                    population_c.push_bulk(&mut new_sub_population);
-                }));
+        let core = candidate.slot;
+        let crossover_rate = self.tuner_params.crossover_rate;
+        let mutation_rate = self.tuner_params.mutation_rate;
+
+        let generation = self.generation.as_mut().unwrap();
+        let family = &mut generation.families[core];
+        family.pending = false;
+
+        if let Some(nrg) = nrg {
+            let final_best_res = self.final_best_res.as_ref().unwrap();
+            let elapsed_time = (time::precise_time_ns() - self.start_time) as f64 /
+                1000000000.0f64;
+            self.res_emitter.send_update(
+                self.temperature.get(),
+                elapsed_time,
+                0.0,
+                nrg,
+                &candidate.state,
+                problem.repetitions(&candidate.state),
+                final_best_res.energy,
+                &final_best_res.state,
+                self.elapsed_steps,
+                core,
+            );
+        }
 
+        // A cost equal to -1.0 means that the state could not be evaluated. The failure of the
+        // first parent (child) stops the evaluation of the second one.
+        let next_phase = match (family.phase.clone(), nrg) {
+            (Phase::Parent1, Some(c_parent_1)) => {
+                family.cost_parent_1 = c_parent_1;
+                Phase::Parent2
+            }
+            (Phase::Parent1, None) => {
+                family.parent_died = true;
+                family.cost_parent_1 = -1.0;
+                family.cost_parent_2 = -1.0;
+                Phase::Child1
+            }
+            (Phase::Parent2, Some(c_parent_2)) => {
+                family.cost_parent_2 = c_parent_2;
+                Phase::Child1
             }
+            (Phase::Parent2, None) => {
+                family.parent_died = true;
+                family.cost_parent_2 = -1.0;
+                Phase::Child1
+            }
+            (Phase::Child1, Some(c_child_1)) => {
+                family.cost_child_1 = c_child_1;
+                Phase::Child2
+            }
+            (Phase::Child1, None) => {
+                family.child_died = true;
+                family.cost_child_1 = -1.0;
+                family.cost_child_2 = -1.0;
+                Phase::Pairing
+            }
+            (Phase::Child2, Some(c_child_2)) => {
+                family.cost_child_2 = c_child_2;
+                Phase::Pairing
+            }
+            (Phase::Child2, None) => {
+                family.child_died = true;
+                family.cost_child_2 = -1.0;
+                Phase::Pairing
+            }
+            (Phase::Pairing, _) => unreachable!(),
+        };
 
-            mb.listen();
+        if next_phase == Phase::Child1 {
+            family.children = generate_children(
+                &mut family.problem,
+                &family.parents.0,
+                &family.parents.1,
+                crossover_rate,
+                mutation_rate,
+            );
+        }
 
-            for h in th_handlers {
-                h.join().unwrap();
-            }
+        if next_phase == Phase::Pairing {
+            let child_accepted =
+                tournament(family, &self.acceptance, self.temperature.get(), &mut generation.results);
+            family.pairs_left -= 1;
 
-            let best_subpop_res =
-                eval_best_res(&mut threads_res.get_coll(), self.tuner_params.energy);
+            if child_accepted {
+                self.accepted += 1;
+            }
 
+            self.elapsed_steps += 1;
+            self.temperature.update(self.elapsed_steps, self.accepted);
+        }
 
-            let de = match self.tuner_params.energy {
-                EnergyType::throughput => best_subpop_res.energy - final_best_res.energy,
-                EnergyType::latency => -(best_subpop_res.energy - final_best_res.energy), 
-            };
+        family.phase = next_phase;
+    }
 
-            let range = Range::new(0.0, 1.0);
+    fn best(&self) -> MrResult {
+        self.final_best_res.clone().unwrap()
+    }
+}
 
-            if de > 0.0 || range.ind_sample(&mut rng) <= (de / temperature.get()).exp() {
-                final_best_res = best_subpop_res;
-                if de > 0.0 {
-                    subsequent_rejected += 1;
-                }
 
+/// Boltzmann tournament between the parents and the children of the family. The winners join
+/// the new sub-population and their results are collected. Return true if a child won.
+fn tournament(
+    family: &mut Family,
+    acceptance: &common::Acceptance,
+    temperature: f64,
+    results: &mut Vec<MrResult>,
+) -> bool {
+    let (ref parent_1, ref parent_2) = family.parents;
+    let (ref child_1, ref child_2) = family.children;
+    let (cost_parent_1, cost_parent_2) = (family.cost_parent_1, family.cost_parent_2);
+    let (cost_child_1, cost_child_2) = (family.cost_child_1, family.cost_child_2);
+    let (parent_died, child_died) = (family.parent_died, family.child_died);
+
+    // Compare cost of parent_1 with cost of child_2
+    let range = Range::new(0.0, 1.0);
+    let mut child_accepted = false;
+    let (best_state_1, best_cost_1) = {
+        if parent_died == false && child_died == false {
+            let de_p1_c2 = acceptance.delta(
+                &family.problem,
+                parent_1,
+                cost_parent_1,
+                child_2,
+                cost_child_2,
+            );
+            if range.ind_sample(family.problem.rng()) <
+                1.0 / (1.0 + (-de_p1_c2 / temperature).exp())
+            {
+                (parent_1.clone(), cost_parent_1)
             } else {
-                subsequent_rejected = 0;
+                child_accepted = true;
+                (child_2.clone(), cost_child_2)
             }
+        } else if parent_died == true {
+            (child_1.clone(), cost_child_1)
+        } else {
+            (parent_1.clone(), cost_parent_1)
+        }
 
+    };
 
+
+    let (best_state_2, best_cost_2) = {
+        if parent_died == false && child_died == false {
+            // Compare cost of parent_2 with cost of child_1
+            let de_p2_c1 = acceptance.delta(
+                &family.problem,
+                parent_2,
+                cost_parent_2,
+                child_1,
+                cost_child_1,
+            );
+            if range.ind_sample(family.problem.rng()) <
+                1.0 / (1.0 + (-de_p2_c1 / temperature).exp())
+            {
+                (parent_2.clone(), cost_parent_2)
+            } else {
+                child_accepted = true;
+                (child_1.clone(), cost_child_1)
+            }
+        } else if parent_died == true {
+            (child_2.clone(), cost_child_2)
+        } else {
+            (parent_2.clone(), cost_parent_2)
         }
 
+    };
+
+
+    family.new_sub_population.push(best_state_1.clone());
+    family.new_sub_population.push(best_state_2.clone());
 
-        final_best_res
+    if best_cost_1 >= 0.0 {
+        results.push(common::MrResult {
+            energy: best_cost_1,
+            state: best_state_1,
+        });
     }
+    if best_cost_2 >= 0.0 {
+        results.push(common::MrResult {
+            energy: best_cost_2,
+            state: best_state_2,
+        });
+    }
+
+    child_accepted
 }
 
 fn eval_best_res(workers_res: &mut Vec<MrResult>, nrg_type: EnergyType) -> MrResult {
@@ -449,9 +698,9 @@ fn eval_best_res(workers_res: &mut Vec<MrResult>, nrg_type: EnergyType) -> MrRes
 }
 
 
-fn get_parents(sub_population: &mut Vec<State>, mut rng: rand::ThreadRng) -> (State, State) {
+fn get_parents<R: Rng>(sub_population: &mut Vec<State>, rng: &mut R) -> (State, State) {
     let len = sub_population.len();
-    let parent_1 = sub_population.swap_remove(rng.gen_range(0, len - 1));
+    let parent_1 = sub_population.swap_remove(rng.gen_range(0, len));
     let parent_2 = sub_population.swap_remove(rng.gen_range(0, len - 1));
     return (parent_1, parent_2);
 }
@@ -460,57 +709,75 @@ fn generate_children(
     problem: &mut Problem,
     parent_1: &State,
     parent_2: &State,
-    mut rng: rand::ThreadRng,
+    crossover_rate: f64,
+    mutation_rate: f64,
 ) -> (State, State) {
 
-    // Enforce Crossover between parent_1 and parent_2 configurations
-    let cutting_point = ((0.4 * parent_1.len() as f64).floor()) as usize;
-
-    let mut child_1 = HashMap::with_capacity(parent_1.len());
-    let mut child_2 = HashMap::with_capacity(parent_1.len());
+    let range = Range::new(0.0, 1.0);
 
-    let mut p1_iter = parent_1.iter();
+    let mut child_1 = parent_1.clone();
+    let mut child_2 = parent_2.clone();
 
-    for i in 0..parent_1.len() {
-        let (mut key_p1, mut val_p1) = p1_iter.next().unwrap();
-        let mut key_p2 = key_p1;
-        let mut val_p2 = parent_2.get(key_p2).unwrap();
+    // Enforce Crossover between parent_1 and parent_2 configurations
+    if range.ind_sample(problem.rng()) < crossover_rate {
+        let cutting_point = ((0.4 * parent_1.len() as f64).floor()) as usize;
 
+        // The genes are taken in alphabetical order, the order of the parameters of a State
+        for (i, (key_p1, val_p1)) in parent_1.iter().enumerate() {
+            let val_p2 = parent_2.get(key_p1).unwrap();
 
-        if i < cutting_point {
-            child_1.insert(key_p1.clone(), val_p1.clone());
-            child_2.insert(key_p2.clone(), val_p2.clone());
-        } else {
-            child_1.insert(key_p2.clone(), val_p2.clone());
-            child_2.insert(key_p1.clone(), val_p1.clone());
+            if i < cutting_point {
+                child_1.insert(key_p1.clone(), val_p1.clone());
+                child_2.insert(key_p1.clone(), val_p2.clone());
+            } else {
+                child_1.insert(key_p1.clone(), val_p2.clone());
+                child_2.insert(key_p1.clone(), val_p1.clone());
+            }
         }
     }
 
-    // Enforce Uniform Mutation on Child_1: This operator replaces the value of the chosen "gene" (configuration parameter) with a
-    // uniform random value selected between the upper and lower bounds for that gene (into the space state of the configuration parameter).
-    let mut keys: Vec<_> = child_1.keys().map(|arg| arg.clone()).collect();
-
-    let keys_c = keys.clone();
-    let mut random_gene = rng.choose(&keys_c).unwrap();
-    let mut gen_space_state = &problem.params_configurator.params_space_state.get(
-        random_gene,
-    );
-
-    let mut new_value = rng.choose(&*gen_space_state.unwrap()).unwrap();
-    *(child_1).get_mut(random_gene).unwrap() = *new_value;
-
-
-    // Enforce Mutation on Child_2
-    keys = child_2.keys().map(|arg| arg.clone()).collect();
-    random_gene = rng.choose(&keys).unwrap();
+    // The crossover may mix the parameters of a parent with children of the other that are
+    // inactive in the child
+    child_1 = problem.canonical(&child_1);
+    child_2 = problem.canonical(&child_2);
 
-    let mut gen_space_state_2 = &problem.params_configurator.params_space_state.get(
-        random_gene,
-    );
-
-    new_value = rng.choose(&*gen_space_state_2.unwrap()).unwrap();
-    *(child_2).get_mut(random_gene).unwrap() = *new_value;
+    // A child that does not satisfy the constraints on the parameters is replaced by its parent
+    if !problem.is_valid(&child_1) {
+        child_1 = parent_1.clone();
+    }
+    if !problem.is_valid(&child_2) {
+        child_2 = parent_2.clone();
+    }
 
+    // Enforce Uniform Mutation on the children: This operator replaces the value of the chosen "gene" (configuration parameter) with a
+    // uniform random value selected between the upper and lower bounds for that gene (into the space state of the configuration parameter).
+    // A mutation that violates the constraints is drawn again, up to max_mutation_draws times.
+    // Only the active genes mutate.
+    for child in vec![&mut child_1, &mut child_2] {
+        if range.ind_sample(problem.rng()) < mutation_rate {
+            let mut keys: Vec<String> = problem
+                .params_configurator
+                .active_params(child)
+                .into_iter()
+                .collect();
+            keys.sort();
+            for _ in 0..max_mutation_draws {
+                // A child without active parameters has no gene to mutate and is kept as it is
+                let random_gene = match problem.rng().choose(&keys) {
+                    Some(gene) => gene.clone(),
+                    None => break,
+                };
+                let new_value = problem.params_configurator.get_rand_param_value(&random_gene);
+                let mut mutant = child.clone();
+                *(mutant).get_mut(&random_gene).unwrap() = new_value;
+                let mutant = problem.canonical(&mutant);
+                if problem.is_valid(&mutant) {
+                    *child = mutant;
+                    break;
+                }
+            }
+        }
+    }
 
 
     return (child_1, child_2);
@@ -0,0 +1,412 @@
+/// ///////////////////////////////////////////////////////////////////////////
+///  File: annealing/solver/ptsa.rs
+/// ///////////////////////////////////////////////////////////////////////////
+///  Copyright 2017 Giovanni Mazzeo
+///
+///  Licensed under the Apache License, Version 2.0 (the "License");
+///  you may not use this file except in compliance with the License.
+///  You may obtain a copy of the License at
+///
+///      http://www.apache.org/licenses/LICENSE-2.0
+///
+///  Unless required by applicable law or agreed to in writing, software
+///  distributed under the License is distributed on an "AS IS" BASIS,
+///  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+///  See the License for the specific language governing permissions and
+///  limitations under the License.
+/// ///////////////////////////////////////////////////////////////////////////
+
+/// ****************************************************************************
+/// *****************************************************************************
+/// **
+/// Parallel Tempering Simulated Annealing (PTSA)
+/// *
+/// *****************************************************************************
+/// ****************************************************************************
+use annealing::solver::Solver;
+use annealing::problem::Problem;
+use annealing::cooler;
+use annealing::solver::common;
+use annealing::solver::common::{MrResult, IntermediateResults};
+use annealing::solver::checkpoint::{Checkpointer, WorkerCheckpoint};
+use res_emitters::Emitter;
+
+use shared::TunerParameter;
+
+use time;
+use EnergyType;
+use State;
+
+use rand::distributions::{Range, IndependentSample};
+use ansi_term::Colour::Green;
+use std::mem;
+use std::thread;
+use std::sync::mpsc::channel;
+
+
+#[derive(Debug, Clone)]
+pub struct Ptsa {
+    pub tuner_params: TunerParameter,
+    pub res_emitter: Emitter,
+    pub checkpointer: Checkpointer,
+}
+
+/// A replica explores the space state at the fixed temperature of its rung of the ladder
+#[derive(Debug, Clone)]
+struct Replica {
+    state: State,
+    energy: f64,
+    best: MrResult,
+    accepted: usize,
+    temperature: common::Temperature,
+}
+
+impl Solver for Ptsa {
+    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+
+        println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+        println!(
+            "{} Initialization Phase: Evaluation of Energy for Default Parameters",
+            Green.paint("[TUNER]")
+        );
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+        let mut start_time = time::precise_time_ns();
+        let range = Range::new(0.0, 1.0);
+        let acceptance = common::Acceptance::new(&self.tuner_params);
+
+        let ladder = temperature_ladder(
+            self.tuner_params.min_temp.unwrap(),
+            self.tuner_params.max_temp.unwrap(),
+            num_workers,
+        );
+        let cooler = cooler::new_cooler(&self.tuner_params);
+
+        // Creation of the replicas. The coldest one starts from the default state given by the
+        // user, the other ones from random states. Each replica is evaluated by its own agent.
+        let resumed = self.checkpointer.resumed_worker(0);
+        let initial_state = problem.initial_state();
+        let handles: Vec<_> = (0..num_workers)
+            .map(|tid| {
+                let mut problem_c = problem.clone();
+                let mut temperature =
+                    common::Temperature::new(ladder[tid], cooler.clone(), self.tuner_params.cooling.clone());
+                let resumed_replica = self.checkpointer.resumed_worker(tid);
+                let state = if tid == num_workers - 1 {
+                    initial_state.clone()
+                } else {
+                    problem.rand_state()
+                };
+
+                thread::spawn(move || match resumed_replica {
+                    Some(ckpt) => {
+                        temperature.restore(&ckpt.temperature);
+                        let current = ckpt.current.unwrap();
+                        Replica {
+                            state: current.state,
+                            energy: current.energy,
+                            best: ckpt.best,
+                            accepted: ckpt.accepted,
+                            temperature: temperature,
+                        }
+                    }
+                    None => {
+                        let energy = match problem_c.energy(&state, tid) {
+                            Some(nrg) => nrg,
+                            None => {
+                                panic!("The initial configuration does not allow to calculate the energy")
+                            }
+                        };
+                        Replica {
+                            state: state.clone(),
+                            energy: energy,
+                            best: MrResult {
+                                energy: energy,
+                                state: state,
+                            },
+                            accepted: 0,
+                            temperature: temperature,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut replicas: Vec<Replica> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        let mut best = replicas[0].best.clone();
+        for replica in replicas.iter() {
+            if improvement(self.tuner_params.energy, replica.best.energy, best.energy) > 0.0 {
+                best = replica.best.clone();
+            }
+        }
+
+        let elapsed_steps = match resumed {
+            Some(ref ckpt) => {
+                start_time -= ckpt.temperature.elapsed_time_ns;
+                // The swaps continue the random stream of the interrupted run
+                problem.restore_rng(ckpt.rng_draws);
+                common::SharedGenericCounter::new_with_val(ckpt.elapsed_steps)
+            }
+            None => common::SharedGenericCounter::new(),
+        };
+        let mut last_checkpoint = elapsed_steps.get();
+        let mut swaps_attempted = vec![0; num_workers];
+        let mut swaps_accepted = vec![0; num_workers];
+
+
+        // Channel for receiving results from worker threads and send them to the file writer.
+        let (tx, rx) = channel::<IntermediateResults>();
+        // Spawn the thread that will take care of writing results into a CSV file
+        let (elapsed_steps_c, ladder_c) = (elapsed_steps.clone(), ladder.clone());
+        let mut res_emitter = self.clone().res_emitter;
+        thread::spawn(move || loop {
+            let elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
+            match rx.recv() {
+                Ok(res) => {
+                    res_emitter.send_update(
+                        ladder_c[res.tid],
+                        elapsed_time,
+                        0.0,
+                        res.last_nrg,
+                        &res.last_state,
+                        res.last_reps,
+                        res.best_nrg,
+                        &res.best_state,
+                        elapsed_steps_c.get(),
+                        res.tid,
+                    );
+                }
+                Err(e) => {}
+            }
+        });
+
+
+        // *********************************************************************************************************
+        start_time = time::precise_time_ns();
+        if let Some(ref ckpt) = resumed {
+            start_time -= ckpt.temperature.elapsed_time_ns;
+        }
+        let mut round = 0;
+        'outer: loop {
+
+            // With a wall-clock budget, the search is not limited by the steps
+            if self.tuner_params.max_time.is_none() &&
+                elapsed_steps.get() > self.tuner_params.max_step
+            {
+                break 'outer;
+            }
+
+            if replicas[0].temperature.is_expired(elapsed_steps.get()) {
+                println!("{} Time Budget Exhausted!!!", Green.paint("[TUNER]"));
+                break 'outer;
+            }
+
+            let elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
+
+            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+            println!(
+                "{} Completed Steps: {:.2} - Percentage of Completion: {:.2}% - Elapsed \
+                      Time: {:.2} s",
+                Green.paint("[TUNER]"),
+                elapsed_steps.get(),
+                replicas[0].temperature.progress(elapsed_steps.get()),
+                elapsed_time
+            );
+            for (tid, replica) in replicas.iter().enumerate() {
+                println!(
+                    "{} Replica [{}] - Temperature: {:.2} - Accepted: {:?} - Swaps Accepted: \
+                          {:?}/{:?} - Energy: {:.4}",
+                    Green.paint("[TUNER]"),
+                    tid,
+                    replica.temperature.get(),
+                    replica.accepted,
+                    swaps_accepted[tid],
+                    swaps_attempted[tid],
+                    replica.energy
+                );
+            }
+            println!("{} Best Energy: {:.4}", Green.paint("[TUNER]"), best.energy);
+            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+
+            /// *********************************************************************************************************
+            // Each replica performs swap_period steps of annealing at its own temperature. The
+            // neighborhoods are generated from the step reached at the beginning of the round, so
+            // that they do not depend on the progress of the other replicas.
+            let round_step = elapsed_steps.get();
+            let handles: Vec<_> = replicas
+                .drain(..)
+                .enumerate()
+                .map(|(tid, mut replica)| {
+                    let mut problem_c = problem.clone();
+                    problem_c.reseed(&[round_step, tid]);
+                    let elapsed_steps_c = elapsed_steps.clone();
+                    let tx_c = tx.clone();
+                    let acceptance_c = acceptance.clone();
+                    let nrg_type = self.tuner_params.energy;
+                    let max_steps = self.tuner_params.max_step;
+                    let max_time = self.tuner_params.max_time;
+                    let max_temp = self.tuner_params.max_temp.unwrap();
+                    let swap_period = self.tuner_params.swap_period;
+
+                    thread::spawn(move || {
+                        let range = Range::new(0.0, 1.0);
+
+                        for _ in 0..swap_period {
+                            if (max_time.is_none() && elapsed_steps_c.get() > max_steps) ||
+                                replica.temperature.is_expired(elapsed_steps_c.get())
+                            {
+                                break;
+                            }
+
+                            let next_state = problem_c.new_state(
+                                &replica.state,
+                                max_steps,
+                                round_step,
+                                replica.temperature.get() / max_temp,
+                            );
+
+                            match problem_c.energy(&next_state, tid) {
+                                Some(new_energy) => {
+                                    let de = acceptance_c.delta(
+                                        &problem_c,
+                                        &next_state,
+                                        new_energy,
+                                        &replica.state,
+                                        replica.energy,
+                                    );
+
+                                    if de > 0.0 ||
+                                        range.ind_sample(problem_c.rng()) <=
+                                            (de / replica.temperature.get()).exp()
+                                    {
+                                        replica.accepted += 1;
+                                        replica.state = next_state.clone();
+                                        replica.energy = new_energy;
+
+                                        if improvement(nrg_type, new_energy, replica.best.energy) > 0.0 {
+                                            replica.best = MrResult {
+                                                energy: new_energy,
+                                                state: next_state.clone(),
+                                            };
+                                        }
+                                    }
+
+                                    tx_c.send(IntermediateResults {
+                                        last_nrg: new_energy,
+                                        last_reps: problem_c.repetitions(&next_state),
+                                        last_state: next_state,
+                                        best_nrg: replica.energy,
+                                        best_state: replica.state.clone(),
+                                        tid: tid,
+                                    });
+                                }
+                                None => {
+                                    println!(
+                                        "{} The current configuration parameters cannot be \
+                                              evaluated. Skip!",
+                                        Green.paint("[TUNER]")
+                                    );
+                                }
+                            }
+
+                            elapsed_steps_c.increment();
+                        }
+
+                        replica
+                    })
+                })
+                .collect();
+
+            replicas = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+            for replica in replicas.iter() {
+                if improvement(self.tuner_params.energy, replica.best.energy, best.energy) > 0.0 {
+                    best = replica.best.clone();
+                }
+            }
+
+
+            /// *********************************************************************************************************
+            // Exchange the states of neighbouring replicas according to the Metropolis criterion.
+            // Even and odd pairs of rungs are alternated at every round.
+            let elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
+            let mut i = round % 2;
+            while i + 1 < replicas.len() {
+                let (t_i, t_j) = (replicas[i].temperature.get(), replicas[i + 1].temperature.get());
+                let delta = acceptance.delta(
+                    problem,
+                    &replicas[i + 1].state,
+                    replicas[i + 1].energy,
+                    &replicas[i].state,
+                    replicas[i].energy,
+                ) * (1.0 / t_i - 1.0 / t_j);
+
+                swaps_attempted[i] += 1;
+                if delta >= 0.0 || range.ind_sample(problem.rng()) <= delta.exp() {
+                    swaps_accepted[i] += 1;
+
+                    let (left, right) = replicas.split_at_mut(i + 1);
+                    mem::swap(&mut left[i].state, &mut right[0].state);
+                    mem::swap(&mut left[i].energy, &mut right[0].energy);
+
+                    self.res_emitter.send_event(
+                        "swap",
+                        t_i,
+                        elapsed_time,
+                        elapsed_steps.get(),
+                        i,
+                    );
+                }
+                i += 2;
+            }
+            round += 1;
+
+            if self.checkpointer.is_due(last_checkpoint, elapsed_steps.get()) {
+                for (tid, replica) in replicas.iter().enumerate() {
+                    self.checkpointer.save(
+                        tid,
+                        WorkerCheckpoint {
+                            current: Some(MrResult {
+                                energy: replica.energy,
+                                state: replica.state.clone(),
+                            }),
+                            best: replica.best.clone(),
+                            elapsed_steps: elapsed_steps.get(),
+                            accepted: replica.accepted,
+                            subsequent_rejected: 0,
+                            temperature: replica.temperature.snapshot(),
+                            rng_draws: problem.rng_draws(),
+                        },
+                        Vec::new(),
+                    );
+                }
+                last_checkpoint = elapsed_steps.get();
+            }
+        }
+
+        best
+    }
+}
+
+/// Geometric ladder of temperatures going from max_temp (first replica) down to
+/// min_temp (last replica)
+fn temperature_ladder(min_temp: f64, max_temp: f64, num_replicas: usize) -> Vec<f64> {
+    if num_replicas == 1 {
+        return vec![max_temp];
+    }
+
+    let ratio = (min_temp / max_temp).powf(1.0 / (num_replicas - 1) as f64);
+    (0..num_replicas)
+        .map(|i| max_temp * ratio.powi(i as i32))
+        .collect()
+}
+
+/// Improvement of new_energy with respect to old_energy, positive if new_energy is better
+fn improvement(nrg_type: EnergyType, new_energy: f64, old_energy: f64) -> f64 {
+    match nrg_type {
+        EnergyType::throughput => new_energy - old_energy,
+        EnergyType::latency => -(new_energy - old_energy),
+    }
+}
@@ -0,0 +1,149 @@
+/// ///////////////////////////////////////////////////////////////////////////
+///  File: annealing/solver/scheduler.rs
+/// ///////////////////////////////////////////////////////////////////////////
+///  Copyright 2017 Giovanni Mazzeo
+///
+///  Licensed under the Apache License, Version 2.0 (the "License");
+///  you may not use this file except in compliance with the License.
+///  You may obtain a copy of the License at
+///
+///      http://www.apache.org/licenses/LICENSE-2.0
+///
+///  Unless required by applicable law or agreed to in writing, software
+///  distributed under the License is distributed on an "AS IS" BASIS,
+///  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+///  See the License for the specific language governing permissions and
+///  limitations under the License.
+/// ///////////////////////////////////////////////////////////////////////////
+
+/// ****************************************************************************
+/// *****************************************************************************
+/// **
+/// Ask/Tell Interface of the Solvers and Evaluation Scheduler
+/// *
+/// *****************************************************************************
+/// ****************************************************************************
+use annealing::problem::Problem;
+use annealing::solver::common;
+use annealing::solver::common::MrResult;
+
+use shared::TunerParameter;
+
+use State;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+use std::sync::mpsc::channel;
+
+
+/// Configuration proposed by an ask/tell solver. The slot identifies the part of the solver that
+/// proposed it (e.g. the annealing chain of a worker) and it is given back with the result.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub state: State,
+    pub slot: usize,
+}
+
+
+/// Solvers that do not own the evaluation loop: they propose the configurations to evaluate and
+/// they are told the measured energies. Hence, the same algorithm can be driven by the pool of
+/// agents (see run), by a replay of the evaluation cache or by an external orchestrator.
+pub trait AskTell {
+    /// Prepare the search for num_workers evaluations running at the same time
+    fn start(&mut self, problem: &mut Problem, num_workers: usize);
+
+    /// Candidates to evaluate next, at most max_candidates. An empty vector means that the solver
+    /// is waiting for the results of its pending candidates or, if none is pending, that the
+    /// search is over.
+    fn ask(&mut self, problem: &mut Problem, max_candidates: usize) -> Vec<Candidate>;
+
+    /// Energy of a candidate returned by ask, None if the configuration could not be evaluated
+    fn tell(&mut self, problem: &mut Problem, candidate: Candidate, energy: Option<f64>);
+
+    /// Best configuration found so far
+    fn best(&self) -> MrResult;
+}
+
+
+/// Drive an ask/tell solver with the pool of agents. As soon as an agent is free the solver is
+/// asked for a new candidate, so that up to num_workers evaluations are always running, and the
+/// results are told in the order in which the evaluations complete. The solvers whose slots are
+/// independent (e.g. the MIR chains) draw the random numbers of each slot from its own stream,
+/// hence a seeded run explores the same configurations whatever the duration of the evaluations.
+pub fn run<S: AskTell>(solver: &mut S, problem: &mut Problem, num_workers: usize) -> MrResult {
+    solver.start(problem, num_workers);
+
+    let (tx, rx) = channel();
+    let mut free_agents: Vec<usize> = (0..num_workers).rev().collect();
+    let mut pending = 0;
+
+    loop {
+        if !free_agents.is_empty() {
+            let candidates = solver.ask(problem, free_agents.len());
+            for candidate in candidates.into_iter().take(free_agents.len()) {
+                let tid = free_agents.pop().unwrap();
+                let mut problem_c = problem.clone();
+                let tx_c = tx.clone();
+
+                thread::spawn(move || {
+                    // A panic of the evaluation is forwarded to the scheduler, which would
+                    // otherwise wait for its result forever
+                    let nrg = panic::catch_unwind(AssertUnwindSafe(
+                        || problem_c.energy(&candidate.state, tid),
+                    ));
+                    tx_c.send((tid, candidate, nrg)).unwrap();
+                });
+                pending += 1;
+            }
+        }
+
+        if pending == 0 {
+            break;
+        }
+
+        let (tid, candidate, nrg) = rx.recv().unwrap();
+        pending -= 1;
+        free_agents.push(tid);
+
+        match nrg {
+            Ok(nrg) => solver.tell(problem, candidate, nrg),
+            Err(e) => panic::resume_unwind(e),
+        }
+    }
+
+    solver.best()
+}
+
+
+/// Drive an ask/tell solver by batches: the candidates of each ask, at most one per agent, are
+/// evaluated together by common::evaluate_batch, hence raced with the racing evaluation, and
+/// their results are told in the order of the batch before the solver is asked again. Used by
+/// the solvers whose candidates are compared with each other (e.g. the neighborhoods explored by
+/// the SPISA workers or the families of a PRSA generation).
+pub fn run_batches<S: AskTell>(
+    solver: &mut S,
+    problem: &mut Problem,
+    tuner_params: &TunerParameter,
+    num_workers: usize,
+) -> MrResult {
+    solver.start(problem, num_workers);
+
+    loop {
+        let candidates: Vec<Candidate> = solver
+            .ask(problem, num_workers)
+            .into_iter()
+            .take(num_workers)
+            .collect();
+        if candidates.is_empty() {
+            break;
+        }
+
+        let batch: Vec<State> = candidates.iter().map(|c| c.state.clone()).collect();
+        let results = common::evaluate_batch(tuner_params, problem, batch);
+        for (candidate, (_, _, nrg)) in candidates.into_iter().zip(results.into_iter()) {
+            solver.tell(problem, candidate, nrg);
+        }
+    }
+
+    solver.best()
+}
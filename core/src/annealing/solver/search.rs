@@ -0,0 +1,290 @@
+/// ///////////////////////////////////////////////////////////////////////////
+///  File: annealing/solver/search.rs
+/// ///////////////////////////////////////////////////////////////////////////
+///  Copyright 2017 Giovanni Mazzeo
+///
+///  Licensed under the Apache License, Version 2.0 (the "License");
+///  you may not use this file except in compliance with the License.
+///  You may obtain a copy of the License at
+///
+///      http://www.apache.org/licenses/LICENSE-2.0
+///
+///  Unless required by applicable law or agreed to in writing, software
+///  distributed under the License is distributed on an "AS IS" BASIS,
+///  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+///  See the License for the specific language governing permissions and
+///  limitations under the License.
+/// ///////////////////////////////////////////////////////////////////////////
+
+/// ****************************************************************************
+/// *****************************************************************************
+/// **
+/// Baseline Solvers: Random Search and Exhaustive Grid Search
+/// *
+/// *****************************************************************************
+/// ****************************************************************************
+use annealing::solver::Solver;
+use annealing::problem::Problem;
+use annealing::cooler;
+use annealing::solver::common;
+use annealing::solver::common::MrResult;
+use annealing::solver::checkpoint::{Checkpointer, WorkerCheckpoint};
+use res_emitters::Emitter;
+
+use shared::TunerParameter;
+
+use time;
+use EnergyType;
+use State;
+
+use ansi_term::Colour::{Green, Yellow};
+
+
+#[derive(Debug, Clone)]
+pub struct RandomSearch {
+    pub tuner_params: TunerParameter,
+    pub res_emitter: Emitter,
+    pub checkpointer: Checkpointer,
+}
+
+#[derive(Debug, Clone)]
+pub struct GridSearch {
+    pub tuner_params: TunerParameter,
+    pub res_emitter: Emitter,
+    pub checkpointer: Checkpointer,
+}
+
+
+impl Solver for RandomSearch {
+    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+        let num_points = self.tuner_params.max_step;
+
+        // The default configuration is evaluated first, as reference for the comparison
+        batch_search(
+            &self.tuner_params,
+            &mut self.res_emitter,
+            &self.checkpointer,
+            problem,
+            num_workers,
+            num_points,
+            |problem, step| if step == 0 {
+                Some(problem.initial_state())
+            } else {
+                Some(problem.rand_state())
+            },
+        )
+    }
+}
+
+
+impl Solver for GridSearch {
+    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+
+        // Pre-flight count of the configurations to evaluate
+        let space_size = problem.space_size();
+        println!("{}",Yellow.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+        println!(
+            "{} {} configurations",
+            Yellow.paint("Size of the Space State: "),
+            space_size
+        );
+        if space_size > self.tuner_params.max_step {
+            println!(
+                "{} only the first {} configurations will be evaluated (Max Step)",
+                Yellow.paint("The Space State exceeds the budget: "),
+                self.tuner_params.max_step
+            );
+        }
+        println!("{}",Yellow.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+        batch_search(
+            &self.tuner_params,
+            &mut self.res_emitter,
+            &self.checkpointer,
+            problem,
+            num_workers,
+            space_size,
+            // The configurations that do not satisfy the constraints are skipped, as well as the
+            // ones that differ from an already enumerated one only in inactive parameters
+            |problem, step| {
+                let state = problem.grid_state(step);
+                if problem.is_valid(&state) && problem.canonical(&state) == state {
+                    Some(state)
+                } else {
+                    None
+                }
+            },
+        )
+    }
+}
+
+
+/// Evaluate the configurations of num_points points, as many at a time as the number of workers,
+/// until max_step configurations have been evaluated. The configuration of each point is given by
+/// next_state, a point without configuration is skipped without using up the budget.
+fn batch_search<F>(
+    tuner_params: &TunerParameter,
+    res_emitter: &mut Emitter,
+    checkpointer: &Checkpointer,
+    problem: &mut Problem,
+    num_workers: usize,
+    num_points: usize,
+    mut next_state: F,
+) -> MrResult
+where
+    F: FnMut(&mut Problem, usize) -> Option<State>,
+{
+    let num_steps = num_points.min(tuner_params.max_step);
+
+    println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+    println!(
+        "{} Search of the Space State: {:?} Configurations",
+        Green.paint("[TUNER]"),
+        num_steps
+    );
+    println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+    let mut start_time = time::precise_time_ns();
+
+    // The temperature is not used by the search, but it keeps track of the budget
+    let mut temperature = common::Temperature::new(
+        tuner_params.max_temp.unwrap(),
+        cooler::new_cooler(tuner_params),
+        tuner_params.cooling.clone(),
+    );
+
+    let mut best: Option<MrResult> = None;
+    let mut elapsed_steps = 0;
+    if let Some(ckpt) = checkpointer.resumed_worker(0) {
+        temperature.restore(&ckpt.temperature);
+        start_time -= ckpt.temperature.elapsed_time_ns;
+        elapsed_steps = ckpt.elapsed_steps;
+        best = Some(ckpt.best);
+    }
+    let mut last_checkpoint = elapsed_steps;
+
+    // Index of the next point. A resumed search skips the points whose configurations have been
+    // evaluated before the checkpoint.
+    let mut cursor = 0;
+    let mut skipped = 0;
+    while skipped < elapsed_steps && cursor < num_points {
+        if next_state(problem, cursor).is_some() {
+            skipped += 1;
+        }
+        cursor += 1;
+    }
+    if let Some(ckpt) = checkpointer.resumed_worker(0) {
+        problem.restore_rng(ckpt.rng_draws);
+    }
+
+    loop {
+
+        if elapsed_steps >= num_steps {
+            break;
+        }
+
+        if temperature.is_expired(elapsed_steps) {
+            println!("{} Time Budget Exhausted!!!", Green.paint("[TUNER]"));
+            break;
+        }
+
+        let batch_size = num_workers.min(num_steps - elapsed_steps);
+        let mut batch: Vec<State> = Vec::with_capacity(batch_size);
+        while batch.len() < batch_size && cursor < num_points {
+            if let Some(state) = next_state(problem, cursor) {
+                batch.push(state);
+            }
+            cursor += 1;
+        }
+        if batch.is_empty() {
+            break;
+        }
+
+        for (tid, state, nrg) in common::evaluate_batch(tuner_params, problem, batch) {
+            elapsed_steps += 1;
+
+            match nrg {
+                Some(new_energy) => {
+                    let is_best = match best {
+                        Some(ref b) => {
+                            let de = match tuner_params.energy {
+                                EnergyType::throughput => new_energy - b.energy,
+                                EnergyType::latency => -(new_energy - b.energy),
+                            };
+                            de > 0.0
+                        }
+                        None => true,
+                    };
+                    if is_best {
+                        best = Some(MrResult {
+                            energy: new_energy,
+                            state: state.clone(),
+                        });
+                    }
+
+                    let best_c = best.clone().unwrap();
+                    let elapsed_time = (time::precise_time_ns() - start_time) as f64 /
+                        1000000000.0f64;
+                    res_emitter.send_update(
+                        0.0,
+                        elapsed_time,
+                        0.0,
+                        new_energy,
+                        &state,
+                        problem.repetitions(&state),
+                        best_c.energy,
+                        &best_c.state,
+                        elapsed_steps,
+                        tid,
+                    );
+                }
+                None => {
+                    println!(
+                        "{} The current configuration parameters cannot be evaluated. Skip!",
+                        Green.paint("[TUNER]")
+                    );
+                }
+            }
+        }
+
+        let elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+        println!(
+            "{} Completed Steps: {:.2} - Percentage of Completion: {:.2}% - Elapsed Time: \
+                  {:.2} s",
+            Green.paint("[TUNER]"),
+            elapsed_steps,
+            (elapsed_steps as f64 / num_steps as f64) * 100.0,
+            elapsed_time
+        );
+        if let Some(ref b) = best {
+            println!("{} Best State: {:?}", Green.paint("[TUNER]"), b.state);
+            println!("{} Best Energy: {:.4}", Green.paint("[TUNER]"), b.energy);
+        }
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+        if let Some(ref b) = best {
+            if checkpointer.is_due(last_checkpoint, elapsed_steps) {
+                checkpointer.save(
+                    0,
+                    WorkerCheckpoint {
+                        current: None,
+                        best: b.clone(),
+                        elapsed_steps: elapsed_steps,
+                        accepted: 0,
+                        subsequent_rejected: 0,
+                        temperature: temperature.snapshot(),
+                        rng_draws: problem.rng_draws(),
+                    },
+                    Vec::new(),
+                );
+                last_checkpoint = elapsed_steps;
+            }
+        }
+    }
+
+    match best {
+        Some(b) => b,
+        None => panic!("None of the configurations allowed to calculate the energy"),
+    }
+}
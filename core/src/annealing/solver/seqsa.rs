@@ -24,9 +24,12 @@
 /// *****************************************************************************
 /// ****************************************************************************
 use annealing::solver::Solver;
+use annealing::solver::scheduler::{self, AskTell, Candidate};
 use annealing::problem::Problem;
-use annealing::cooler::{Cooler, StepsCooler, TimeCooler};
+use annealing::cooler;
+use annealing::solver::common;
 use annealing::solver::common::MrResult;
+use annealing::solver::checkpoint::{Checkpointer, WorkerCheckpoint};
 
 
 use res_emitters;
@@ -35,187 +38,364 @@ use res_emitters::Emitter;
 use shared::TunerParameter;
 
 use time;
-use CoolingSchedule;
 use EnergyType;
-use pbr;
-use rand;
-use libc;
-use num_cpus;
 
-use rand::{Rng, thread_rng};
 use rand::distributions::{Range, IndependentSample};
 use ansi_term::Colour::Green;
-use std::collections::HashMap;
-use pbr::{ProgressBar, MultiBar};
 
 
 #[derive(Debug, Clone)]
 pub struct Seqsa {
     pub tuner_params: TunerParameter,
     pub res_emitter: Emitter,
+    pub checkpointer: Checkpointer,
+    temperature: common::Temperature,
+    stagnation: common::Stagnation,
+    acceptance: common::Acceptance,
+    current: Option<MrResult>,
+    best: Option<MrResult>,
+    elapsed_steps: usize,
+    accepted: usize,
+    subsequent_rejected: usize,
+    last_checkpoint: usize,
+    last_nrg: f64,
+    start_time: u64,
+    exec_time: f64,
+    pending: bool,
+    restarting: bool,
+    finished: bool,
 }
 
-impl Solver for Seqsa {
-    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+impl Seqsa {
+    pub fn new(tuner_params: TunerParameter, res_emitter: Emitter, checkpointer: Checkpointer) -> Self {
+        let mut temperature = common::Temperature::new(
+            tuner_params.max_temp.unwrap(),
+            cooler::new_cooler(&tuner_params),
+            tuner_params.cooling.clone(),
+        );
+        let stagnation = common::Stagnation::new(&tuner_params, 400);
+        let acceptance = common::Acceptance::new(&tuner_params);
+
+        let mut seqsa = Seqsa {
+            tuner_params: tuner_params,
+            res_emitter: res_emitter,
+            checkpointer: checkpointer,
+            temperature: temperature.clone(),
+            stagnation: stagnation,
+            acceptance: acceptance,
+            current: None,
+            best: None,
+            elapsed_steps: 0,
+            accepted: 0,
+            subsequent_rejected: 0,
+            last_checkpoint: 0,
+            last_nrg: 0.0,
+            start_time: time::precise_time_ns(),
+            exec_time: 0.0,
+            pending: false,
+            restarting: false,
+            finished: false,
+        };
 
+        if let Some(ckpt) = seqsa.checkpointer.resumed_worker(0) {
+            temperature.restore(&ckpt.temperature);
+            let current = ckpt.current.unwrap();
+            seqsa.temperature = temperature;
+            seqsa.start_time -= ckpt.temperature.elapsed_time_ns;
+            seqsa.last_nrg = current.energy;
+            seqsa.current = Some(current);
+            seqsa.best = Some(ckpt.best);
+            seqsa.accepted = ckpt.accepted;
+            seqsa.subsequent_rejected = ckpt.subsequent_rejected;
+            seqsa.elapsed_steps = ckpt.elapsed_steps;
+            seqsa.last_checkpoint = ckpt.elapsed_steps;
+        }
 
-        let cooler = StepsCooler {
-            max_steps: self.tuner_params.max_step,
-            min_temp: self.tuner_params.min_temp.unwrap(),
-            max_temp: self.tuner_params.max_temp.unwrap(),
-        };
+        seqsa
+    }
+}
 
-        let mut rng = thread_rng();
-        let range = Range::new(0.0, 1.0);
+impl Solver for Seqsa {
+    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+        scheduler::run(self, problem, num_workers)
+    }
+}
 
-        println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
-        println!(
-            "{} Initialization Phase: Evaluation of Energy for Default Parameters",
-            Green.paint("[TUNER]")
-        );
-        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+impl AskTell for Seqsa {
+    fn start(&mut self, problem: &mut Problem, num_workers: usize) {
+        if self.current.is_none() {
+            println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+            println!(
+                "{} Initialization Phase: Evaluation of Energy for Default Parameters",
+                Green.paint("[TUNER]")
+            );
+            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+        }
 
-        let mut start_time = time::precise_time_ns();
+        // A resumed run continues the random stream of the interrupted one
+        if let Some(ckpt) = self.checkpointer.resumed_worker(0) {
+            problem.restore_rng(ckpt.rng_draws);
+        }
+    }
+
+    /// The annealing is sequential: a single candidate is proposed at a time
+    fn ask(&mut self, problem: &mut Problem, max_candidates: usize) -> Vec<Candidate> {
+        if self.pending || self.finished || max_candidates == 0 {
+            return Vec::new();
+        }
 
-        let mut state = problem.initial_state();
-        let mut energy = match problem.energy(&state, 0) {
-            Some(nrg) => nrg,
-            None => panic!("The initial configuration does not allow to calculate the energy"),
+        let (mut state, mut energy) = match self.current {
+            Some(ref current) => (current.state.clone(), current.energy),
+            None => {
+                self.pending = true;
+                return vec![
+                    Candidate {
+                        state: problem.initial_state(),
+                        slot: 0,
+                    },
+                ];
+            }
         };
 
+        // With a wall-clock budget, the search is not limited by the steps
+        if self.tuner_params.max_time.is_none() &&
+            self.elapsed_steps >= self.tuner_params.max_step
+        {
+            self.finished = true;
+            return Vec::new();
+        }
 
+        if self.temperature.is_expired(self.elapsed_steps) {
+            println!("{} Time Budget Exhausted!!!", Green.paint("[TUNER]"));
+            self.finished = true;
+            return Vec::new();
+        }
 
-        let mut exec_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
-        let mut elapsed_time = 0.0;
-        let mut temperature: f64 = self.tuner_params.max_temp.unwrap();
-        let mut accepted = 0;
-        let mut subsequent_rejected = 0;
-        let mut last_nrg = energy;
+        let elapsed_time = (time::precise_time_ns() - self.start_time) as f64 / 1000000000.0f64;
 
+        if self.stagnation.is_reached(self.subsequent_rejected) {
+            let current = MrResult {
+                energy: energy,
+                state: state,
+            };
+            match self.stagnation.react(
+                problem,
+                &self.temperature,
+                &mut self.res_emitter,
+                current,
+                self.best.as_ref().unwrap(),
+                self.elapsed_steps,
+                elapsed_time,
+                0,
+            ) {
+                Some(common::Restart::Evaluated(res)) => {
+                    energy = res.energy;
+                    state = res.state;
+                }
+                Some(common::Restart::Candidate(restart_state)) => {
+                    self.subsequent_rejected = 0;
+                    self.pending = true;
+                    self.restarting = true;
+                    return vec![
+                        Candidate {
+                            state: restart_state,
+                            slot: 0,
+                        },
+                    ];
+                }
+                None => {
+                    self.finished = true;
+                    return Vec::new();
+                }
+            }
+            self.subsequent_rejected = 0;
+            self.current = Some(MrResult {
+                energy: energy,
+                state: state.clone(),
+            });
+        }
 
-        start_time = time::precise_time_ns();
+        let time_2_complete_mins =
+            self.temperature.time_to_complete(self.elapsed_steps, self.exec_time) / 60.0;
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+        println!(
+            "{} Completed Steps: {:.2} - Percentage of Completion: {:.2}% - Estimated \
+                  time to Complete: {:.2} Mins",
+            Green.paint("[TUNER]"),
+            self.elapsed_steps,
+            self.temperature.progress(self.elapsed_steps),
+            time_2_complete_mins as usize
+        );
+        println!(
+            "{} Total Accepted Solutions: {:?} - Subsequent Rejected: {:?} - Current \
+                  Temperature: {:.2} - Elapsed Time: {:.2} s",
+            Green.paint("[TUNER]"),
+            self.accepted,
+            self.subsequent_rejected,
+            self.temperature.get(),
+            elapsed_time
+        );
+        println!("{} Accepted State: {:?}", Green.paint("[TUNER]"), state);
+        println!(
+            "{} Accepted Energy: {:.4} - Last Measured Energy: {:.4}",
+            Green.paint("[TUNER]"),
+            energy,
+            self.last_nrg
+        );
 
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
 
-        let cpu_time = 0.0;
-        for elapsed_steps in 0..self.tuner_params.max_step {
+        self.pending = true;
+        vec![
+            Candidate {
+                state: problem.new_state(
+                    &state,
+                    self.tuner_params.max_step,
+                    self.elapsed_steps,
+                    self.temperature.get() / self.tuner_params.max_temp.unwrap(),
+                ),
+                slot: 0,
+            },
+        ]
+    }
 
-            elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
+    fn tell(&mut self, problem: &mut Problem, candidate: Candidate, new_energy: Option<f64>) {
+        self.pending = false;
+        let next_state = candidate.state;
 
+        let current = match self.current.clone() {
+            Some(current) => current,
+            None => {
+                // Evaluation of the initial state
+                let energy = match new_energy {
+                    Some(nrg) => nrg,
+                    None => {
+                        panic!("The initial configuration does not allow to calculate the energy")
+                    }
+                };
+                self.exec_time = (time::precise_time_ns() - self.start_time) as f64 /
+                    1000000000.0f64;
+                self.start_time = time::precise_time_ns();
+                self.last_nrg = energy;
+                self.current = Some(MrResult {
+                    energy: energy,
+                    state: next_state.clone(),
+                });
+                self.best = Some(MrResult {
+                    energy: energy,
+                    state: next_state,
+                });
+                return;
+            }
+        };
 
-            let time_2_complete_mins =
-                exec_time * ((self.tuner_params.max_step - elapsed_steps) as f64) / 60.0;
-            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
-            println!(
-                "{} Completed Steps: {:.2} - Percentage of Completion: {:.2}% - Estimated \
-                      time to Complete: {:.2} Mins",
-                Green.paint("[TUNER]"),
-                elapsed_steps,
-                (elapsed_steps as f64 / cooler.max_steps as f64) * 100.0,
-                time_2_complete_mins as usize
-            );
-            println!(
-                "{} Total Accepted Solutions: {:?} - Subsequent Rejected: {:?} - Current \
-                      Temperature: {:.2} - Elapsed Time: {:.2} s",
-                Green.paint("[TUNER]"),
-                accepted,
-                subsequent_rejected,
-                temperature,
-                elapsed_time
-            );
-            println!("{} Accepted State: {:?}", Green.paint("[TUNER]"), state);
-            println!(
-                "{} Accepted Energy: {:.4} - Last Measured Energy: {:.4}",
-                Green.paint("[TUNER]"),
-                energy,
-                last_nrg
-            );
+        if self.restarting {
+            // Evaluation of the random restart of the annealing
+            self.restarting = false;
+            self.current = Some(self.stagnation.restarted(current, next_state, new_energy));
+            return;
+        }
 
-            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+        let elapsed_time = (time::precise_time_ns() - self.start_time) as f64 / 1000000000.0f64;
+        let cpu_time = 0.0;
+        let range = Range::new(0.0, 1.0);
 
+        match new_energy {
+            Some(new_energy) => {
+                self.last_nrg = new_energy;
 
-            state = {
-
-                let next_state =
-                    problem.new_state(&state, self.tuner_params.max_step, elapsed_steps);
-
-                let accepted_state = match problem.energy(&next_state, 0) {
-                    Some(new_energy) => {
-                        last_nrg = new_energy;
-
-                        let de = match self.tuner_params.energy {
-                            EnergyType::throughput => new_energy - energy,
-                            EnergyType::latency => -(new_energy - energy), 
-                        };
-
-                        if subsequent_rejected > 400 {
-                            println!("{} Convergence Reached!!!", Green.paint("[TUNER]"));
-                            break;
-                        }
-
-                        if de > 0.0 || range.ind_sample(&mut rng) <= (de / temperature).exp() {
-                            accepted += 1;
-                            energy = new_energy;
-
-                            if de > 0.0 {
-                                subsequent_rejected = 0;
-                            }
-
-                            self.res_emitter.send_update(
-                                temperature,
-                                elapsed_time,
-                                cpu_time,
-                                new_energy,
-                                &next_state,
-                                energy,
-                                &next_state,
-                                elapsed_steps,
-                                0,
-                            );
-                            next_state
-
-                        } else {
-                            subsequent_rejected += 1;
-                            self.res_emitter.send_update(
-                                temperature,
-                                elapsed_time,
-                                cpu_time,
-                                new_energy,
-                                &next_state,
-                                energy,
-                                &state,
-                                elapsed_steps,
-                                0,
-                            );
-                            state
-                        }
+                let de = self.acceptance.delta(
+                    problem,
+                    &next_state,
+                    new_energy,
+                    &current.state,
+                    current.energy,
+                );
 
+                if de > 0.0 || range.ind_sample(problem.rng()) <= (de / self.temperature.get()).exp() {
+                    self.accepted += 1;
 
+                    if de > 0.0 {
+                        self.subsequent_rejected = 0;
                     }
-                    None => {
-                        println!(
-                            "{} The current configuration parameters cannot be evaluated. \
-                                  Skip!",
-                            Green.paint("[TUNER]")
-                        );
-                        state
-                    }
-                };
-
-                accepted_state
-            };
 
+                    let de_best = match self.tuner_params.energy {
+                        EnergyType::throughput => new_energy - self.best.as_ref().unwrap().energy,
+                        EnergyType::latency => -(new_energy - self.best.as_ref().unwrap().energy), 
+                    };
+                    if de_best > 0.0 {
+                        self.best = Some(MrResult {
+                            energy: new_energy,
+                            state: next_state.clone(),
+                        });
+                    }
 
-            temperature = match self.tuner_params.cooling {
-                CoolingSchedule::linear => cooler.linear_cooling(elapsed_steps),
-                CoolingSchedule::exponential => cooler.exponential_cooling(elapsed_steps),
-                CoolingSchedule::basic_exp_cooling => cooler.basic_exp_cooling(temperature),
-            };
-        }
+                    self.res_emitter.send_update(
+                        self.temperature.get(),
+                        elapsed_time,
+                        cpu_time,
+                        new_energy,
+                        &next_state,
+                        problem.repetitions(&next_state),
+                        new_energy,
+                        &next_state,
+                        self.elapsed_steps,
+                        0,
+                    );
+                    self.current = Some(MrResult {
+                        energy: new_energy,
+                        state: next_state,
+                    });
+
+                } else {
+                    self.subsequent_rejected += 1;
+                    self.res_emitter.send_update(
+                        self.temperature.get(),
+                        elapsed_time,
+                        cpu_time,
+                        new_energy,
+                        &next_state,
+                        problem.repetitions(&next_state),
+                        current.energy,
+                        &current.state,
+                        self.elapsed_steps,
+                        0,
+                    );
+                }
+
+
+            }
+            None => {
+                println!(
+                    "{} The current configuration parameters cannot be evaluated. \
+                          Skip!",
+                    Green.paint("[TUNER]")
+                );
+            }
+        };
 
-        MrResult {
-            energy: energy,
-            state: state,
+        self.elapsed_steps += 1;
+        self.temperature.update(self.elapsed_steps, self.accepted);
+
+        if self.checkpointer.is_due(self.last_checkpoint, self.elapsed_steps) {
+            self.checkpointer.save(
+                0,
+                WorkerCheckpoint {
+                    current: self.current.clone(),
+                    best: self.best.clone().unwrap(),
+                    elapsed_steps: self.elapsed_steps,
+                    accepted: self.accepted,
+                    subsequent_rejected: self.subsequent_rejected,
+                    temperature: self.temperature.snapshot(),
+                    rng_draws: problem.rng_draws(),
+                },
+                Vec::new(),
+            );
+            self.last_checkpoint = self.elapsed_steps;
         }
+    }
 
+    fn best(&self) -> MrResult {
+        self.best.clone().unwrap()
     }
 }
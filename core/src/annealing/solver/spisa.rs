@@ -24,11 +24,12 @@
 /// *****************************************************************************
 /// ****************************************************************************
 use annealing::solver::Solver;
+use annealing::solver::scheduler::{self, AskTell, Candidate};
 use annealing::problem::Problem;
-use annealing::cooler::{Cooler, StepsCooler, TimeCooler};
+use annealing::cooler;
 use annealing::solver::common;
 use annealing::solver::common::MrResult;
-use annealing::solver::common::IntermediateResults;
+use annealing::solver::checkpoint::{Checkpointer, WorkerCheckpoint};
 use res_emitters;
 use res_emitters::Emitter;
 
@@ -36,323 +37,685 @@ use shared::TunerParameter;
 
 
 use time;
-use CoolingSchedule;
 use EnergyType;
-use pbr;
-use rand;
-use libc;
-use num_cpus;
+use EvaluationMode;
+use ExchangeMode;
+use State;
 
-use rand::{Rng, thread_rng};
 use rand::distributions::{Range, IndependentSample};
 use ansi_term::Colour::Green;
-use std::collections::HashMap;
-use pbr::{ProgressBar, MultiBar};
-use std::thread;
-use std::sync::mpsc::channel;
-use std::time::Duration;
 
 
 #[derive(Debug, Clone)]
 pub struct Spisa {
     pub tuner_params: TunerParameter,
     pub res_emitter: Emitter,
+    pub checkpointer: Checkpointer,
+    temperature: common::Temperature,
+    stagnation: common::Stagnation,
+    acceptance: common::Acceptance,
+    master: Option<MrResult>,
+    best: Option<MrResult>,
+    elapsed_steps: usize,
+    accepted: usize,
+    subsequent_rej: usize,
+    last_checkpoint: usize,
+    start_time: u64,
+    exec_time: f64,
+    num_workers: usize,
+    round: Option<Round>,
+    restart: Option<State>,
+    pending: bool,
+    finished: bool,
 }
 
-impl Solver for Spisa {
-    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+/// Exploration of the neighborhoods of the master state. Each worker anneals its own state,
+/// taking the configurations to evaluate from the shared pool of neighborhoods.
+#[derive(Debug, Clone)]
+struct Round {
+    neigh_pool: common::NeighborhoodsPool,
+    workers: Vec<RoundWorker>,
+}
 
+/// A stale worker is waiting for the result of a configuration of a previous round, taken from
+/// the neighborhoods of a previous master state
+#[derive(Debug, Clone)]
+struct RoundWorker {
+    problem: Problem,
+    current: MrResult,
+    pending: bool,
+    stale: bool,
+    done: bool,
+}
 
-        let cooler = StepsCooler {
-            max_steps: self.tuner_params.max_step,
-            min_temp: self.tuner_params.min_temp.unwrap(),
-            max_temp: self.tuner_params.max_temp.unwrap(),
+impl Spisa {
+    pub fn new(tuner_params: TunerParameter, res_emitter: Emitter, checkpointer: Checkpointer) -> Self {
+        let mut temperature = common::Temperature::new(
+            tuner_params.max_temp.unwrap(),
+            cooler::new_cooler(&tuner_params),
+            tuner_params.cooling.clone(),
+        );
+        let stagnation = common::Stagnation::new(&tuner_params, 300);
+        let acceptance = common::Acceptance::new(&tuner_params);
+
+        let mut spisa = Spisa {
+            tuner_params: tuner_params,
+            res_emitter: res_emitter,
+            checkpointer: checkpointer,
+            temperature: temperature.clone(),
+            stagnation: stagnation,
+            acceptance: acceptance,
+            master: None,
+            best: None,
+            elapsed_steps: 0,
+            accepted: 0,
+            subsequent_rej: 0,
+            last_checkpoint: 0,
+            start_time: time::precise_time_ns(),
+            exec_time: 0.0,
+            num_workers: 1,
+            round: None,
+            restart: None,
+            pending: false,
+            finished: false,
         };
 
+        if let Some(ckpt) = spisa.checkpointer.resumed_worker(0) {
+            temperature.restore(&ckpt.temperature);
+            spisa.temperature = temperature;
+            spisa.start_time -= ckpt.temperature.elapsed_time_ns;
+            spisa.master = ckpt.current;
+            spisa.best = Some(ckpt.best);
+            spisa.elapsed_steps = ckpt.elapsed_steps;
+            spisa.accepted = ckpt.accepted;
+            spisa.subsequent_rej = ckpt.subsequent_rejected;
+            spisa.last_checkpoint = ckpt.elapsed_steps;
+        }
 
-        ("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+        spisa
+    }
+
+
+    /// Start a round from the master state. None if the search is over or if the master state
+    /// has to be restarted from a random configuration first (see restart_candidate).
+    fn start_round(&mut self, problem: &mut Problem) -> Option<Round> {
+        let (mut master_state, mut master_energy) = {
+            let master = self.master.as_ref().unwrap();
+            (master.state.clone(), master.energy)
+        };
+
+        // With a wall-clock budget, the search is not limited by the steps
+        if self.tuner_params.max_time.is_none() &&
+            self.elapsed_steps >= self.tuner_params.max_step
+        {
+            return None;
+        }
+
+        if self.temperature.is_expired(self.elapsed_steps) {
+            println!("{} Time Budget Exhausted!!!", Green.paint("[TUNER]"));
+            return None;
+        }
+        let elapsed_time = (time::precise_time_ns() - self.start_time) as f64 / 1000000000.0f64;
+
+        if self.stagnation.is_reached(self.subsequent_rej) {
+            let current = MrResult {
+                energy: master_energy,
+                state: master_state,
+            };
+            match self.stagnation.react(
+                problem,
+                &self.temperature,
+                &mut self.res_emitter,
+                current,
+                self.best.as_ref().unwrap(),
+                self.elapsed_steps,
+                elapsed_time,
+                0,
+            ) {
+                Some(common::Restart::Evaluated(res)) => {
+                    master_energy = res.energy;
+                    master_state = res.state;
+                }
+                Some(common::Restart::Candidate(state)) => {
+                    self.subsequent_rej = 0;
+                    self.restart = Some(state);
+                    return None;
+                }
+                None => return None,
+            }
+            self.subsequent_rej = 0;
+            self.master = Some(MrResult {
+                energy: master_energy,
+                state: master_state.clone(),
+            });
+        }
+
+        let time_2_complete_hrs =
+            self.temperature.time_to_complete(self.elapsed_steps, self.exec_time) / 3600.0;
+        println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
         println!(
-            "{} Initialization Phase: Evaluation of Energy for Default Parameters",
-            Green.paint("[TUNER]")
+            "{} Completed Steps: {:.2} - Percentage of Completion: {:.2}% - Estimated \
+                  time to Complete: {:.2} Hrs",
+            Green.paint("[TUNER]"),
+            self.elapsed_steps,
+            self.temperature.progress(self.elapsed_steps),
+            time_2_complete_hrs as usize
         );
+        println!(
+            "{} Total Accepted: {:?} - Subsequent Rejected: {:?} - Current Temperature: \
+                  {:.2} - Elapsed Time: {:.2} s",
+            Green.paint("[TUNER]"),
+            self.accepted,
+            self.subsequent_rej,
+            self.temperature.get(),
+            elapsed_time
+        );
+        println!(
+            "{} Accepted State: {:?}",
+            Green.paint("[TUNER]"),
+            master_state
+        );
+        println!(
+            "{} Accepted Energy: {:.4}",
+            Green.paint("[TUNER]"),
+            master_energy
+        );
+
         println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
 
-        let mut start_time = time::precise_time_ns();
-        let mut rng = thread_rng();
 
-        let mut rng_c = rng.clone();
-        let mut master_state = problem.initial_state();
-        let mut master_energy = match problem.energy(&master_state.clone(), 0) {
-            Some(nrg) => nrg,
-            None => panic!("The initial configuration does not allow to calculate the energy"),
-        };
+        // Create the Pool of Neighborhoods
+        let neigh_space = problem.neigh_space(&master_state);
+        let round_step = self.elapsed_steps;
+
+        let workers = (0..self.num_workers)
+            .map(|worker_nr| {
+                let mut problem_c = problem.clone();
+                // Each worker of each round draws from its own stream of random numbers
+                problem_c.reseed(&[round_step, worker_nr]);
+                RoundWorker {
+                    problem: problem_c,
+                    current: MrResult {
+                        energy: master_energy,
+                        state: master_state.clone(),
+                    },
+                    pending: false,
+                    stale: false,
+                    done: false,
+                }
+            })
+            .collect();
+
+        Some(Round {
+            neigh_pool: common::NeighborhoodsPool::new(neigh_space),
+            workers: workers,
+        })
+    }
 
-        let mut elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
-        let time_2_complete_hrs = ((elapsed_time as f64) * self.tuner_params.max_step as f64) /
-            3600.0;
 
+    /// Get the final states of the workers and choose between them which one will be the best.
+    /// The master state moves to it with the Metropolis criterion.
+    fn end_round(&mut self, problem: &mut Problem, round: Round) {
+        let mut workers_res: Vec<MrResult> = round.workers.into_iter().map(|w| w.current).collect();
+        let first_elem = workers_res.remove(0);
 
-        let mut cpu_time = 0.0;
-        let mut elapsed_steps = common::SharedGenericCounter::new();
-        let mut accepted = common::SharedGenericCounter::new();
-        let mut subsequent_rej = common::SharedGenericCounter::new();
+        let mut best_workers_nrg = first_elem.energy;
+        let mut best_workers_state = first_elem.state;
 
-        let mut temperature = common::Temperature::new(
-            self.tuner_params.max_temp.unwrap(),
-            cooler,
-            self.clone().tuner_params.cooling,
+        for elem in workers_res.iter() {
+            let diff = match self.tuner_params.energy {
+                EnergyType::throughput => elem.energy - best_workers_nrg,
+                EnergyType::latency => -(elem.energy - best_workers_nrg), 
+            };
+            if diff > 0.0 {
+                best_workers_nrg = elem.clone().energy;
+                best_workers_state = elem.clone().state;
+            }
+        }
+
+        let de_best = match self.tuner_params.energy {
+            EnergyType::throughput => best_workers_nrg - self.best.as_ref().unwrap().energy,
+            EnergyType::latency => -(best_workers_nrg - self.best.as_ref().unwrap().energy), 
+        };
+        if de_best > 0.0 {
+            self.best = Some(MrResult {
+                energy: best_workers_nrg,
+                state: best_workers_state.clone(),
+            });
+        }
+
+        let master = self.master.clone().unwrap();
+        let de = self.acceptance.delta(
+            problem,
+            &best_workers_state,
+            best_workers_nrg,
+            &master.state,
+            master.energy,
         );
+        let range = Range::new(0.0, 1.0);
+
+        if de > 0.0 || range.ind_sample(problem.rng()) <= (de / self.temperature.get()).exp() {
+            self.master = Some(MrResult {
+                energy: best_workers_nrg,
+                state: best_workers_state,
+            });
+            if de > 0.0 {
+                self.subsequent_rej = 0;
+            }
+
+        } else {
+            self.subsequent_rej += 1;
+        }
+
+        self.save_checkpoint(problem);
+    }
 
 
-        // Channel for receiving results from worker threads and send them to the file writer.
-        let (tx, rx) = channel::<IntermediateResults>();
-        // Spawn the thread that will take care of writing results into a CSV file
-        let (elapsed_steps_c, temperature_c) = (elapsed_steps.clone(), temperature.clone());
-        let mut res_emitter = self.clone().res_emitter;
-        thread::spawn(move || loop {
-            elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
-            match rx.recv() {
-                Ok(res) => {
-                    res_emitter.send_update(
-                        temperature_c.get(),
-                        elapsed_time,
-                        cpu_time,
-                        res.last_nrg,
-                        &res.last_state,
-                        res.best_nrg,
-                        &res.best_state,
-                        elapsed_steps_c.get(),
-                        res.tid,
-                    );
+    /// Candidate of the random restart requested when a round could not be started. It is
+    /// told in the slot after the ones of the workers. If no restart is requested, the search
+    /// is over.
+    fn restart_candidate(&mut self) -> Option<Candidate> {
+        match self.restart.take() {
+            Some(state) => {
+                self.pending = true;
+                Some(Candidate {
+                    state: state,
+                    slot: self.num_workers,
+                })
+            }
+            None => {
+                self.finished = true;
+                None
+            }
+        }
+    }
+
+
+    fn save_checkpoint(&mut self, problem: &mut Problem) {
+        if self.checkpointer.is_due(self.last_checkpoint, self.elapsed_steps) {
+            self.checkpointer.save(
+                0,
+                WorkerCheckpoint {
+                    current: self.master.clone(),
+                    best: self.best.clone().unwrap(),
+                    elapsed_steps: self.elapsed_steps,
+                    accepted: self.accepted,
+                    subsequent_rejected: self.subsequent_rej,
+                    temperature: self.temperature.snapshot(),
+                    rng_draws: problem.rng_draws(),
+                },
+                Vec::new(),
+            );
+            self.last_checkpoint = self.elapsed_steps;
+        }
+    }
+
+    /// Asynchronous information exchange: once the pool of neighborhoods is exhausted, the
+    /// workers restart from the master state with the pool of its neighborhoods. Unlike a
+    /// round, the exchange does not wait for the pending evaluations, whose results are still
+    /// told to their workers. False if the search is over.
+    fn exchange(&mut self, problem: &mut Problem) -> bool {
+        self.save_checkpoint(problem);
+
+        match self.start_round(problem) {
+            Some(mut round) => {
+                if let Some(old_round) = self.round.take() {
+                    for (worker, old_worker) in round.workers.iter_mut().zip(old_round.workers) {
+                        worker.pending = old_worker.pending;
+                        worker.stale = old_worker.pending;
+                    }
                 }
-                Err(e) => {} 
+                self.round = Some(round);
+                true
             }
-        });
+            None => false,
+        }
+    }
 
 
-        // *********************************************************************************************************
-        start_time = time::precise_time_ns();
-        'outer: loop {
+    /// Asynchronous counterpart of ask: every free worker takes a configuration from the pool of
+    /// neighborhoods straight away, without waiting for the other workers at the end of a round
+    fn ask_async(&mut self, problem: &mut Problem, max_candidates: usize) -> Vec<Candidate> {
+        if self.round.is_none() && !self.exchange(problem) {
+            return self.restart_candidate().into_iter().collect();
+        }
 
+        let mut candidates = Vec::new();
+        let mut worker_nr = 0;
+        while worker_nr < self.num_workers && candidates.len() < max_candidates {
+            if self.round.as_ref().unwrap().workers[worker_nr].pending {
+                worker_nr += 1;
+                continue;
+            }
 
+            if self.tuner_params.max_time.is_none() &&
+                self.elapsed_steps >= self.tuner_params.max_step
+            {
+                self.finished = true;
+                break;
+            }
 
+            if self.temperature.is_expired(self.elapsed_steps) {
+                println!("{} Time Budget Exhausted!!!", Green.paint("[TUNER]"));
+                self.finished = true;
+                break;
+            }
 
-            if elapsed_steps.get() > self.tuner_params.max_step {
-                break 'outer;
+            let next_state = {
+                let round = self.round.as_mut().unwrap();
+                let worker = &mut round.workers[worker_nr];
+                round.neigh_pool.remove_one(worker.problem.rng())
+            };
+
+            match next_state {
+                Some(next_state) => {
+                    self.round.as_mut().unwrap().workers[worker_nr].pending = true;
+                    candidates.push(Candidate {
+                        state: next_state,
+                        slot: worker_nr,
+                    });
+                    worker_nr += 1;
+                }
+                None => {
+                    if !self.exchange(problem) {
+                        candidates.extend(self.restart_candidate());
+                        break;
+                    }
+                }
+            }
+        }
+
+        candidates
+    }
+
+
+    /// The best state moves to the new one if it improves it
+    fn update_best(&mut self, new_state: &State, new_energy: f64) {
+        let de_best = match self.tuner_params.energy {
+            EnergyType::throughput => new_energy - self.best.as_ref().unwrap().energy,
+            EnergyType::latency => -(new_energy - self.best.as_ref().unwrap().energy), 
+        };
+        if de_best > 0.0 {
+            self.best = Some(MrResult {
+                energy: new_energy,
+                state: new_state.clone(),
+            });
+        }
+    }
+
+
+    /// With the asynchronous exchange, the master state is updated as the results arrive: a
+    /// configuration accepted by a worker moves the master with the Metropolis criterion. The
+    /// subsequent rejections are counted here for the configurations accepted by the workers.
+    /// The random draw comes from the stream of the worker, not from the order of the results.
+    fn update_master(
+        &mut self,
+        problem: &mut Problem,
+        worker_nr: usize,
+        new_state: &State,
+        new_energy: f64,
+    ) {
+        self.update_best(new_state, new_energy);
+
+        let master = self.master.clone().unwrap();
+        let de = self.acceptance.delta(
+            problem,
+            new_state,
+            new_energy,
+            &master.state,
+            master.energy,
+        );
+        let range = Range::new(0.0, 1.0);
+        let worker_rng = self.round.as_mut().unwrap().workers[worker_nr].problem.rng();
+
+        if de > 0.0 || range.ind_sample(worker_rng) <= (de / self.temperature.get()).exp() {
+            self.master = Some(MrResult {
+                energy: new_energy,
+                state: new_state.clone(),
+            });
+            if de > 0.0 {
+                self.subsequent_rej = 0;
             }
+        } else {
+            self.subsequent_rej += 1;
+        }
+    }
+}
 
-            if subsequent_rej.get() > 300 {
-                println!("{} Convergence Reached!!!", Green.paint("[TUNER]"));
-                break 'outer;
+impl Solver for Spisa {
+    /// With the racing evaluation the candidates of each ask are raced against each other
+    fn solve(&mut self, problem: &mut Problem, num_workers: usize) -> MrResult {
+        match self.tuner_params.evaluation {
+            EvaluationMode::racing => {
+                let tuner_params = self.tuner_params.clone();
+                scheduler::run_batches(self, problem, &tuner_params, num_workers)
             }
-            elapsed_time = (time::precise_time_ns() - start_time) as f64 / 1000000000.0f64;
+            EvaluationMode::full => scheduler::run(self, problem, num_workers),
+        }
+    }
+}
 
-            println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
-            println!(
-                "{} Completed Steps: {:.2} - Percentage of Completion: {:.2}% - Estimated \
-                      time to Complete: {:.2} Hrs",
-                Green.paint("[TUNER]"),
-                elapsed_steps.get(),
-                (elapsed_steps.get() as f64 / self.tuner_params.max_step as f64) * 100.0,
-                time_2_complete_hrs as usize
-            );
-            println!(
-                "{} Total Accepted: {:?} - Subsequent Rejected: {:?} - Current Temperature: \
-                      {:.2} - Elapsed Time: {:.2} s",
-                Green.paint("[TUNER]"),
-                accepted.get(),
-                subsequent_rej.get(),
-                temperature.get(),
-                elapsed_time
-            );
-            println!(
-                "{} Accepted State: {:?}",
-                Green.paint("[TUNER]"),
-                master_state
-            );
+impl AskTell for Spisa {
+    fn start(&mut self, problem: &mut Problem, num_workers: usize) {
+        self.num_workers = num_workers;
+
+        if self.master.is_none() {
+            println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
             println!(
-                "{} Accepted Energy: {:.4}",
-                Green.paint("[TUNER]"),
-                master_energy
+                "{} Initialization Phase: Evaluation of Energy for Default Parameters",
+                Green.paint("[TUNER]")
             );
-
             println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+        }
 
+        // A resumed run continues the random stream of the interrupted one
+        if let Some(ckpt) = self.checkpointer.resumed_worker(0) {
+            problem.restore_rng(ckpt.rng_draws);
+        }
+    }
 
-            // Create the Pool of Neighborhoods
-            let neigh_space = problem.neigh_space(&master_state);
-            let neigh_pool = common::NeighborhoodsPool::new(neigh_space);
-
-            let threads_res = common::ThreadsResults::new();
-
-            let mut mb = MultiBar::new();
-
-
-
-
-            /// *********************************************************************************************************
-            let handles: Vec<_> = (0..num_workers).map(|worker_nr| {
-	 				let mut pb=mb.create_bar(neigh_pool.size()/num_workers as u64);
- 			        pb.show_message = true;
-		            					
-					let (mut master_state_c, mut problem_c) = (master_state.clone(), problem.clone());
-	            	let (elapsed_steps_c, temperature_c,
-	            		 neigh_pool_c, accepted_c,
-	            		 subsequent_rej_c,threads_res_c) = (elapsed_steps.clone(),
-	            		 							  temperature.clone(),
-	            		 							  neigh_pool.clone(), 
-	            		 							  accepted.clone(), 
-	            		 							  subsequent_rej.clone(),
-	            		 							  threads_res.clone());
-
-					let nrg_type = self.clone().tuner_params.energy;
-					
-					let tx_c=tx.clone();
-
-
-					/************************************************************************************************************/
-		            thread::spawn(move || {
-
-  			 			
-							let mut worker_nrg=master_energy.clone();
-							let mut worker_state=master_state_c.clone();
-  					        let range = Range::new(0.0, 1.0);
-		  					let mut rng = thread_rng();
- 							
-							let mut last_nrg=master_energy.clone();
-							let mut last_state=master_state_c.clone();
-							
-				            loop{
-				            	
-				            	
-				            	pb.message(&format!("TID [{}] - Neigh. Exploration Status - ", worker_nr));
-
-				            	worker_state = {
-	            	
-						                let next_state = match neigh_pool_c.remove_one(){
-							            		Some(res) => res,
-							            		None 	  => break,
-						            	};
-										
-										last_state=next_state.clone();
-									
-										let accepted_state = match problem_c.energy(&next_state.clone(), worker_nr) {
-						                    Some(new_energy) => {
-						            			println!("Thread : {:?} - Step: {:?} - State: {:?} - Energy: {:?}",worker_nr, elapsed_steps_c.get(),next_state,new_energy);
-												last_nrg=new_energy;
-						                        let de = match nrg_type {
-						                            EnergyType::throughput => new_energy - worker_nrg,
-						                            EnergyType::latency => -(new_energy - worker_nrg), 
-						                        };
-						
-						                        if de > 0.0 || range.ind_sample(&mut rng) <= (de / temperature_c.get()).exp() {
-						                            accepted_c.increment();
-						                        	
-						                            worker_nrg = new_energy;
-						
-						                            if de > 0.0 {
-														subsequent_rej_c.reset();
-						                            }
-						                          
-						                            next_state
-						
-						                        } else {
-													subsequent_rej_c.increment();					                        	
-							                           
-						                            worker_state
-						                        }
-						                    }
-						                    None => {
-						                        println!("{} The current configuration parameters cannot be evaluated. \
-						                                  Skip!",
-						                                 Green.paint("[TUNER]"));
-						                        worker_state
-						                    }
-						                };
-						                
-						                accepted_state
-						            };
-                                                            
-				            	   let intermediate_res=IntermediateResults{
-				            			last_nrg: last_nrg,
-				            			last_state:last_state.clone(),
-				            			best_nrg: worker_nrg,
-				            			best_state: worker_state.clone(),
-				            			tid: worker_nr,
-				            		};
-				            		
-				            		tx_c.send(intermediate_res);
-				            		
-					            	elapsed_steps_c.increment();
- 									pb.inc();	            	
-									temperature_c.update(elapsed_steps_c.get());	
-							}
-				            
-				            let res=common::MrResult{
-				            	energy: worker_nrg,
-				            	state: worker_state,
-				            };
-				            
-
-				            threads_res_c.push(res);	
-    		            	pb.finish_print(&format!("Child Thread [{}] Terminated the Execution", worker_nr));
-	                	
-		            })
-
-		        }).collect();
-
-            mb.listen();
-
-            // Wait for all threads to complete before start a search in a new set of neighborhoods.
-            for h in handles {
-                h.join().unwrap();
-            }
-
+    /// The free workers of the current round take a configuration from the pool of
+    /// neighborhoods. Once the pool is exhausted and all the workers are done, the round is
+    /// closed and a new one is started.
+    fn ask(&mut self, problem: &mut Problem, max_candidates: usize) -> Vec<Candidate> {
+        if self.finished || self.pending {
+            return Vec::new();
+        }
 
-            /// *********************************************************************************************************
+        if self.master.is_none() {
+            self.pending = true;
+            return vec![
+                Candidate {
+                    state: problem.initial_state(),
+                    slot: 0,
+                },
+            ];
+        }
 
-            // Get results of worker threads (each one will put its best evaluated energy) and
-            // choose between them which one will be the best
-            let mut workers_res = threads_res.get_coll();
-            let first_elem = workers_res.pop().unwrap();
+        if self.tuner_params.exchange == ExchangeMode::async {
+            return self.ask_async(problem, max_candidates);
+        }
 
-            let mut best_workers_nrg = first_elem.energy;
-            let mut best_workers_state = first_elem.state;
+        loop {
+            if self.round.is_none() {
+                match self.start_round(problem) {
+                    Some(round) => self.round = Some(round),
+                    None => return self.restart_candidate().into_iter().collect(),
+                }
+            }
 
-            for elem in workers_res.iter() {
-                let diff = match self.tuner_params.energy {
-                    EnergyType::throughput => elem.energy - best_workers_nrg,
-                    EnergyType::latency => -(elem.energy - best_workers_nrg), 
-                };
-                if diff > 0.0 {
-                    best_workers_nrg = elem.clone().energy;
-                    best_workers_state = elem.clone().state;
+            let mut candidates = Vec::new();
+            let mut round_over = true;
+            {
+                let round = self.round.as_mut().unwrap();
+                for (worker_nr, worker) in round.workers.iter_mut().enumerate() {
+                    if worker.pending {
+                        round_over = false;
+                        continue;
+                    }
+                    if worker.done {
+                        continue;
+                    }
+                    if candidates.len() >= max_candidates {
+                        round_over = false;
+                        continue;
+                    }
+                    if self.temperature.is_expired(self.elapsed_steps) {
+                        worker.done = true;
+                        continue;
+                    }
+                    match round.neigh_pool.remove_one(worker.problem.rng()) {
+                        Some(next_state) => {
+                            worker.pending = true;
+                            round_over = false;
+                            candidates.push(Candidate {
+                                state: next_state,
+                                slot: worker_nr,
+                            });
+                        }
+                        None => worker.done = true,
+                    }
                 }
             }
 
-            let de = match self.tuner_params.energy {
-                EnergyType::throughput => best_workers_nrg - master_energy,
-                EnergyType::latency => -(best_workers_nrg - master_energy), 
-            };
-            let range = Range::new(0.0, 1.0);
+            if !round_over {
+                return candidates;
+            }
+
+            let round = self.round.take().unwrap();
+            self.end_round(problem, round);
+        }
+    }
 
-            if de > 0.0 || range.ind_sample(&mut rng_c) <= (de / temperature.get()).exp() {
-                master_energy = best_workers_nrg;
-                master_state = best_workers_state;
-                if de > 0.0 {
-                    subsequent_rej.reset();
+    fn tell(&mut self, problem: &mut Problem, candidate: Candidate, new_energy: Option<f64>) {
+        let next_state = candidate.state;
+
+        if self.master.is_none() {
+            // Evaluation of the initial state
+            self.pending = false;
+            let master_energy = match new_energy {
+                Some(nrg) => nrg,
+                None => {
+                    panic!("The initial configuration does not allow to calculate the energy")
                 }
+            };
+            self.exec_time = (time::precise_time_ns() - self.start_time) as f64 / 1000000000.0f64;
+            self.start_time = time::precise_time_ns();
+            self.master = Some(MrResult {
+                energy: master_energy,
+                state: next_state.clone(),
+            });
+            self.best = Some(MrResult {
+                energy: master_energy,
+                state: next_state,
+            });
+            return;
+        }
 
-            } else {
-                subsequent_rej.increment();
-            }
+        if candidate.slot == self.num_workers {
+            // Evaluation of the random restart of the master state
+            self.pending = false;
+            let master = self.master.clone().unwrap();
+            self.master = Some(self.stagnation.restarted(master, next_state, new_energy));
+            return;
+        }
 
+        let worker_nr = candidate.slot;
+        let is_async = self.tuner_params.exchange == ExchangeMode::async;
+        let stale = {
+            let worker = &mut self.round.as_mut().unwrap().workers[worker_nr];
+            worker.pending = false;
+            let stale = worker.stale;
+            worker.stale = false;
+            stale
+        };
 
+        if stale {
+            // The configuration comes from the neighborhoods of a previous master state, hence it
+            // is not compared with the current one. It only counts as a step.
+            if let Some(new_energy) = new_energy {
+                self.update_best(&next_state, new_energy);
+            }
+            self.elapsed_steps += 1;
+            self.temperature.update(self.elapsed_steps, self.accepted);
+            return;
         }
 
-        MrResult {
-            energy: master_energy,
-            state: master_state,
+        let round = self.round.as_mut().unwrap();
+        let worker = &mut round.workers[worker_nr];
+
+        let range = Range::new(0.0, 1.0);
+        let mut worker_accepted = false;
+
+        match new_energy {
+            Some(new_energy) => {
+                println!(
+                    "Thread : {:?} - Step: {:?} - State: {:?} - Energy: {:?}",
+                    worker_nr,
+                    self.elapsed_steps,
+                    next_state,
+                    new_energy
+                );
+                let de = self.acceptance.delta(
+                    &worker.problem,
+                    &next_state,
+                    new_energy,
+                    &worker.current.state,
+                    worker.current.energy,
+                );
+
+                if de > 0.0 || range.ind_sample(worker.problem.rng()) <= (de / self.temperature.get()).exp() {
+                    self.accepted += 1;
+
+                    // With the asynchronous exchange, the configuration is counted once by
+                    // update_master
+                    if de > 0.0 && !is_async {
+                        self.subsequent_rej = 0;
+                    }
+
+                    worker.current = MrResult {
+                        energy: new_energy,
+                        state: next_state.clone(),
+                    };
+                    worker_accepted = true;
+                } else {
+                    self.subsequent_rej += 1;
+                }
+
+                let elapsed_time = (time::precise_time_ns() - self.start_time) as f64 /
+                    1000000000.0f64;
+                self.res_emitter.send_update(
+                    self.temperature.get(),
+                    elapsed_time,
+                    0.0,
+                    new_energy,
+                    &next_state,
+                    worker.problem.repetitions(&next_state),
+                    worker.current.energy,
+                    &worker.current.state,
+                    self.elapsed_steps,
+                    worker_nr,
+                );
+            }
+            None => {
+                println!(
+                    "{} The current configuration parameters cannot be evaluated. \
+                          Skip!",
+                    Green.paint("[TUNER]")
+                );
+            }
+        };
+
+        if worker_accepted && is_async {
+            let new_energy = new_energy.unwrap();
+            self.update_master(problem, worker_nr, &next_state, new_energy);
         }
+
+        self.elapsed_steps += 1;
+        self.temperature.update(self.elapsed_steps, self.accepted);
+    }
+
+    fn best(&self) -> MrResult {
+        self.best.clone().unwrap()
     }
 }
@@ -0,0 +1,260 @@
+/// ///////////////////////////////////////////////////////////////////////////
+///  File: annealing/validate.rs
+/// ///////////////////////////////////////////////////////////////////////////
+///  Copyright 2017 Giovanni Mazzeo
+///
+///  Licensed under the Apache License, Version 2.0 (the "License");
+///  you may not use this file except in compliance with the License.
+///  You may obtain a copy of the License at
+///
+///      http://www.apache.org/licenses/LICENSE-2.0
+///
+///  Unless required by applicable law or agreed to in writing, software
+///  distributed under the License is distributed on an "AS IS" BASIS,
+///  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+///  See the License for the specific language governing permissions and
+///  limitations under the License.
+/// ///////////////////////////////////////////////////////////////////////////
+
+/// ****************************************************************************
+/// *****************************************************************************
+/// **
+/// Validation of the Best Configurations
+/// *
+/// *****************************************************************************
+/// ****************************************************************************
+use annealing::problem::Problem;
+use annealing::solver::common::MrResult;
+use energy_eval::{self, Distribution};
+use energy_eval::cache::EvalCache;
+use res_emitters::Emitter;
+
+use shared::TunerParameter;
+
+use CachePolicy;
+use EnergyType;
+use State;
+
+use ansi_term::Colour::{Green, Yellow};
+use std::thread;
+
+
+/// Outcome of the validation of a configuration: the distribution of its energy over the
+/// validation runs, the half-width of its confidence interval, its speedup over the default
+/// configuration and whether the difference from the default one is statistically significant
+#[derive(Debug, Clone)]
+pub struct ValidationResult {
+    pub label: String,
+    pub state: State,
+    pub dist: Distribution,
+    pub half_width: f64,
+    pub speedup: f64,
+    pub significant: bool,
+}
+
+
+/// The validation_top best distinct configurations evaluated during the search (the one found by
+/// the solver always among them) and the default configuration are measured validation_runs times
+/// more. The runs are interleaved: at each round every configuration is run once, in an order
+/// rotated at each round, so that a drift of the performance of the machines affects all of them
+/// alike. Only the validation runs are taken into account, hence the report is not biased by the
+/// lucky measurements that made a configuration win the search. The configuration with the best
+/// validated energy is returned.
+pub fn validate(
+    tuner_params: &TunerParameter,
+    res_emitter: &mut Emitter,
+    problem: &mut Problem,
+    found: MrResult,
+    num_workers: usize,
+) -> MrResult {
+
+    println!("{}",Green.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+    println!(
+        "{} Validation Phase: {} Runs of the {} Best Configurations and of the Default One",
+        Green.paint("[TUNER]"),
+        tuner_params.validation_runs,
+        tuner_params.validation_top
+    );
+    println!("{}",Green.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+    let fitness = |nrg: f64| match tuner_params.energy {
+        EnergyType::throughput => nrg,
+        EnergyType::latency => -nrg,
+    };
+
+    // The configurations are ranked by their energy over all their measurements
+    let default_state = problem.initial_state();
+    let default_key = EvalCache::key(&default_state);
+    let mut ranked: Vec<(State, f64)> = problem
+        .visited_states()
+        .into_iter()
+        .filter_map(|state| problem.distribution(&state).map(|d| (state, d.mean)))
+        .filter(|&(_, mean)| mean.is_finite())
+        .collect();
+    ranked.sort_by(|a, b| fitness(b.1).partial_cmp(&fitness(a.1)).unwrap());
+
+    let found_key = EvalCache::key(&found.state);
+    let mut candidates: Vec<(String, State)> = vec![("default".to_string(), default_state)];
+    let mut keys = vec![default_key];
+    let mut rank = 0;
+    for state in Some(found.state.clone()).into_iter().chain(
+        ranked.into_iter().map(|(state, _)| state),
+    )
+    {
+        if candidates.len() > tuner_params.validation_top {
+            break;
+        }
+        let key = EvalCache::key(&state);
+        if keys.contains(&key) {
+            continue;
+        }
+        let label = if key == found_key {
+            "solver".to_string()
+        } else {
+            rank += 1;
+            format!("top-{}", rank)
+        };
+        keys.push(key);
+        candidates.push((label, state));
+    }
+
+    let mut values: Vec<Vec<f64>> = vec![Vec::new(); candidates.len()];
+    if let CachePolicy::replay = problem.cache_policy {
+        // The benchmark is never executed: the report is built on the cached measurements
+        println!(
+            "{} Replay Cache Policy: the Validation uses the Cached Measurements",
+            Green.paint("[TUNER]")
+        );
+        for (i, &(_, ref state)) in candidates.iter().enumerate() {
+            if let Some(measurements) = problem.eval_cache.lookup(state) {
+                values[i] = measurements
+                    .iter()
+                    .filter(|m| m.valid)
+                    .flat_map(|m| m.values.iter().cloned())
+                    .collect();
+            }
+        }
+    } else {
+        for round in 0..tuner_params.validation_runs {
+            println!(
+                "{} Validation Round: {} of {}",
+                Green.paint("[TUNER]"),
+                round + 1,
+                tuner_params.validation_runs
+            );
+
+            let order: Vec<usize> = (0..candidates.len())
+                .map(|i| (i + round) % candidates.len())
+                .collect();
+            for batch in order.chunks(num_workers.max(1)) {
+                let handles: Vec<_> = batch
+                    .iter()
+                    .enumerate()
+                    .map(|(tid, &i)| {
+                        let mut problem_c = problem.clone();
+                        let state = candidates[i].1.clone();
+                        thread::spawn(move || (i, problem_c.measure_once(&state, tid)))
+                    })
+                    .collect();
+                for h in handles {
+                    let (i, nrg) = h.join().unwrap();
+                    if let Some(nrg) = nrg {
+                        values[i].push(nrg);
+                    }
+                }
+            }
+        }
+    }
+
+    let default_dist = match Distribution::from_values(&values[0]) {
+        Some(dist) => dist,
+        None => {
+            println!(
+                "{} The default configuration cannot be evaluated. Validation skipped!",
+                Green.paint("[TUNER]")
+            );
+            return found;
+        }
+    };
+
+    let mut report: Vec<ValidationResult> = Vec::new();
+    for ((label, state), vals) in candidates.into_iter().zip(values.into_iter()) {
+        let dist = match Distribution::from_values(&vals) {
+            Some(dist) => dist,
+            None => {
+                println!(
+                    "{} The configuration {} cannot be evaluated. Skip!",
+                    Green.paint("[TUNER]"),
+                    label
+                );
+                continue;
+            }
+        };
+
+        let half_width = if dist.n > 1 {
+            energy_eval::t_quantile((1.0 + tuner_params.confidence) / 2.0, (dist.n - 1) as f64) *
+                dist.sq_std_error().sqrt()
+        } else {
+            0.0
+        };
+        let speedup = match tuner_params.energy {
+            EnergyType::throughput => dist.mean / default_dist.mean,
+            EnergyType::latency => default_dist.mean / dist.mean,
+        };
+        let significant = match energy_eval::welch(&dist, &default_dist) {
+            Some((se, df)) => {
+                (dist.mean - default_dist.mean).abs() / se >=
+                    energy_eval::t_quantile((1.0 + tuner_params.confidence) / 2.0, df)
+            }
+            None => false,
+        };
+
+        report.push(ValidationResult {
+            label: label,
+            state: state,
+            dist: dist,
+            half_width: half_width,
+            speedup: speedup,
+            significant: significant,
+        });
+    }
+
+    println!("{}",Yellow.paint("\n-------------------------------------------------------------------------------------------------------------------"));
+    println!(
+        "{} {:.0}% confidence intervals",
+        Yellow.paint("Validation Report: "),
+        tuner_params.confidence * 100.0
+    );
+    for res in report.iter() {
+        println!(
+            "{} {} - {} {:.4} +- {:.4} ({} runs) - {} {:.4}x{} - {} {:?}",
+            Yellow.paint("==>"),
+            res.label,
+            Yellow.paint("Energy:"),
+            res.dist.mean,
+            res.half_width,
+            res.dist.n,
+            Yellow.paint("Speedup:"),
+            res.speedup,
+            if res.significant { "" } else { " (not significant)" },
+            Yellow.paint("Configuration:"),
+            res.state
+        );
+    }
+    println!("{}",Yellow.paint("-------------------------------------------------------------------------------------------------------------------"));
+
+    res_emitter.send_validation(&report);
+
+    // A configuration whose validated energy is not finite cannot be ranked
+    let best = report.iter().filter(|r| r.dist.mean.is_finite()).max_by(|a, b| {
+        fitness(a.dist.mean).partial_cmp(&fitness(b.dist.mean)).unwrap()
+    });
+
+    match best {
+        Some(best) => MrResult {
+            energy: best.dist.mean,
+            state: best.state.clone(),
+        },
+        None => found,
+    }
+}
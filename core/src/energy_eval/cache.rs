@@ -0,0 +1,291 @@
+use rustc_serialize::json;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use ansi_term::Colour::{Green, Red};
+use energy_eval::{self, Measurement, Distribution};
+use {EnergyType, CachePolicy};
+use State;
+
+
+/// Memoization of the energy evaluations. Every measurement of a configuration is kept, so that
+/// the solvers can reuse it or measure the configuration again. If a file is given, the cache
+/// is loaded from it at start-up and written back after each new measurement, so that it
+/// survives across tuning runs.
+#[derive(Debug, Clone)]
+pub struct EvalCache {
+    path: Option<String>,
+    entries: Arc<Mutex<HashMap<String, Vec<Measurement>>>>,
+    visited: Arc<Mutex<HashMap<String, State>>>,
+}
+
+
+impl EvalCache {
+    pub fn new(path: Option<String>) -> Self {
+        let mut entries: HashMap<String, Vec<Measurement>> = HashMap::new();
+
+        if let Some(ref p) = path {
+            let mut content = String::new();
+            if let Ok(mut f) = File::open(p) {
+                f.read_to_string(&mut content).unwrap();
+                entries = match json::decode(&content) {
+                    Ok(e) => e,
+                    Err(e) => panic!("The evaluation cache {} cannot be decoded: {:?}", p, e),
+                };
+                println!(
+                    "{} Loaded {} cached configurations from {}",
+                    Green.paint("[TUNER]"),
+                    entries.len(),
+                    p
+                );
+            }
+        }
+
+        EvalCache {
+            path: path,
+            entries: Arc::new(Mutex::new(entries)),
+            visited: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Canonical form of a state: its name=value sequence, whose parameters are sorted by name
+    pub fn key(state: &State) -> String {
+        state.encode_params()
+    }
+
+    /// All the measurements taken so far for the state, if any
+    pub fn lookup(&self, state: &State) -> Option<Vec<Measurement>> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(&EvalCache::key(state)).cloned()
+    }
+
+    /// Energy of a state evaluated over all its valid cached measurements. It returns
+    /// Some(None) if the state has been measured but it was never possible to evaluate it.
+    pub fn cached_energy(&self, state: &State) -> Option<Option<f64>> {
+        self.cached_distribution(state).map(|d| d.map(|d| d.mean))
+    }
+
+    /// Distribution of the energy of a state over the repetitions of all its valid cached
+    /// measurements, as for the energy
+    pub fn cached_distribution(&self, state: &State) -> Option<Option<Distribution>> {
+        let measurements = match self.lookup(state) {
+            Some(m) => m,
+            None => return None,
+        };
+
+        let values: Vec<f64> = measurements
+            .iter()
+            .filter(|m| m.valid)
+            .flat_map(|m| m.values.iter().cloned())
+            .collect();
+
+        Some(Distribution::from_values(&values))
+    }
+
+    /// Distribution that the cache policy takes instead of running the benchmark, if any. With
+    /// reuse, a configuration with less than min_runs valid repetitions (e.g. only screened by the
+    /// racing evaluation) is measured again, while replay takes whatever has been cached and
+    /// remeasure never takes the cache.
+    pub fn serve(
+        &self,
+        state: &State,
+        policy: &CachePolicy,
+        min_runs: usize,
+    ) -> Option<Option<Distribution>> {
+        match *policy {
+            CachePolicy::reuse => {
+                match self.cached_distribution(state) {
+                    Some(Some(dist)) => if dist.n >= min_runs { Some(Some(dist)) } else { None },
+                    other => other,
+                }
+            }
+            CachePolicy::replay => self.cached_distribution(state),
+            CachePolicy::remeasure => None,
+        }
+    }
+
+    /// Objectives of a state evaluated over all its valid cached measurements, as for the energy
+    pub fn cached_objectives(
+        &self,
+        state: &State,
+        objectives: &Vec<EnergyType>,
+    ) -> Option<Option<Vec<f64>>> {
+        let measurements = match self.lookup(state) {
+            Some(m) => m,
+            None => return None,
+        };
+
+        let metrics: Vec<HashMap<String, f64>> = measurements
+            .iter()
+            .filter(|m| m.valid)
+            .flat_map(|m| m.metrics.iter().cloned())
+            .collect();
+
+        Some(energy_eval::average_objectives(&metrics, objectives))
+    }
+
+    /// Keep track of a configuration evaluated during the current tuning run, whether it has been
+    /// measured or taken from the cache
+    pub fn visit(&self, state: &State) {
+        let mut visited = self.visited.lock().unwrap();
+        visited.entry(EvalCache::key(state)).or_insert(state.clone());
+    }
+
+    /// Configurations evaluated during the current tuning run, in the order of their keys
+    pub fn visited(&self) -> Vec<State> {
+        let visited = self.visited.lock().unwrap();
+        let mut keys: Vec<&String> = visited.keys().collect();
+        keys.sort();
+        keys.into_iter().map(|k| visited.get(k).unwrap().clone()).collect()
+    }
+
+    pub fn record(&self, state: &State, measurement: Measurement) {
+        let mut entries = self.entries.lock().unwrap();
+        entries
+            .entry(EvalCache::key(state))
+            .or_insert(Vec::new())
+            .push(measurement);
+
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return,
+        };
+
+        let encoded = json::encode(&*entries).unwrap();
+        let tmp_path = format!("{}.tmp", path);
+        let res = File::create(&tmp_path)
+            .and_then(|mut f| f.write_all(encoded.as_bytes()))
+            .and_then(|_| fs::rename(&tmp_path, &path));
+
+        if let Err(e) = res {
+            println!(
+                "{} The evaluation cache {} cannot be written: {}",
+                Red.paint("[TUNER]"),
+                path,
+                e
+            );
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state::ParamValue;
+
+    fn state(params: &[(&str, ParamValue)]) -> State {
+        params
+            .iter()
+            .map(|&(name, ref value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    fn measurement(values: &[f64], valid: bool) -> Measurement {
+        Measurement {
+            values: values.to_vec(),
+            metrics: Vec::new(),
+            timestamp: 0,
+            valid: valid,
+        }
+    }
+
+    fn sample() -> State {
+        state(&[
+            ("threads", ParamValue::Int(4)),
+            ("ratio", ParamValue::float(0.5)),
+        ])
+    }
+
+    #[test]
+    fn key_does_not_depend_on_the_insertion_order() {
+        let a = sample();
+        let b = state(&[
+            ("ratio", ParamValue::float(0.5)),
+            ("threads", ParamValue::Int(4)),
+        ]);
+        assert_eq!(EvalCache::key(&a), EvalCache::key(&b));
+        assert_eq!(EvalCache::key(&a), a.encode_params());
+
+        let c = state(&[
+            ("threads", ParamValue::Int(4)),
+            ("ratio", ParamValue::float(0.2 + 0.3)),
+        ]);
+        assert_eq!(EvalCache::key(&a), EvalCache::key(&c));
+
+        let d = state(&[
+            ("threads", ParamValue::Int(8)),
+            ("ratio", ParamValue::float(0.5)),
+        ]);
+        assert!(EvalCache::key(&a) != EvalCache::key(&d));
+    }
+
+    #[test]
+    fn record_accumulates_the_measurements() {
+        let cache = EvalCache::new(None);
+        let s = sample();
+        assert!(cache.lookup(&s).is_none());
+        assert!(cache.cached_distribution(&s).is_none());
+
+        cache.record(&s, measurement(&[1.0, 3.0], true));
+        cache.record(&s, measurement(&[100.0], false));
+        cache.record(&s, measurement(&[5.0], true));
+        assert_eq!(cache.lookup(&s).unwrap().len(), 3);
+
+        let dist = cache.cached_distribution(&s).unwrap().unwrap();
+        assert_eq!(dist.n, 3);
+        assert_eq!(dist.mean, 3.0);
+        assert_eq!(cache.cached_energy(&s), Some(Some(3.0)));
+    }
+
+    #[test]
+    fn invalid_measurements_give_no_energy() {
+        let cache = EvalCache::new(None);
+        let s = sample();
+        cache.record(&s, measurement(&[], false));
+        assert!(cache.lookup(&s).is_some());
+        assert!(cache.cached_distribution(&s).unwrap().is_none());
+        assert_eq!(cache.cached_energy(&s), Some(None));
+    }
+
+    #[test]
+    fn reuse_serves_only_full_evaluations() {
+        let cache = EvalCache::new(None);
+        let s = sample();
+        assert!(cache.serve(&s, &CachePolicy::reuse, 3).is_none());
+
+        // A racing screen with less repetitions than a full evaluation
+        cache.record(&s, measurement(&[2.0], true));
+        assert!(cache.serve(&s, &CachePolicy::reuse, 3).is_none());
+
+        cache.record(&s, measurement(&[4.0, 6.0], true));
+        let dist = cache.serve(&s, &CachePolicy::reuse, 3).unwrap().unwrap();
+        assert_eq!(dist.n, 3);
+        assert_eq!(dist.mean, 4.0);
+
+        let failed = state(&[("threads", ParamValue::Int(1))]);
+        cache.record(&failed, measurement(&[], false));
+        assert!(cache.serve(&failed, &CachePolicy::reuse, 3).unwrap().is_none());
+    }
+
+    #[test]
+    fn remeasure_never_serves_the_cache() {
+        let cache = EvalCache::new(None);
+        let s = sample();
+        cache.record(&s, measurement(&[1.0, 2.0, 3.0], true));
+        assert!(cache.serve(&s, &CachePolicy::remeasure, 1).is_none());
+    }
+
+    #[test]
+    fn replay_serves_whatever_has_been_cached() {
+        let cache = EvalCache::new(None);
+        let s = sample();
+        assert!(cache.serve(&s, &CachePolicy::replay, 3).is_none());
+
+        cache.record(&s, measurement(&[2.0], true));
+        let dist = cache.serve(&s, &CachePolicy::replay, 3).unwrap().unwrap();
+        assert_eq!(dist.n, 1);
+        assert_eq!(dist.mean, 2.0);
+    }
+}
@@ -1,3 +1,5 @@
+pub mod cache;
+
 use time;
 use pbr;
 use pbr::{ProgressBar, MultiBar};
@@ -8,12 +10,14 @@ use std::collections::HashMap;
 use ansi_term::Colour::{Red, Yellow, Green};
 use EnergyType;
 use ExecutionType;
+use RepetitionMode;
 use State;
 use shared::{TunerParameter, ProcessPool, ScriptInfo};
 use ctrlc;
 use zmq;
 use std::process;
 use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex};
 use std::sync::mpsc::{Receiver, channel};
 
 
@@ -22,6 +26,178 @@ pub struct EnergyEval {
     targets_pool: ProcessPool,
     benchs_pool: ProcessPool,
     tuner_params: TunerParameter,
+    best_nrg: Arc<Mutex<Option<f64>>>,
+}
+
+
+/// Outcome of the evaluation of a configuration: the energy measured by each repetition of the
+/// benchmark, all the metrics returned by each repetition, when the evaluation has been
+/// performed and whether the configuration was valid
+#[derive(Clone, Debug, RustcEncodable, RustcDecodable)]
+pub struct Measurement {
+    pub values: Vec<f64>,
+    pub metrics: Vec<HashMap<String, f64>>,
+    pub timestamp: i64,
+    pub valid: bool,
+}
+
+/// Distribution of the energy of a configuration over the repetitions of the benchmark
+#[derive(Clone, Debug)]
+pub struct Distribution {
+    pub mean: f64,
+    pub variance: f64,
+    pub n: usize,
+}
+
+impl Distribution {
+    /// Sample mean and variance of the given values. The variance of a single value is zero.
+    pub fn from_values(values: &Vec<f64>) -> Option<Distribution> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let n = values.len();
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let variance = if n > 1 {
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.0
+        };
+
+        Some(Distribution {
+            mean: mean,
+            variance: variance,
+            n: n,
+        })
+    }
+
+    /// Squared standard error of the mean
+    pub fn sq_std_error(&self) -> f64 {
+        self.variance / self.n as f64
+    }
+}
+
+impl Measurement {
+    /// Average energy of the repetitions, if the configuration could be evaluated
+    pub fn energy(&self) -> Option<f64> {
+        self.distribution().map(|d| d.mean)
+    }
+
+    /// Distribution of the energy over the repetitions, if the configuration could be evaluated
+    pub fn distribution(&self) -> Option<Distribution> {
+        if self.valid {
+            Distribution::from_values(&self.values)
+        } else {
+            None
+        }
+    }
+
+    /// Average value of each objective over the repetitions, if the configuration could be
+    /// evaluated and the benchmark returned all the objectives
+    pub fn objectives(&self, objectives: &Vec<EnergyType>) -> Option<Vec<f64>> {
+        if self.valid {
+            average_objectives(&self.metrics, objectives)
+        } else {
+            None
+        }
+    }
+}
+
+
+/// Name of the metric returned by the bench agent for an objective
+pub fn metric_name(objective: &EnergyType) -> String {
+    format!("{:?}", objective)
+}
+
+
+/// Average value of each objective over the given repetitions
+pub fn average_objectives(
+    metrics: &Vec<HashMap<String, f64>>,
+    objectives: &Vec<EnergyType>,
+) -> Option<Vec<f64>> {
+    let mut res = Vec::with_capacity(objectives.len());
+    for objective in objectives.iter() {
+        let name = metric_name(objective);
+        let values: Vec<f64> = metrics.iter().filter_map(|m| m.get(&name).cloned()).collect();
+        if values.is_empty() {
+            return None;
+        }
+        res.push(values.iter().sum::<f64>() / values.len() as f64);
+    }
+    Some(res)
+}
+
+
+/// Quantile of the standard normal distribution (Abramowitz and Stegun 26.2.23, with an
+/// absolute error lower than 4.5e-4)
+pub fn normal_quantile(p: f64) -> f64 {
+    if p < 0.5 {
+        return -normal_quantile(1.0 - p);
+    }
+
+    let t = (-2.0 * (1.0 - p).ln()).sqrt();
+    t -
+        (2.515517 + 0.802853 * t + 0.010328 * t * t) /
+            (1.0 + 1.432788 * t + 0.189269 * t * t + 0.001308 * t * t * t)
+}
+
+
+/// Quantile of the Student's t distribution with df degrees of freedom, by the Cornish-Fisher
+/// expansion around the normal quantile
+pub fn t_quantile(p: f64, df: f64) -> f64 {
+    let z = normal_quantile(p);
+    let (z3, z5) = (z.powi(3), z.powi(5));
+    z + (z3 + z) / (4.0 * df) + (5.0 * z5 + 16.0 * z3 + 3.0 * z) / (96.0 * df * df)
+}
+
+
+/// Standard error of the difference between the means of two distributions and its degrees of
+/// freedom (Welch-Satterthwaite). None if any of them has less than two repetitions or if both
+/// have no variance.
+pub fn welch(a: &Distribution, b: &Distribution) -> Option<(f64, f64)> {
+    if a.n < 2 || b.n < 2 {
+        return None;
+    }
+
+    let (se_a, se_b) = (a.sq_std_error(), b.sq_std_error());
+    let se = se_a + se_b;
+    if se <= 0.0 {
+        return None;
+    }
+
+    let df = se * se / (se_a * se_a / (a.n - 1) as f64 + se_b * se_b / (b.n - 1) as f64);
+    Some((se.sqrt(), df))
+}
+
+
+/***
+	Parse the result sent by the bench agent. It is either a single value, which is the energy
+	selected by the user, or a sequence of metrics in the form name=value|name=value|
+	***/
+fn parse_metrics(result_meas: &str, energy: &EnergyType) -> Option<HashMap<String, f64>> {
+    let mut metrics = HashMap::new();
+
+    if !result_meas.contains('=') {
+        match result_meas.trim().parse::<f64>() {
+            Ok(val) => {
+                metrics.insert(metric_name(energy), val);
+            }
+            Err(_) => return None,
+        }
+        return Some(metrics);
+    }
+
+    for metric in result_meas.split('|').filter(|m| !m.is_empty()) {
+        let mut pair = metric.splitn(2, '=');
+        let name = pair.next().unwrap().trim().to_string();
+        match pair.next().map(|v| v.trim().parse::<f64>()) {
+            Some(Ok(val)) => {
+                metrics.insert(name, val);
+            }
+            _ => return None,
+        }
+    }
+    Some(metrics)
 }
 
 
@@ -46,16 +222,157 @@ impl EnergyEval {
             targets_pool: t_pool,
             benchs_pool: b_pool,
             tuner_params: tuner_params,
+            best_nrg: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Maximum number of repetitions of the benchmark for a configuration
+    fn max_repetitions(&self) -> u8 {
+        match self.tuner_params.repetitions {
+            RepetitionMode::fixed => self.tuner_params.num_iter,
+            RepetitionMode::adaptive => self.tuner_params.max_iter.max(self.tuner_params.num_iter),
+        }
+    }
+
+    /// Minimum number of repetitions of a full evaluation of a configuration
+    pub fn min_repetitions(&self) -> usize {
+        self.tuner_params.num_iter as usize
+    }
+
+    /***
+	With the adaptive repetitions, a configuration is repeated at least num_iter times and then
+	until the confidence interval of its energy is narrow enough (ci_width times the mean) or
+	its upper confidence bound is worse than the best energy measured so far
+	***/
+    fn enough_repetitions(&self, values: &Vec<f64>, valid_result: bool, tid: usize) -> bool {
+        if let RepetitionMode::fixed = self.tuner_params.repetitions {
+            return false;
+        }
+
+        if !valid_result {
+            return true;
+        }
+
+        let n = values.len();
+        if n < (self.tuner_params.num_iter as usize).max(2) {
+            return false;
+        }
+
+        let dist = Distribution::from_values(values).unwrap();
+        let half_width = self.half_width(&dist);
+
+        if half_width <= self.tuner_params.ci_width * dist.mean.abs() {
+            println!(
+                "Thread [{}] {} {:.4} +- {:.4} after {} repetitions",
+                tid,
+                Green.paint("====> Confidence Interval Reached: "),
+                dist.mean,
+                half_width,
+                n
+            );
+            return true;
+        }
+
+        let clearly_worse = match *self.best_nrg.lock().unwrap() {
+            Some(best) => {
+                match self.tuner_params.energy {
+                    EnergyType::throughput => dist.mean + half_width < best,
+                    EnergyType::latency => dist.mean - half_width > best,
+                }
+            }
+            None => false,
+        };
+        if clearly_worse {
+            println!(
+                "Thread [{}] {} {:.4} +- {:.4} after {} repetitions",
+                tid,
+                Green.paint("====> Clearly Worse than the Best Energy: "),
+                dist.mean,
+                half_width,
+                n
+            );
+            return true;
+        }
+
+        false
+    }
+
+    /// Half-width of the confidence interval of the mean energy, at the Confidence level
+    fn half_width(&self, dist: &Distribution) -> f64 {
+        t_quantile((1.0 + self.tuner_params.confidence) / 2.0, (dist.n - 1) as f64) *
+            dist.sq_std_error().sqrt()
+    }
+
+    /***
+	Keep track of the best energy measured by all the evaluators, which is the reference of the
+	clearly worse configurations. Only the full adaptive evaluations update it, with the lower
+	confidence bound of their energy (the upper one for the latency), so that a lucky brief or
+	shortened run does not cut off the following evaluations.
+	***/
+    fn update_best(&self, values: &Vec<f64>) {
+        if values.len() < 2 {
+            return;
+        }
+        let dist = Distribution::from_values(values).unwrap();
+        let half_width = self.half_width(&dist);
+        let nrg = match self.tuner_params.energy {
+            EnergyType::throughput => dist.mean - half_width,
+            EnergyType::latency => dist.mean + half_width,
+        };
+
+        let mut best_nrg = self.best_nrg.lock().unwrap();
+        let is_best = match *best_nrg {
+            Some(best) => {
+                match self.tuner_params.energy {
+                    EnergyType::throughput => nrg > best,
+                    EnergyType::latency => nrg < best,
+                }
+            }
+            None => true,
+        };
+        if is_best {
+            *best_nrg = Some(nrg);
         }
     }
 
     /***
 	Execute an an instance of the benchmark on the target application for the specific
 	configuration of parameters. The function returns the cost result (in this case the response throughput)
-	that will be used by the simulated annealing algorithm for the energy evaluation
+	that will be used by the simulated annealing algorithm for the energy evaluation, together with
+	the results of the single repetitions
+	***/
+
+    pub fn execute_test_instance(&mut self, params: &State, tid: usize) -> Measurement {
+        let max_iter = self.max_repetitions();
+        self.execute_runs(params, tid, max_iter, true, 1.0)
+    }
+
+    /***
+	Brief execution of the benchmark: exactly num_runs repetitions, whatever the repetition mode.
+	It is used by the racing evaluation to screen the configurations.
+	***/
+    pub fn execute_brief_instance(&mut self, params: &State, tid: usize, num_runs: u8) -> Measurement {
+        self.execute_runs(params, tid, num_runs, false, 1.0)
+    }
+
+    /***
+	Execution of the benchmark at a reduced fidelity, i.e., a fraction of its full duration (num_iter
+	repetitions). The fidelity is sent to the bench agent, which shortens the run accordingly.
+	It is used by the multi-fidelity solvers to screen the configurations.
 	***/
+    pub fn execute_fidelity_instance(&mut self, params: &State, tid: usize, fidelity: f64) -> Measurement {
+        let num_iter = self.tuner_params.num_iter;
+        self.execute_runs(params, tid, num_iter, false, fidelity)
+    }
 
-    pub fn execute_test_instance(&mut self, params: &State, tid: usize) -> Option<f64> {
+    fn execute_runs(
+        &mut self,
+        params: &State,
+        tid: usize,
+        max_iter: u8,
+        adaptive: bool,
+        fidelity: f64,
+    ) -> Measurement {
 
         //Init ZMQ context
         let mut msg = zmq::Message::new();
@@ -80,8 +397,10 @@ impl EnergyEval {
 
         let mut valid_result: bool = false;
 
-        // Repeat the execution num_iter times for accurate results
-        let mut nrg_vec = Vec::with_capacity(self.tuner_params.num_iter as usize);
+        // Repeat the execution num_iter times for accurate results (up to max_iter times with
+        // the adaptive repetitions)
+        let mut nrg_vec = Vec::with_capacity(max_iter as usize);
+        let mut metrics_vec = Vec::with_capacity(max_iter as usize);
         println!(
             "{} TID [{}] - Evaluation of: {:?}",
             Green.paint("====>"),
@@ -91,10 +410,18 @@ impl EnergyEval {
         println!(
             "{} Waiting for {} iterations to complete",
             Green.paint("====>"),
-            self.tuner_params.num_iter
+            max_iter
         );
 
-        let mut pb = ProgressBar::new(self.tuner_params.num_iter as u64);
+        // The full-length runs keep the plain request, so that they do not depend on the
+        // fidelity support of the bench agent
+        let bench_request = if fidelity < 1.0 {
+            format!("start_bench|fidelity={}", fidelity)
+        } else {
+            "start_bench".to_string()
+        };
+
+        let mut pb = ProgressBar::new(max_iter as u64);
         pb.format("╢▌▌░╟");
         pb.show_message = true;
         pb.message(&format!("Thread [{}] - ", tid));
@@ -102,7 +429,7 @@ impl EnergyEval {
 
         let mut measured_nrg: f64 = 0.0;
 
-        for i in 0..self.tuner_params.num_iter {
+        for i in 0..max_iter {
             pb.inc();
 
             let (stop_tx, stop_rx) = channel::<bool>();
@@ -121,10 +448,7 @@ impl EnergyEval {
             /// Launch Target and Benchmark Applications by sending messages to related agents
             /// *
             	************************************************************************************************************/
-            let mut params_sequence = String::new();
-            for (name, value) in params.iter() {
-                params_sequence += format!("{}={}|", name, value).as_str();
-            }
+            let params_sequence = params.encode_params();
 
 
             req_socket_targ
@@ -137,7 +461,7 @@ impl EnergyEval {
 
             if msg.as_str().unwrap() == "target_ok" {
                 //Target correctly started, we can start the Benchmark now
-                req_socket_bench.send("start_bench", 0).unwrap();
+                req_socket_bench.send(bench_request.as_str(), 0).unwrap();
 
                 req_socket_bench.recv(&mut msg, 0).unwrap();
                 let result_meas = msg.as_str().unwrap();
@@ -148,10 +472,26 @@ impl EnergyEval {
                     stop_tx.clone().send(true);
                     println!("Not a valid target configuration");
                 } else {
-                    valid_result = true;
-                    measured_nrg = result_meas.parse::<f64>().unwrap();
-                    nrg_vec.push(measured_nrg);
-                    println!("Received from bench {:?}", measured_nrg);
+                    let metrics = parse_metrics(result_meas, &self.tuner_params.energy);
+                    match metrics.as_ref().and_then(
+                        |m| m.get(&metric_name(&self.tuner_params.energy)),
+                    ) {
+                        Some(nrg) => {
+                            valid_result = true;
+                            measured_nrg = *nrg;
+                            nrg_vec.push(measured_nrg);
+                            println!("Received from bench {:?}", result_meas);
+                        }
+                        None => {
+                            //The benchmark did not return the energy selected by the user
+                            valid_result = false;
+                            measured_nrg = 0.0;
+                            println!("Not a valid result from bench {:?}", result_meas);
+                        }
+                    }
+                    if let Some(m) = metrics {
+                        metrics_vec.push(m);
+                    }
                 }
 
             } else {
@@ -182,13 +522,29 @@ impl EnergyEval {
             self.benchs_pool.push(bench.clone().to_string());
             stop_tx.clone().send(true);
 
+            if adaptive && self.enough_repetitions(&nrg_vec, valid_result, tid) {
+                break;
+            }
         }
 
         pb.finish();
 
-        if valid_result {
-            let sum_nrg: f64 = nrg_vec.iter().sum();
-            let avg_nrg = sum_nrg / self.tuner_params.num_iter as f64;
+        let measurement = Measurement {
+            values: nrg_vec,
+            metrics: metrics_vec,
+            timestamp: time::get_time().sec,
+            valid: valid_result,
+        };
+
+        let full_adaptive = match self.tuner_params.repetitions {
+            RepetitionMode::adaptive => adaptive && fidelity >= 1.0,
+            RepetitionMode::fixed => false,
+        };
+        if full_adaptive && measurement.valid {
+            self.update_best(&measurement.values);
+        }
+
+        if let Some(avg_nrg) = measurement.energy() {
             match self.tuner_params.energy {
                 EnergyType::throughput => {
                     println!(
@@ -208,12 +564,9 @@ impl EnergyEval {
                 }
             };
             println!("{}",Yellow.paint("==================================================================================================================="));
-
-            return Some(avg_nrg);
-        } else {
-            return None;
         }
 
+        measurement
     }
 
     fn set_stop_handler(&mut self, target: String, bench: String, stop_rx: Receiver<bool>) {
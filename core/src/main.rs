@@ -33,6 +33,7 @@ use annealing::problem::Problem;
 use annealing::solver::seqsa;
 use annealing::solver::Solver;
 use annealing::solver::common::MrResult;
+use annealing::solver::checkpoint;
 
 mod annealing;
 mod res_emitters;
@@ -41,8 +42,9 @@ mod states_gen;
 mod energy_eval;
 
 mod shared;
+mod state;
 
-type State = HashMap<String, String>;
+use state::{State, ParamValue};
 
 
 /***
@@ -71,7 +73,12 @@ fn main() {
 	***/
     let mut conf_generator = states_gen::ParamsConfigurator::new(
         xml_reader.get_target_int_params(),
+        xml_reader.get_target_float_params(),
         xml_reader.get_target_bool_params(),
+        xml_reader.get_target_enum_params(),
+        xml_reader.get_conditions(),
+        xml_reader.get_constraints(),
+        &xml_reader.get_tuner_params(),
     );
 
 
@@ -93,12 +100,24 @@ fn main() {
     let mut problem = Problem {
         params_configurator: conf_generator.clone(),
         energy_evaluator: energy_eval,
+        eval_cache: energy_eval::cache::EvalCache::new(xml_reader.get_tuner_params().eval_cache),
+        cache_policy: xml_reader.get_tuner_params().cache_policy,
     };
 
 
 
     let mut tuner_params = xml_reader.get_tuner_params();
 
+    /***
+	If the resume of a previous tuning run has been requested, load its last checkpoint. The
+	temperatures evaluated by that run are kept.
+	***/
+    let resumed = checkpoint::load(&tuner_params);
+    if let Some(ref ckpt) = resumed {
+        tuner_params.min_temp = Some(ckpt.min_temp);
+        tuner_params.max_temp = Some(ckpt.max_temp);
+    }
+
 
     let res_emitter = res_emitters::Emitter {
         influx_res_emitter: res_emitters::influx_emitter::InfluxEmitter::new(
@@ -110,59 +129,143 @@ fn main() {
         csv_res_emitter: res_emitters::csv_emitter::CSVEmitter::new(
             yml_reader.get_num_targets(),
             conf_generator.get_params_name(),
+            resumed.is_some(),
         ),
     };
 
 
-    annealing::eval_temperature(&mut tuner_params, &mut problem);
+    /***
+	The solvers that are not annealings do not need the temperatures, whose evaluation would cost
+	several runs of the benchmark
+	***/
+    match tuner_params.version {
+        SolverVersion::bo |
+        SolverVersion::random |
+        SolverVersion::grid |
+        SolverVersion::hyperband => annealing::default_temperature(&mut tuner_params),
+        _ => annealing::eval_temperature(&mut tuner_params, &mut problem),
+    }
 
     println!("temp: {:?}", tuner_params.max_temp);
 
+    let checkpointer = checkpoint::Checkpointer::new(&tuner_params, resumed);
+
+    let refine_params = tuner_params.clone();
+    let mut refine_emitter = res_emitter.clone();
+
     let mr_result = match tuner_params.version {
         SolverVersion::seqsa => {
-            let mut solver = annealing::solver::seqsa::Seqsa {
+            let mut solver =
+                annealing::solver::seqsa::Seqsa::new(tuner_params, res_emitter, checkpointer);
+
+            solver.solve(&mut problem, 1)
+        }
+        SolverVersion::spisa => {
+            let mut solver =
+                annealing::solver::spisa::Spisa::new(tuner_params, res_emitter, checkpointer);
+
+            solver.solve(&mut problem, yml_reader.get_num_targets())
+        }
+        SolverVersion::mir => {
+            let mut solver =
+                annealing::solver::mir::Mir::new(tuner_params, res_emitter, checkpointer);
+
+            solver.solve(&mut problem, yml_reader.get_num_targets())
+        }
+        SolverVersion::prsa => {
+            let mut solver =
+                annealing::solver::prsa::Prsa::new(tuner_params, res_emitter, checkpointer);
+
+            solver.solve(&mut problem, yml_reader.get_num_targets())
+        }
+        SolverVersion::ptsa => {
+            let mut solver = annealing::solver::ptsa::Ptsa {
                 tuner_params: tuner_params,
                 res_emitter: res_emitter,
+                checkpointer: checkpointer,
             };
 
-            solver.solve(&mut problem, 1)
+            solver.solve(&mut problem, yml_reader.get_num_targets())
         }
-        SolverVersion::spisa => {
-            let mut solver = annealing::solver::spisa::Spisa {
+        SolverVersion::bo => {
+            let mut solver = annealing::solver::bo::Bo {
                 tuner_params: tuner_params,
                 res_emitter: res_emitter,
+                checkpointer: checkpointer,
             };
 
             solver.solve(&mut problem, yml_reader.get_num_targets())
         }
-        SolverVersion::mir => {
-            let mut solver = annealing::solver::mir::Mir {
+        SolverVersion::random => {
+            let mut solver = annealing::solver::search::RandomSearch {
                 tuner_params: tuner_params,
                 res_emitter: res_emitter,
+                checkpointer: checkpointer,
             };
 
             solver.solve(&mut problem, yml_reader.get_num_targets())
         }
-        SolverVersion::prsa => {
-            let mut solver = annealing::solver::mir::Mir {
-                //TOADJUST
+        SolverVersion::grid => {
+            let mut solver = annealing::solver::search::GridSearch {
+                tuner_params: tuner_params,
+                res_emitter: res_emitter,
+                checkpointer: checkpointer,
+            };
+
+            solver.solve(&mut problem, yml_reader.get_num_targets())
+        }
+        SolverVersion::mosa => {
+            let mut solver = annealing::solver::mosa::Mosa {
                 tuner_params: tuner_params,
                 res_emitter: res_emitter,
             };
 
             solver.solve(&mut problem, yml_reader.get_num_targets())
-        }/*{
-        let mut solver = annealing::solver::prsa::Prsa {
-                min_temp: t_min,
-                max_temp: t_max,
-                max_steps: tuner_params.max_step,
-                population_size: 32,
-                energy_type: tuner_params.energy,
-                cooling_schedule: tuner_params.cooling,
+        }
+        SolverVersion::hyperband => {
+            let mut solver = annealing::solver::hyperband::Hyperband {
+                tuner_params: tuner_params,
+                res_emitter: res_emitter,
             };
 
-            solver.solve(&mut problem, yml_reader.get_num_targets()) //TODO
-        }*/
+            solver.solve(&mut problem, yml_reader.get_num_targets())
+        }
+    };
+
+    /***
+	Optional refinement phase: a local pattern search around the best configuration found by the
+	solver. The multi-objective solver returns a Pareto front, hence it is not refined.
+	***/
+    let mr_result = match refine_params.version {
+        SolverVersion::mosa => mr_result,
+        _ if refine_params.refine_steps == 0 => mr_result,
+        _ => {
+            annealing::refine::refine(
+                &refine_params,
+                &mut refine_emitter,
+                &mut problem,
+                mr_result,
+                yml_reader.get_num_targets(),
+            )
+        }
+    };
+
+    /***
+	Optional validation phase: the best configurations and the default one are measured again,
+	and the one with the best validated energy is the result of the tuning run
+	***/
+    let mr_result = match refine_params.version {
+        SolverVersion::mosa => mr_result,
+        _ if refine_params.validation_top == 0 => mr_result,
+        _ => {
+            annealing::validate::validate(
+                &refine_params,
+                &mut refine_emitter,
+                &mut problem,
+                mr_result,
+                yml_reader.get_num_targets(),
+            )
+        }
     };
 
     println!("{}",Yellow.paint("\n-----------------------------------------------------------------------------------------------------------------------------------------------"));
@@ -186,6 +289,14 @@ pub enum CoolingSchedule {
     linear,
     exponential,
     basic_exp_cooling,
+    lam,
+}
+
+#[derive(Debug, Clone, PartialEq, RustcDecodable)]
+pub enum NeighborhoodOperator {
+    uniform,
+    local,
+    gaussian,
 }
 
 #[derive(Debug, Clone, RustcDecodable)]
@@ -194,6 +305,52 @@ pub enum SolverVersion {
     spisa,
     mir,
     prsa,
+    ptsa,
+    bo,
+    random,
+    grid,
+    mosa,
+    hyperband,
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+pub enum StagnationPolicy {
+    stop,
+    reheat,
+    restart_best,
+    restart_random,
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+pub enum EvaluationMode {
+    full,
+    racing,
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+pub enum RepetitionMode {
+    fixed,
+    adaptive,
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+pub enum AcceptanceTest {
+    mean,
+    welch,
+    bound,
+}
+
+#[derive(Debug, Clone, PartialEq, RustcDecodable)]
+pub enum ExchangeMode {
+    sync,
+    async,
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+pub enum CachePolicy {
+    reuse,
+    remeasure,
+    replay,
 }
 
 #[derive(Debug, Clone, Copy, RustcDecodable)]
@@ -226,11 +383,24 @@ impl std::str::FromStr for CoolingSchedule {
             "linear" => Ok(CoolingSchedule::linear),
             "exponential" => Ok(CoolingSchedule::exponential),
             "basic_exp_cooling" => Ok(CoolingSchedule::basic_exp_cooling),
+            "lam" => Ok(CoolingSchedule::lam),
             _ => Err("Cooling Schedule - not a valid value"),
         }
     }
 }
 
+impl std::str::FromStr for NeighborhoodOperator {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(NeighborhoodOperator::uniform),
+            "local" => Ok(NeighborhoodOperator::local),
+            "gaussian" => Ok(NeighborhoodOperator::gaussian),
+            _ => Err("Neighborhood Operator - not a valid value"),
+        }
+    }
+}
+
 impl std::str::FromStr for SolverVersion {
     type Err = &'static str;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -239,11 +409,87 @@ impl std::str::FromStr for SolverVersion {
             "spisa" => Ok(SolverVersion::spisa),
             "mir" => Ok(SolverVersion::mir),
             "prsa" => Ok(SolverVersion::prsa),
+            "ptsa" => Ok(SolverVersion::ptsa),
+            "bo" => Ok(SolverVersion::bo),
+            "random" => Ok(SolverVersion::random),
+            "grid" => Ok(SolverVersion::grid),
+            "mosa" => Ok(SolverVersion::mosa),
+            "hyperband" => Ok(SolverVersion::hyperband),
             _ => Err("Solver Version - not a valid value"),
         }
     }
 }
 
+impl std::str::FromStr for StagnationPolicy {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stop" => Ok(StagnationPolicy::stop),
+            "reheat" => Ok(StagnationPolicy::reheat),
+            "restart_best" => Ok(StagnationPolicy::restart_best),
+            "restart_random" => Ok(StagnationPolicy::restart_random),
+            _ => Err("Stagnation Policy - not a valid value"),
+        }
+    }
+}
+
+impl std::str::FromStr for EvaluationMode {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(EvaluationMode::full),
+            "racing" => Ok(EvaluationMode::racing),
+            _ => Err("Evaluation Mode - not a valid value"),
+        }
+    }
+}
+
+impl std::str::FromStr for RepetitionMode {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(RepetitionMode::fixed),
+            "adaptive" => Ok(RepetitionMode::adaptive),
+            _ => Err("Repetition Mode - not a valid value"),
+        }
+    }
+}
+
+impl std::str::FromStr for AcceptanceTest {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mean" => Ok(AcceptanceTest::mean),
+            "welch" => Ok(AcceptanceTest::welch),
+            "bound" => Ok(AcceptanceTest::bound),
+            _ => Err("Acceptance Test - not a valid value"),
+        }
+    }
+}
+
+impl std::str::FromStr for ExchangeMode {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sync" => Ok(ExchangeMode::sync),
+            "async" => Ok(ExchangeMode::async),
+            _ => Err("Exchange Mode - not a valid value"),
+        }
+    }
+}
+
+impl std::str::FromStr for CachePolicy {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reuse" => Ok(CachePolicy::reuse),
+            "remeasure" => Ok(CachePolicy::remeasure),
+            "replay" => Ok(CachePolicy::replay),
+            _ => Err("Cache Policy - not a valid value"),
+        }
+    }
+}
+
 impl std::str::FromStr for EnergyType {
     type Err = &'static str;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -5,14 +5,27 @@ use std::collections::HashMap;
 use EnergyType;
 use ExecutionType;
 use CoolingSchedule;
+use NeighborhoodOperator;
 use SolverVersion;
-use shared::{IntParameter, BoolParameter, TunerParameter, ScriptInfo};
+use StagnationPolicy;
+use CachePolicy;
+use ExchangeMode;
+use AcceptanceTest;
+use RepetitionMode;
+use EvaluationMode;
+use shared::{IntParameter, FloatParameter, BoolParameter, EnumParameter, TunerParameter, ScriptInfo,
+             Scale};
+use states_gen::constraints::Constraint;
 
 #[derive(Debug, Clone)]
 pub struct XMLReader {
     hm_tuner: HashMap<String, String>,
     targ_int_param: Vec<IntParameter>,
+    targ_float_param: Vec<FloatParameter>,
     targ_bool_param: Vec<BoolParameter>,
+    targ_enum_param: Vec<EnumParameter>,
+    conditions: HashMap<String, Constraint>,
+    constraints: Vec<Constraint>,
     hm_script: HashMap<String, String>,
 }
 
@@ -26,17 +39,26 @@ impl XMLReader {
 
         let mut found_tuner = false;
         let mut found_int_targ = false;
+        let mut found_float_targ = false;
         let mut found_bool_targ = false;
+        let mut found_enum_targ = false;
         let mut found_script = false;
+        let mut found_constraints = false;
 
 
         let mut _hm_tuner: HashMap<String, String> = HashMap::new();
         let mut _hm_int_targ: HashMap<String, String> = HashMap::new();
+        let mut _hm_float_targ: HashMap<String, String> = HashMap::new();
         let mut _hm_bool_targ: HashMap<String, String> = HashMap::new();
+        let mut _hm_enum_targ: HashMap<String, String> = HashMap::new();
         let mut _hm_script_info: HashMap<String, String> = HashMap::new();
 
         let mut _targ_int_p: Vec<IntParameter> = Vec::new();
+        let mut _targ_float_p: Vec<FloatParameter> = Vec::new();
         let mut _targ_bool_p: Vec<BoolParameter> = Vec::new();
+        let mut _targ_enum_p: Vec<EnumParameter> = Vec::new();
+        let mut _conditions: HashMap<String, Constraint> = HashMap::new();
+        let mut _constraints: Vec<Constraint> = Vec::new();
 
 
         let mut tag = String::new();
@@ -47,33 +69,77 @@ impl XMLReader {
                     if name.to_string() == "tuner-params" {
                         found_tuner = true;
                         found_int_targ = false;
+                        found_float_targ = false;
                         found_bool_targ = false;
+                        found_enum_targ = false;
                         found_script = false;
                     } else if name.to_string() == "int-parameter" {
                         found_tuner = false;
                         found_int_targ = true;
+                        found_float_targ = false;
                         found_bool_targ = false;
+                        found_enum_targ = false;
                         found_script = false;
+                        // The elements of the previous parameter (e.g. its step) are not inherited
+                        _hm_int_targ.clear();
+                    } else if name.to_string() == "float-parameter" {
+                        found_tuner = false;
+                        found_int_targ = false;
+                        found_float_targ = true;
+                        found_bool_targ = false;
+                        found_enum_targ = false;
+                        found_script = false;
+                        _hm_float_targ.clear();
                     } else if name.to_string() == "bool-parameter" {
                         found_tuner = false;
                         found_int_targ = false;
+                        found_float_targ = false;
                         found_bool_targ = true;
+                        found_enum_targ = false;
                         found_script = false;
+                        _hm_bool_targ.clear();
+                    } else if name.to_string() == "enum-parameter" {
+                        found_tuner = false;
+                        found_int_targ = false;
+                        found_float_targ = false;
+                        found_bool_targ = false;
+                        found_enum_targ = true;
+                        found_script = false;
+                        _hm_enum_targ.clear();
                     } else if name.to_string() == "builder-script" {
                         found_tuner = false;
                         found_int_targ = false;
+                        found_float_targ = false;
                         found_bool_targ = false;
+                        found_enum_targ = false;
                         found_script = true;
+                    } else if name.to_string() == "constraints" {
+                        found_tuner = false;
+                        found_int_targ = false;
+                        found_float_targ = false;
+                        found_bool_targ = false;
+                        found_enum_targ = false;
+                        found_script = false;
+                        found_constraints = true;
                     }
 
                 }
                 Ok(XmlEvent::Characters(val)) => {
-                    if found_tuner == true {
+                    if found_constraints == true && tag == "constraint" {
+                        match Constraint::parse(&val) {
+                            Ok(constraint) => _constraints.push(constraint),
+                            Err(e) => panic!("Constraint {} - {}", val, e),
+                        }
+                    } else if found_tuner == true {
                         _hm_tuner.insert(tag.clone(), val.clone());
                     } else if found_int_targ == true {
                         _hm_int_targ.insert(tag.clone(), val.clone());
+                    } else if found_float_targ == true {
+                        _hm_float_targ.insert(tag.clone(), val.clone());
                     } else if found_bool_targ == true {
                         _hm_bool_targ.insert(tag.clone(), val.clone());
+                    } else if found_enum_targ == true {
+                        _hm_enum_targ.insert(tag.clone(), val.clone());
                     } else if found_script == true {
                         _hm_script_info.insert(tag.clone(), val.clone());
                     }
@@ -83,8 +149,14 @@ impl XMLReader {
 
                     if name.to_string() == "int-parameter" {
                         found_int_targ = false;
+                    } else if name.to_string() == "float-parameter" {
+                        found_float_targ = false;
                     } else if name.to_string() == "bool-parameter" {
                         found_bool_targ = false;
+                    } else if name.to_string() == "enum-parameter" {
+                        found_enum_targ = false;
+                    } else if name.to_string() == "constraints" {
+                        found_constraints = false;
                     }
 
 
@@ -103,12 +175,7 @@ impl XMLReader {
                                 .to_string()
                                 .parse::<usize>()
                                 .unwrap(),
-                            step: _hm_int_targ
-                                .get("step")
-                                .unwrap()
-                                .to_string()
-                                .parse::<usize>()
-                                .unwrap(),
+                            scale: parse_scale(&_hm_int_targ),
                             default: _hm_int_targ
                                 .get("default")
                                 .unwrap()
@@ -126,6 +193,39 @@ impl XMLReader {
                     }
 
 
+                    if name.to_string() == "float-parameter" {
+                        let targ_float_param = FloatParameter {
+                            name: _hm_float_targ.get("name").unwrap().to_string(),
+                            min: _hm_float_targ
+                                .get("minimum")
+                                .unwrap()
+                                .to_string()
+                                .parse::<f64>()
+                                .unwrap(),
+                            max: _hm_float_targ
+                                .get("maximum")
+                                .unwrap()
+                                .to_string()
+                                .parse::<f64>()
+                                .unwrap(),
+                            scale: parse_scale(&_hm_float_targ),
+                            default: _hm_float_targ
+                                .get("default")
+                                .unwrap()
+                                .to_string()
+                                .parse::<f64>()
+                                .unwrap(),
+                            level: _hm_float_targ
+                                .get("level")
+                                .unwrap()
+                                .to_string()
+                                .parse()
+                                .unwrap(),
+                        };
+                        _targ_float_p.push(targ_float_param);
+                    }
+
+
                     if name.to_string() == "bool-parameter" {
                         let mut targ_bool_param = BoolParameter {
                             name: _hm_bool_targ.get("name").unwrap().to_string(),
@@ -147,6 +247,57 @@ impl XMLReader {
                         _targ_bool_p.push(targ_bool_param);
                     }
 
+
+                    if name.to_string() == "enum-parameter" {
+                        let targ_enum_param = EnumParameter {
+                            name: _hm_enum_targ.get("name").unwrap().to_string(),
+                            values: _hm_enum_targ
+                                .get("values")
+                                .unwrap()
+                                .split(',')
+                                .map(|val| val.trim().to_string())
+                                .collect(),
+                            default: _hm_enum_targ.get("default").unwrap().trim().to_string(),
+                            level: _hm_enum_targ
+                                .get("level")
+                                .unwrap()
+                                .to_string()
+                                .parse()
+                                .unwrap(),
+                        };
+                        if !targ_enum_param.values.contains(&targ_enum_param.default) {
+                            panic!(
+                                "The default value of {} is not one of its values",
+                                targ_enum_param.name
+                            );
+                        }
+                        _targ_enum_p.push(targ_enum_param);
+                    }
+
+
+                    // A parameter with a condition is active only in the configurations that
+                    // satisfy it
+                    let hm_param = match name.to_string().as_str() {
+                        "int-parameter" => Some(&_hm_int_targ),
+                        "float-parameter" => Some(&_hm_float_targ),
+                        "bool-parameter" => Some(&_hm_bool_targ),
+                        "enum-parameter" => Some(&_hm_enum_targ),
+                        _ => None,
+                    };
+                    if let Some(hm_param) = hm_param {
+                        if let Some(text) = hm_param.get("condition") {
+                            match Constraint::parse(text) {
+                                Ok(condition) => {
+                                    _conditions.insert(
+                                        hm_param.get("name").unwrap().to_string(),
+                                        condition,
+                                    );
+                                }
+                                Err(e) => panic!("Condition {} - {}", text, e),
+                            }
+                        }
+                    }
+
                 }
 
                 Err(e) => {
@@ -161,7 +312,11 @@ impl XMLReader {
         XMLReader {
             hm_tuner: _hm_tuner,
             targ_int_param: _targ_int_p,
+            targ_float_param: _targ_float_p,
             targ_bool_param: _targ_bool_p,
+            targ_enum_param: _targ_enum_p,
+            conditions: _conditions,
+            constraints: _constraints,
             hm_script: _hm_script_info,
         }
     }
@@ -177,6 +332,17 @@ impl XMLReader {
     }
 
 
+    /***********************************************************************************************************
+    /// **
+    /// Target Enum Params
+    /// *
+    	************************************************************************************************************/
+
+    pub fn get_target_enum_params(&self) -> Vec<EnumParameter> {
+        return self.targ_enum_param.clone();
+    }
+
+
     /***********************************************************************************************************
     /// **
     /// Target Int Params
@@ -187,6 +353,37 @@ impl XMLReader {
         return self.targ_int_param.clone();
     }
 
+
+    /***********************************************************************************************************
+    /// **
+    /// Target Float Params
+    /// *
+    	************************************************************************************************************/
+
+    pub fn get_target_float_params(&self) -> Vec<FloatParameter> {
+        return self.targ_float_param.clone();
+    }
+
+    /***********************************************************************************************************
+    /// **
+    /// Constraints on the Target Params
+    /// *
+    	************************************************************************************************************/
+
+    pub fn get_constraints(&self) -> Vec<Constraint> {
+        return self.constraints.clone();
+    }
+
+    /***********************************************************************************************************
+    /// **
+    /// Activation Conditions of the Target Params
+    /// *
+    	************************************************************************************************************/
+
+    pub fn get_conditions(&self) -> HashMap<String, Constraint> {
+        return self.conditions.clone();
+    }
+
     /***********************************************************************************************************
     /// **
     /// Script Param
@@ -212,12 +409,45 @@ impl XMLReader {
     pub fn get_tuner_params(&self) -> TunerParameter {
         return TunerParameter {
             max_step: self.ann_max_steps(),
+            max_time: self.ann_max_time(),
             num_iter: self.ann_num_iter(),
+            repetitions: self.ann_repetitions(),
+            max_iter: self.ann_max_iter(),
+            ci_width: self.ann_ci_width(),
+            evaluation: self.ann_evaluation(),
             min_temp: self.ann_min_temp(),
             max_temp: self.ann_max_temp(),
             energy: self.ann_energy(),
+            objectives: self.ann_objectives(),
             cooling: self.ann_cooling(),
+            neighborhood: self.ann_neighborhood(),
+            neighborhood_steps: self.ann_neighborhood_steps(),
+            neighborhood_width: self.ann_neighborhood_width(),
+            vary_ratio: self.ann_vary_ratio(),
+            vary_decrease: self.ann_vary_decrease(),
             version: self.ann_version(),
+            population_size: self.ann_population_size(),
+            crossover_rate: self.ann_crossover_rate(),
+            mutation_rate: self.ann_mutation_rate(),
+            swap_period: self.ann_swap_period(),
+            exchange: self.ann_exchange(),
+            initial_samples: self.ann_initial_samples(),
+            min_fidelity: self.ann_min_fidelity(),
+            eta: self.ann_eta(),
+            stagnation_policy: self.ann_stagnation_policy(),
+            max_rejected: self.ann_max_rejected(),
+            reheat_ratio: self.ann_reheat_ratio(),
+            acceptance: self.ann_acceptance(),
+            confidence: self.ann_confidence(),
+            refine_steps: self.ann_refine_steps(),
+            validation_top: self.ann_validation_top(),
+            validation_runs: self.ann_validation_runs(),
+            checkpoint: self.ann_checkpoint(),
+            checkpoint_period: self.ann_checkpoint_period(),
+            resume: self.ann_resume(),
+            eval_cache: self.ann_eval_cache(),
+            cache_policy: self.ann_cache_policy(),
+            seed: self.ann_seed(),
         };
     }
 
@@ -229,6 +459,36 @@ impl XMLReader {
             .parse()
             .unwrap();
     }
+    fn ann_max_time(&self) -> Option<usize> {
+        match self.hm_tuner.get("max_time") {
+            Some(val) => return Some(val.to_string().parse::<usize>().unwrap()),
+            None => return None,
+        };
+    }
+    fn ann_repetitions(&self) -> RepetitionMode {
+        match self.hm_tuner.get("repetitions") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return RepetitionMode::fixed,
+        };
+    }
+    fn ann_max_iter(&self) -> u8 {
+        match self.hm_tuner.get("max_iter") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 10,
+        };
+    }
+    fn ann_ci_width(&self) -> f64 {
+        match self.hm_tuner.get("ci_width") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 0.05,
+        };
+    }
+    fn ann_evaluation(&self) -> EvaluationMode {
+        match self.hm_tuner.get("evaluation") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return EvaluationMode::full,
+        };
+    }
     fn ann_num_iter(&self) -> u8 {
         return self.hm_tuner
             .get("num_iter")
@@ -259,6 +519,17 @@ impl XMLReader {
             .unwrap();
         return energy_type;
     }
+    fn ann_objectives(&self) -> Vec<EnergyType> {
+        match self.hm_tuner.get("objectives") {
+            Some(val) => {
+                return val.to_string()
+                    .split(',')
+                    .map(|obj| obj.trim().parse().unwrap())
+                    .collect()
+            }
+            None => return vec![self.ann_energy()],
+        };
+    }
     fn ann_cooling(&self) -> CoolingSchedule {
         let cooling_schedule: CoolingSchedule = self.hm_tuner
             .get("cooling")
@@ -278,4 +549,198 @@ impl XMLReader {
             .unwrap();
         return solver_version;
     }
+
+    fn ann_population_size(&self) -> usize {
+        match self.hm_tuner.get("population_size") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 32,
+        };
+    }
+    fn ann_crossover_rate(&self) -> f64 {
+        match self.hm_tuner.get("crossover_rate") {
+            Some(val) => return val.to_string().parse::<f64>().unwrap(),
+            None => return 1.0,
+        };
+    }
+    fn ann_mutation_rate(&self) -> f64 {
+        match self.hm_tuner.get("mutation_rate") {
+            Some(val) => return val.to_string().parse::<f64>().unwrap(),
+            None => return 1.0,
+        };
+    }
+    fn ann_swap_period(&self) -> usize {
+        match self.hm_tuner.get("swap_period") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 10,
+        };
+    }
+    fn ann_neighborhood(&self) -> NeighborhoodOperator {
+        match self.hm_tuner.get("neighborhood") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return NeighborhoodOperator::uniform,
+        };
+    }
+    fn ann_neighborhood_steps(&self) -> usize {
+        match self.hm_tuner.get("neighborhood_steps") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 1,
+        };
+    }
+    fn ann_neighborhood_width(&self) -> f64 {
+        match self.hm_tuner.get("neighborhood_width") {
+            Some(val) => return val.to_string().parse::<f64>().unwrap(),
+            None => return 0.25,
+        };
+    }
+    fn ann_vary_ratio(&self) -> f64 {
+        match self.hm_tuner.get("vary_ratio") {
+            Some(val) => return val.to_string().parse::<f64>().unwrap(),
+            None => return 0.6,
+        };
+    }
+    fn ann_vary_decrease(&self) -> f64 {
+        match self.hm_tuner.get("vary_decrease") {
+            Some(val) => return val.to_string().parse::<f64>().unwrap(),
+            None => return 0.1,
+        };
+    }
+    fn ann_exchange(&self) -> ExchangeMode {
+        match self.hm_tuner.get("exchange") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return ExchangeMode::sync,
+        };
+    }
+    fn ann_initial_samples(&self) -> usize {
+        match self.hm_tuner.get("initial_samples") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 10,
+        };
+    }
+    fn ann_min_fidelity(&self) -> f64 {
+        match self.hm_tuner.get("min_fidelity") {
+            Some(val) => return val.to_string().parse::<f64>().unwrap(),
+            None => return 0.11,
+        };
+    }
+    fn ann_eta(&self) -> usize {
+        match self.hm_tuner.get("eta") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 3,
+        };
+    }
+
+    fn ann_stagnation_policy(&self) -> StagnationPolicy {
+        match self.hm_tuner.get("stagnation") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return StagnationPolicy::stop,
+        };
+    }
+    fn ann_max_rejected(&self) -> Option<usize> {
+        match self.hm_tuner.get("max_rejected") {
+            Some(val) => return Some(val.to_string().parse::<usize>().unwrap()),
+            None => return None,
+        };
+    }
+    fn ann_reheat_ratio(&self) -> f64 {
+        match self.hm_tuner.get("reheat_ratio") {
+            Some(val) => return val.to_string().parse::<f64>().unwrap(),
+            None => return 0.5,
+        };
+    }
+
+    fn ann_acceptance(&self) -> AcceptanceTest {
+        match self.hm_tuner.get("acceptance") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return AcceptanceTest::mean,
+        };
+    }
+    fn ann_confidence(&self) -> f64 {
+        match self.hm_tuner.get("confidence") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 0.95,
+        };
+    }
+    fn ann_refine_steps(&self) -> usize {
+        match self.hm_tuner.get("refine_steps") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 0,
+        };
+    }
+    fn ann_validation_top(&self) -> usize {
+        match self.hm_tuner.get("validation_top") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 0,
+        };
+    }
+    fn ann_validation_runs(&self) -> usize {
+        match self.hm_tuner.get("validation_runs") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 10,
+        };
+    }
+    fn ann_checkpoint(&self) -> Option<String> {
+        match self.hm_tuner.get("checkpoint") {
+            Some(val) => return Some(val.to_string()),
+            None => return None,
+        };
+    }
+    fn ann_checkpoint_period(&self) -> usize {
+        match self.hm_tuner.get("checkpoint_period") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return 10,
+        };
+    }
+    fn ann_resume(&self) -> bool {
+        match self.hm_tuner.get("resume") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return false,
+        };
+    }
+
+    fn ann_eval_cache(&self) -> Option<String> {
+        match self.hm_tuner.get("eval_cache") {
+            Some(val) => return Some(val.to_string()),
+            None => return None,
+        };
+    }
+    fn ann_cache_policy(&self) -> CachePolicy {
+        match self.hm_tuner.get("cache_policy") {
+            Some(val) => return val.to_string().parse().unwrap(),
+            None => return CachePolicy::reuse,
+        };
+    }
+    fn ann_seed(&self) -> Option<u64> {
+        match self.hm_tuner.get("seed") {
+            Some(val) => return Some(val.to_string().parse::<u64>().unwrap()),
+            None => return None,
+        };
+    }
+}
+
+
+/// Scale of a numeric parameter: a <step> makes its values equally spaced, a <percent> makes each
+/// value percent% larger than the previous one
+fn parse_scale(hm_param: &HashMap<String, String>) -> Scale {
+    let name = hm_param.get("name").unwrap();
+    match (hm_param.get("step"), hm_param.get("percent")) {
+        (Some(step), None) => {
+            let step = step.trim().parse::<f64>().unwrap();
+            if step <= 0.0 {
+                panic!("The step of the parameter {} must be positive", name);
+            }
+            Scale::Step(step)
+        }
+        (None, Some(percent)) => {
+            let percent = percent.trim().parse::<f64>().unwrap();
+            if percent <= 0.0 {
+                panic!("The percent of the parameter {} must be positive", name);
+            }
+            let min = hm_param.get("minimum").unwrap().trim().parse::<f64>().unwrap();
+            if min <= 0.0 {
+                panic!("The minimum of the log-scale parameter {} must be positive", name);
+            }
+            Scale::Percent(percent)
+        }
+        _ => panic!("The parameter {} needs either a <step> or a <percent>", name),
+    }
 }
@@ -4,7 +4,12 @@ use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::io::{BufWriter, BufReader, BufRead};
 use std::collections::HashMap;
+use std::path::Path;
 
+use annealing::solver::common::ParetoPoint;
+use annealing::validate::ValidationResult;
+use energy_eval;
+use EnergyType;
 use State;
 
 #[derive(Debug, Clone)]
@@ -14,36 +19,89 @@ pub struct CSVEmitter {
 
 
 impl CSVEmitter {
-    pub fn new(num_targets: usize, params_name: Vec<String>) -> Self {
+    pub fn new(num_targets: usize, params_name: Vec<String>, resume: bool) -> Self {
         let mut temp_vec: Vec<String> = Vec::new();
 
         for i in 0..num_targets {
+            // Create a path to the params file
+            for param_name in params_name.iter() {
+                temp_vec.push(param_name.to_string());
+            }
+
+            // When a tuning run is resumed, the results logged before the interruption are kept
             let filename = format!("{}{}{}", "results-", i, ".csv");
+            if !(resume && Path::new(&filename).exists()) {
+                let f = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(filename);
+
+                let mut writer = BufWriter::new(f.unwrap());
+                let mut wtr = csv::Writer::from_buffer(writer);
+
+                let mut vec_2_write: Vec<String> = Vec::new();
+                vec_2_write.push("time_s".to_string());
+                vec_2_write.push("cputime_s".to_string());
+                vec_2_write.push("temperature".to_string());
+                vec_2_write.push("best_nrg".to_string());
+                for param_name in temp_vec.clone().iter().cloned() {
+                    vec_2_write.push("best_".to_string() + &*param_name);
+                }
+
+                vec_2_write.push("last_nrg".to_string());
+                vec_2_write.push("last_reps".to_string());
+                for param_name in temp_vec.clone().iter().cloned() {
+                    vec_2_write.push("last_".to_string() + &*param_name);
+                }
+
+                let res = wtr.encode(vec_2_write);
+                assert!(res.is_ok());
+
+                wtr.flush();
+            }
+
+            // File of the events occurred during the annealing
+            let events_filename = format!("{}{}{}", "events-", i, ".csv");
+            if !(resume && Path::new(&events_filename).exists()) {
+                let f = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(events_filename);
+
+                let mut writer = BufWriter::new(f.unwrap());
+                let mut wtr = csv::Writer::from_buffer(writer);
+
+                let res = wtr.encode(vec!["time_s", "step", "event", "temperature"]);
+                assert!(res.is_ok());
+
+                wtr.flush();
+            }
+        }
+
+        // File of the refinement phase that follows the search
+        let refinement_filename = "refinement.csv";
+        if !(resume && Path::new(refinement_filename).exists()) {
             let f = OpenOptions::new()
                 .write(true)
                 .create(true)
                 .truncate(true)
-                .open(filename);
+                .open(refinement_filename);
 
             let mut writer = BufWriter::new(f.unwrap());
             let mut wtr = csv::Writer::from_buffer(writer);
 
-            // Create a path to the params file
-            for param_name in params_name.iter() {
-                temp_vec.push(param_name.to_string());
-            }
-
             let mut vec_2_write: Vec<String> = Vec::new();
             vec_2_write.push("time_s".to_string());
-            vec_2_write.push("cputime_s".to_string());
-            vec_2_write.push("temperature".to_string());
+            vec_2_write.push("step".to_string());
             vec_2_write.push("best_nrg".to_string());
-            for param_name in temp_vec.clone().iter().cloned() {
+            for param_name in params_name.iter() {
                 vec_2_write.push("best_".to_string() + &*param_name);
             }
-
             vec_2_write.push("last_nrg".to_string());
-            for param_name in temp_vec.clone().iter().cloned() {
+            vec_2_write.push("last_reps".to_string());
+            for param_name in params_name.iter() {
                 vec_2_write.push("last_".to_string() + &*param_name);
             }
 
@@ -64,6 +122,7 @@ impl CSVEmitter {
         cputime: f64,
         measured_val: f64,
         measured_state: &State,
+        measured_reps: usize,
         best_val: f64,
         best_state: &State,
         num_iter: usize,
@@ -71,7 +130,7 @@ impl CSVEmitter {
     ) {
 
         let filename = format!("{}{}{}", "results-", tid, ".csv");
-        let f = OpenOptions::new().write(true).open(filename);
+        let f = OpenOptions::new().append(true).open(filename);
 
         let mut writer = BufWriter::new(f.unwrap());
         let mut wtr = csv::Writer::from_buffer(writer);
@@ -90,6 +149,7 @@ impl CSVEmitter {
 
 
         vec_2_write.push(measured_val.to_string());
+        vec_2_write.push(measured_reps.to_string());
 
         for param_name in self.ordered_params.clone().iter().cloned() {
             vec_2_write.push((measured_state.get(&param_name).unwrap()).to_string());
@@ -100,4 +160,168 @@ impl CSVEmitter {
 
         wtr.flush();
     }
+
+    pub fn send_event(
+        &mut self,
+        event: &str,
+        temperature: f64,
+        time: f64,
+        num_iter: usize,
+        tid: usize,
+    ) {
+        let filename = format!("{}{}{}", "events-", tid, ".csv");
+        let f = OpenOptions::new().append(true).open(filename);
+
+        let mut writer = BufWriter::new(f.unwrap());
+        let mut wtr = csv::Writer::from_buffer(writer);
+
+        let mut vec_2_write: Vec<String> = Vec::new();
+        vec_2_write.push(time.to_string());
+        vec_2_write.push(num_iter.to_string());
+        vec_2_write.push(event.to_string());
+        vec_2_write.push(temperature.to_string());
+
+        let result = wtr.encode(vec_2_write);
+        assert!(result.is_ok());
+
+        wtr.flush();
+    }
+
+    /// Name of each parameter, once (the ordered parameters are repeated for each target)
+    fn params_name(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for param_name in self.ordered_params.iter() {
+            if !names.contains(param_name) {
+                names.push(param_name.clone());
+            }
+        }
+        names
+    }
+
+    pub fn send_refinement(
+        &mut self,
+        time: f64,
+        measured_val: f64,
+        measured_state: &State,
+        measured_reps: usize,
+        best_val: f64,
+        best_state: &State,
+        num_iter: usize,
+    ) {
+        let f = OpenOptions::new().append(true).open("refinement.csv");
+
+        let mut writer = BufWriter::new(f.unwrap());
+        let mut wtr = csv::Writer::from_buffer(writer);
+
+        let mut vec_2_write: Vec<String> = Vec::new();
+        vec_2_write.push(time.to_string());
+        vec_2_write.push(num_iter.to_string());
+
+        vec_2_write.push(best_val.to_string());
+        for param_name in self.params_name().iter() {
+            vec_2_write.push((best_state.get(param_name).unwrap()).to_string());
+        }
+
+        vec_2_write.push(measured_val.to_string());
+        vec_2_write.push(measured_reps.to_string());
+        for param_name in self.params_name().iter() {
+            vec_2_write.push((measured_state.get(param_name).unwrap()).to_string());
+        }
+
+        let result = wtr.encode(vec_2_write);
+        assert!(result.is_ok());
+
+        wtr.flush();
+    }
+
+    /// The file of the Pareto front is rewritten at each update, so that it always contains
+    /// the current front
+    pub fn send_front(
+        &mut self,
+        front: &Vec<ParetoPoint>,
+        objectives: &Vec<EnergyType>,
+        time: f64,
+        num_iter: usize,
+    ) {
+        let f = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open("pareto-front.csv");
+
+        let mut writer = BufWriter::new(f.unwrap());
+        let mut wtr = csv::Writer::from_buffer(writer);
+
+        let mut vec_2_write: Vec<String> = Vec::new();
+        vec_2_write.push("time_s".to_string());
+        vec_2_write.push("step".to_string());
+        for objective in objectives.iter() {
+            vec_2_write.push(energy_eval::metric_name(objective));
+        }
+        for param_name in self.ordered_params.clone().iter().cloned() {
+            vec_2_write.push(param_name);
+        }
+        let res = wtr.encode(vec_2_write);
+        assert!(res.is_ok());
+
+        for point in front.iter() {
+            let mut vec_2_write: Vec<String> = Vec::new();
+            vec_2_write.push(time.to_string());
+            vec_2_write.push(num_iter.to_string());
+            for val in point.objectives.iter() {
+                vec_2_write.push(val.to_string());
+            }
+            for param_name in self.ordered_params.clone().iter().cloned() {
+                vec_2_write.push((point.state.get(&param_name).unwrap()).to_string());
+            }
+
+            let result = wtr.encode(vec_2_write);
+            assert!(result.is_ok());
+        }
+
+        wtr.flush();
+    }
+
+    /// The validation report is written at the end of the tuning run, in the validation.csv file
+    pub fn send_validation(&mut self, report: &Vec<ValidationResult>) {
+        let f = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open("validation.csv");
+
+        let mut writer = BufWriter::new(f.unwrap());
+        let mut wtr = csv::Writer::from_buffer(writer);
+
+        let mut vec_2_write: Vec<String> = Vec::new();
+        vec_2_write.push("configuration".to_string());
+        vec_2_write.push("mean_nrg".to_string());
+        vec_2_write.push("ci_half_width".to_string());
+        vec_2_write.push("runs".to_string());
+        vec_2_write.push("speedup".to_string());
+        vec_2_write.push("significant".to_string());
+        for param_name in self.params_name().iter() {
+            vec_2_write.push(param_name.clone());
+        }
+        let res = wtr.encode(vec_2_write);
+        assert!(res.is_ok());
+
+        for res in report.iter() {
+            let mut vec_2_write: Vec<String> = Vec::new();
+            vec_2_write.push(res.label.clone());
+            vec_2_write.push(res.dist.mean.to_string());
+            vec_2_write.push(res.half_width.to_string());
+            vec_2_write.push(res.dist.n.to_string());
+            vec_2_write.push(res.speedup.to_string());
+            vec_2_write.push(res.significant.to_string());
+            for param_name in self.params_name().iter() {
+                vec_2_write.push((res.state.get(param_name).unwrap()).to_string());
+            }
+
+            let result = wtr.encode(vec_2_write);
+            assert!(result.is_ok());
+        }
+
+        wtr.flush();
+    }
 }
@@ -3,7 +3,11 @@ use influent::client::{Client, Credentials};
 use influent::measurement::{Measurement, Value};
 
 use std::time::{SystemTime, UNIX_EPOCH};
-use State;
+use annealing::solver::common::ParetoPoint;
+use annealing::validate::ValidationResult;
+use energy_eval;
+use EnergyType;
+use {State, ParamValue};
 
 #[derive(Debug, Clone)]
 pub struct InfluxEmitter {
@@ -31,6 +35,7 @@ impl InfluxEmitter {
         cputime: f64,
         measured_val: f64,
         measured_state: &State,
+        measured_reps: usize,
         best_val: f64,
         best_state: &State,
         num_iter: usize,
@@ -55,6 +60,7 @@ impl InfluxEmitter {
         measurement_metrics.add_field("Step", Value::Integer(num_iter as i64));
         measurement_metrics.add_field("Temperature", Value::Float(temperature));
         measurement_metrics.add_field("Measured NRG", Value::Float(measured_val));
+        measurement_metrics.add_field("Repetitions", Value::Integer(measured_reps as i64));
         measurement_metrics.add_field("Best NRG", Value::Float(best_val));
 
         client.write_one(measurement_metrics, None);
@@ -62,7 +68,7 @@ impl InfluxEmitter {
         let mut measurement_current_states = Measurement::new("Tuner Measured States");
         measurement_current_states.set_timestamp(timestamp);
         for (param, value) in measured_state.iter() {
-            measurement_current_states.add_field(param, Value::String(value));
+            measurement_current_states.add_field(param, param_field(value));
         }
 
         client.write_one(measurement_current_states, None);
@@ -70,11 +76,179 @@ impl InfluxEmitter {
         let mut measurement_best_states = Measurement::new("Tuner Best States");
         measurement_best_states.set_timestamp(timestamp);
         for (param, value) in best_state.iter() {
-            measurement_best_states.add_field(param, Value::String(value));
+            measurement_best_states.add_field(param, param_field(value));
         }
 
         client.write_one(measurement_best_states, None);
 
 
     }
+
+    pub fn send_event(
+        &mut self,
+        event: &str,
+        temperature: f64,
+        time: f64,
+        num_iter: usize,
+        tid: usize,
+    ) {
+        let credentials = Credentials {
+            username: self.username.as_str(),
+            password: self.password.as_str(),
+            database: self.database.as_str(),
+        };
+
+        let start = SystemTime::now();
+        let since_the_epoch = start.duration_since(UNIX_EPOCH).expect(
+            "Time went backwards",
+        );
+        let timestamp: i64 = (since_the_epoch.as_secs() as i64) * 1000000000i64;
+
+        let client = create_client(credentials, vec![self.address.as_str()]);
+        let mut measurement_events = Measurement::new("Tuner Events");
+        measurement_events.set_timestamp(timestamp);
+        measurement_events.add_field("Event", Value::String(event));
+        measurement_events.add_field("Step", Value::Integer(num_iter as i64));
+        measurement_events.add_field("Thread", Value::Integer(tid as i64));
+        measurement_events.add_field("Temperature", Value::Float(temperature));
+
+        client.write_one(measurement_events, None);
+    }
+
+    pub fn send_front(
+        &mut self,
+        front: &Vec<ParetoPoint>,
+        objectives: &Vec<EnergyType>,
+        time: f64,
+        num_iter: usize,
+    ) {
+        let credentials = Credentials {
+            username: self.username.as_str(),
+            password: self.password.as_str(),
+            database: self.database.as_str(),
+        };
+
+        let start = SystemTime::now();
+        let since_the_epoch = start.duration_since(UNIX_EPOCH).expect(
+            "Time went backwards",
+        );
+        let timestamp: i64 = (since_the_epoch.as_secs() as i64) * 1000000000i64;
+
+        let client = create_client(credentials, vec![self.address.as_str()]);
+        let objectives_name: Vec<String> = objectives
+            .iter()
+            .map(|obj| energy_eval::metric_name(obj))
+            .collect();
+        let points_id: Vec<String> = (0..front.len()).map(|i| i.to_string()).collect();
+
+        // Each point of the front is tagged with its position, so that the points written
+        // with the same timestamp do not overwrite each other
+        for (point, point_id) in front.iter().zip(points_id.iter()) {
+            let mut measurement_front = Measurement::new("Tuner Pareto Front");
+            measurement_front.set_timestamp(timestamp);
+            measurement_front.add_tag("Point", point_id);
+            measurement_front.add_field("Step", Value::Integer(num_iter as i64));
+            for (name, val) in objectives_name.iter().zip(point.objectives.iter()) {
+                measurement_front.add_field(name, Value::Float(*val));
+            }
+            for (param, value) in point.state.iter() {
+                measurement_front.add_field(param, param_field(value));
+            }
+
+            client.write_one(measurement_front, None);
+        }
+    }
+
+    pub fn send_refinement(
+        &mut self,
+        time: f64,
+        measured_val: f64,
+        measured_state: &State,
+        measured_reps: usize,
+        best_val: f64,
+        best_state: &State,
+        num_iter: usize,
+    ) {
+        let credentials = Credentials {
+            username: self.username.as_str(),
+            password: self.password.as_str(),
+            database: self.database.as_str(),
+        };
+
+        let start = SystemTime::now();
+        let since_the_epoch = start.duration_since(UNIX_EPOCH).expect(
+            "Time went backwards",
+        );
+        let timestamp: i64 = (since_the_epoch.as_secs() as i64) * 1000000000i64;
+
+        let client = create_client(credentials, vec![self.address.as_str()]);
+        let mut measurement_metrics = Measurement::new("Tuner Refinement");
+        measurement_metrics.set_timestamp(timestamp);
+        measurement_metrics.add_field("Step", Value::Integer(num_iter as i64));
+        measurement_metrics.add_field("Measured NRG", Value::Float(measured_val));
+        measurement_metrics.add_field("Repetitions", Value::Integer(measured_reps as i64));
+        measurement_metrics.add_field("Best NRG", Value::Float(best_val));
+
+        client.write_one(measurement_metrics, None);
+
+        let mut measurement_current_states = Measurement::new("Tuner Refinement Measured States");
+        measurement_current_states.set_timestamp(timestamp);
+        for (param, value) in measured_state.iter() {
+            measurement_current_states.add_field(param, param_field(value));
+        }
+
+        client.write_one(measurement_current_states, None);
+
+        let mut measurement_best_states = Measurement::new("Tuner Refinement Best States");
+        measurement_best_states.set_timestamp(timestamp);
+        for (param, value) in best_state.iter() {
+            measurement_best_states.add_field(param, param_field(value));
+        }
+
+        client.write_one(measurement_best_states, None);
+    }
+
+    pub fn send_validation(&mut self, report: &Vec<ValidationResult>) {
+        let credentials = Credentials {
+            username: self.username.as_str(),
+            password: self.password.as_str(),
+            database: self.database.as_str(),
+        };
+
+        let start = SystemTime::now();
+        let since_the_epoch = start.duration_since(UNIX_EPOCH).expect(
+            "Time went backwards",
+        );
+        let timestamp: i64 = (since_the_epoch.as_secs() as i64) * 1000000000i64;
+
+        let client = create_client(credentials, vec![self.address.as_str()]);
+
+        // Each configuration is tagged with its label, so that the configurations written with
+        // the same timestamp do not overwrite each other
+        for res in report.iter() {
+            let mut measurement_validation = Measurement::new("Tuner Validation");
+            measurement_validation.set_timestamp(timestamp);
+            measurement_validation.add_tag("Configuration", res.label.as_str());
+            measurement_validation.add_field("Mean NRG", Value::Float(res.dist.mean));
+            measurement_validation.add_field("CI Half Width", Value::Float(res.half_width));
+            measurement_validation.add_field("Runs", Value::Integer(res.dist.n as i64));
+            measurement_validation.add_field("Speedup", Value::Float(res.speedup));
+            measurement_validation.add_field("Significant", Value::Boolean(res.significant));
+            for (param, value) in res.state.iter() {
+                measurement_validation.add_field(param, param_field(value));
+            }
+
+            client.write_one(measurement_validation, None);
+        }
+    }
+}
+
+
+/// Field of the value of a parameter, of the type of the parameter
+fn param_field(value: &ParamValue) -> Value {
+    match *value {
+        ParamValue::Int(val) => Value::Integer(val as i64),
+        ParamValue::Float(val) => Value::Float(val),
+        ParamValue::Text(ref val) => Value::String(val),
+    }
 }
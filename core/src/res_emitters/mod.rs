@@ -1,6 +1,9 @@
 pub mod csv_emitter;
 pub mod influx_emitter;
 
+use annealing::solver::common::ParetoPoint;
+use annealing::validate::ValidationResult;
+use EnergyType;
 use State;
 
 #[derive(Debug, Clone)]
@@ -18,6 +21,7 @@ impl Emitter {
         cputime: f64,
         measured_val: f64,
         measured_state: &State,
+        measured_reps: usize,
         best_val: f64,
         best_state: &State,
         num_iter: usize,
@@ -30,6 +34,7 @@ impl Emitter {
             cputime,
             measured_val,
             measured_state,
+            measured_reps,
             best_val,
             best_state,
             num_iter,
@@ -41,6 +46,7 @@ impl Emitter {
             cputime,
             measured_val,
             measured_state,
+            measured_reps,
             best_val,
             best_state,
             num_iter,
@@ -49,4 +55,88 @@ impl Emitter {
 
 
     }
+
+    /// Report an event occurred during the annealing (e.g. a reheat of the temperature)
+    pub fn send_event(
+        &mut self,
+        event: &str,
+        temperature: f64,
+        time: f64,
+        num_iter: usize,
+        tid: usize,
+    ) {
+        self.influx_res_emitter.send_event(
+            event,
+            temperature,
+            time,
+            num_iter,
+            tid,
+        );
+        self.csv_res_emitter.send_event(
+            event,
+            temperature,
+            time,
+            num_iter,
+            tid,
+        );
+    }
+
+    /// Report the current Pareto front of the multi-objective solvers
+    pub fn send_front(
+        &mut self,
+        front: &Vec<ParetoPoint>,
+        objectives: &Vec<EnergyType>,
+        time: f64,
+        num_iter: usize,
+    ) {
+        self.influx_res_emitter.send_front(
+            front,
+            objectives,
+            time,
+            num_iter,
+        );
+        self.csv_res_emitter.send_front(
+            front,
+            objectives,
+            time,
+            num_iter,
+        );
+    }
+
+    /// Report a step of the refinement phase that follows the search
+    pub fn send_refinement(
+        &mut self,
+        time: f64,
+        measured_val: f64,
+        measured_state: &State,
+        measured_reps: usize,
+        best_val: f64,
+        best_state: &State,
+        num_iter: usize,
+    ) {
+        self.influx_res_emitter.send_refinement(
+            time,
+            measured_val,
+            measured_state,
+            measured_reps,
+            best_val,
+            best_state,
+            num_iter,
+        );
+        self.csv_res_emitter.send_refinement(
+            time,
+            measured_val,
+            measured_state,
+            measured_reps,
+            best_val,
+            best_state,
+            num_iter,
+        );
+    }
+
+    /// Report the outcome of the validation of the best configurations
+    pub fn send_validation(&mut self, report: &Vec<ValidationResult>) {
+        self.influx_res_emitter.send_validation(report);
+        self.csv_res_emitter.send_validation(report);
+    }
 }
@@ -1,17 +1,52 @@
-use {EnergyType, CoolingSchedule, SolverVersion, ParameterLevel};
+use {EnergyType, CoolingSchedule, NeighborhoodOperator, SolverVersion, ParameterLevel, StagnationPolicy, CachePolicy,
+     ExchangeMode, AcceptanceTest, RepetitionMode, EvaluationMode};
 use std::sync::{Arc, Mutex};
+use rand::{Rng, SeedableRng, StdRng};
 
 
 
 #[derive(Debug, Clone)]
 pub struct TunerParameter {
     pub max_step: usize,
+    pub max_time: Option<usize>,
     pub num_iter: u8,
+    pub repetitions: RepetitionMode,
+    pub max_iter: u8,
+    pub ci_width: f64,
+    pub evaluation: EvaluationMode,
     pub min_temp: Option<f64>,
     pub max_temp: Option<f64>,
     pub energy: EnergyType,
+    pub objectives: Vec<EnergyType>,
     pub cooling: CoolingSchedule,
+    pub neighborhood: NeighborhoodOperator,
+    pub neighborhood_steps: usize,
+    pub neighborhood_width: f64,
+    pub vary_ratio: f64,
+    pub vary_decrease: f64,
     pub version: SolverVersion,
+    pub population_size: usize,
+    pub crossover_rate: f64,
+    pub mutation_rate: f64,
+    pub swap_period: usize,
+    pub exchange: ExchangeMode,
+    pub initial_samples: usize,
+    pub min_fidelity: f64,
+    pub eta: usize,
+    pub stagnation_policy: StagnationPolicy,
+    pub max_rejected: Option<usize>,
+    pub reheat_ratio: f64,
+    pub acceptance: AcceptanceTest,
+    pub confidence: f64,
+    pub refine_steps: usize,
+    pub validation_top: usize,
+    pub validation_runs: usize,
+    pub checkpoint: Option<String>,
+    pub checkpoint_period: usize,
+    pub resume: bool,
+    pub eval_cache: Option<String>,
+    pub cache_policy: CachePolicy,
+    pub seed: Option<u64>,
 }
 
 #[derive(Debug, Clone)]
@@ -21,16 +56,34 @@ pub struct ScriptInfo {
     pub envfile: String,
 }
 
+/// Spacing of the values of a numeric parameter between its minimum and maximum: a constant
+/// step, or a constant ratio of (100 + percent)% between two consecutive values (log scale)
+#[derive(Debug, Clone, Copy)]
+pub enum Scale {
+    Step(f64),
+    Percent(f64),
+}
+
 #[derive(Debug, Clone)]
 pub struct IntParameter {
     pub name: String,
     pub min: usize,
     pub max: usize,
-    pub step: usize,
+    pub scale: Scale,
     pub default: usize,
     pub level: ParameterLevel,
 }
 
+#[derive(Debug, Clone)]
+pub struct FloatParameter {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub scale: Scale,
+    pub default: f64,
+    pub level: ParameterLevel,
+}
+
 #[derive(Debug, Clone)]
 pub struct BoolParameter {
     pub name: String,
@@ -40,6 +93,14 @@ pub struct BoolParameter {
     pub level: ParameterLevel,
 }
 
+#[derive(Debug, Clone)]
+pub struct EnumParameter {
+    pub name: String,
+    pub values: Vec<String>,
+    pub default: String,
+    pub level: ParameterLevel,
+}
+
 
 #[derive(Debug, Clone)]
 pub struct ProcessPool(Arc<Mutex<Vec<String>>>);
@@ -64,3 +125,69 @@ impl ProcessPool {
         (*collection).len()
     }
 }
+
+
+/// Random number generator of the tuner. It counts its draws, so that a resumed run can move
+/// the stream to the position reached by the interrupted one (see restore).
+#[derive(Debug, Clone)]
+pub struct TunerRng {
+    rng: StdRng,
+    seed: Option<u64>,
+    stream: Vec<usize>,
+    draws: u64,
+}
+
+
+/// Random number generator of the tuner. With a seed, the generator is derived from the seed and
+/// from the stream given in input (e.g. the number of the worker), so that a run can be
+/// reproduced. Without a seed, it is initialized by the OS.
+pub fn new_rng(seed: Option<u64>, stream: &[usize]) -> TunerRng {
+    let rng = match seed {
+        Some(s) => {
+            let mut key = vec![s as usize];
+            key.extend_from_slice(stream);
+            StdRng::from_seed(key.as_slice())
+        }
+        None => StdRng::new().unwrap(),
+    };
+
+    TunerRng {
+        rng: rng,
+        seed: seed,
+        stream: stream.to_vec(),
+        draws: 0,
+    }
+}
+
+
+impl TunerRng {
+    /// Number of draws since the generator has been created
+    pub fn draws(&self) -> u64 {
+        self.draws
+    }
+
+    /// Restart the stream and skip the given number of draws. Without a seed the stream cannot
+    /// be reproduced, hence it is left as it is.
+    pub fn restore(&mut self, draws: u64) {
+        if self.seed.is_none() {
+            return;
+        }
+        let stream = self.stream.clone();
+        *self = new_rng(self.seed, &stream);
+        for _ in 0..draws {
+            self.next_u64();
+        }
+    }
+}
+
+impl Rng for TunerRng {
+    /// As for the StdRng, a u32 is the lower half of a u64, hence each draw takes a u64
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draws += 1;
+        self.rng.next_u64()
+    }
+}
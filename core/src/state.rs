@@ -0,0 +1,302 @@
+/// ///////////////////////////////////////////////////////////////////////////
+///  File: state.rs
+/// ///////////////////////////////////////////////////////////////////////////
+///  Copyright 2017 Giovanni Mazzeo
+///
+///  Licensed under the Apache License, Version 2.0 (the "License");
+///  you may not use this file except in compliance with the License.
+///  You may obtain a copy of the License at
+///
+///      http://www.apache.org/licenses/LICENSE-2.0
+///
+///  Unless required by applicable law or agreed to in writing, software
+///  distributed under the License is distributed on an "AS IS" BASIS,
+///  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+///  See the License for the specific language governing permissions and
+///  limitations under the License.
+/// ///////////////////////////////////////////////////////////////////////////
+
+/// ****************************************************************************
+/// *****************************************************************************
+/// **
+/// Configuration of the Target Parameters
+/// *
+/// *****************************************************************************
+/// ****************************************************************************
+use rustc_serialize::{Encodable, Encoder, Decodable, Decoder};
+use std::collections::BTreeMap;
+use std::collections::btree_map;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+
+
+/// Typed value of a parameter: int parameters hold an Int, float parameters a Float, bool and
+/// enum parameters the Text of their current choice
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub enum ParamValue {
+    Int(usize),
+    Float(f64),
+    Text(String),
+}
+
+impl ParamValue {
+    /// Float value rounded to 6 decimal digits, so that the rounding errors of the arithmetic
+    /// (e.g. 0.30000000000000004) do not make two equal values differ
+    pub fn float(val: f64) -> ParamValue {
+        ParamValue::Float((val * 1e6).round() / 1e6)
+    }
+
+    /// Numeric value of the parameter. A Text is numeric if it can be parsed as a number (e.g.
+    /// an enum value such as 4).
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            ParamValue::Int(val) => Some(val as f64),
+            ParamValue::Float(val) => Some(val),
+            ParamValue::Text(ref val) => val.trim().parse::<f64>().ok(),
+        }
+    }
+}
+
+/// Floats are compared by their bit pattern, so that the equality is an equivalence relation
+/// consistent with the hash
+impl PartialEq for ParamValue {
+    fn eq(&self, other: &ParamValue) -> bool {
+        match (self, other) {
+            (&ParamValue::Int(a), &ParamValue::Int(b)) => a == b,
+            (&ParamValue::Float(a), &ParamValue::Float(b)) => a.to_bits() == b.to_bits(),
+            (&ParamValue::Text(ref a), &ParamValue::Text(ref b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ParamValue {}
+
+impl Hash for ParamValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            ParamValue::Int(val) => {
+                0u8.hash(state);
+                val.hash(state);
+            }
+            ParamValue::Float(val) => {
+                1u8.hash(state);
+                val.to_bits().hash(state);
+            }
+            ParamValue::Text(ref val) => {
+                2u8.hash(state);
+                val.hash(state);
+            }
+        }
+    }
+}
+
+/// Textual form of the value, the one sent to the target agent
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParamValue::Int(val) => write!(f, "{}", val),
+            ParamValue::Float(val) => {
+                let text = format!("{:.6}", val);
+                write!(f, "{}", text.trim_right_matches('0').trim_right_matches('.'))
+            }
+            ParamValue::Text(ref val) => write!(f, "{}", val),
+        }
+    }
+}
+
+impl fmt::Debug for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+
+/// Configuration of the target: the value of each parameter, identified by its name. The
+/// parameters are kept ordered by name, hence the iteration order, the equality, the hash and
+/// the serialization of a configuration do not depend on the order in which its parameters
+/// have been inserted.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct State {
+    params: BTreeMap<String, ParamValue>,
+}
+
+impl State {
+    pub fn new() -> State {
+        State { params: BTreeMap::new() }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ParamValue> {
+        self.params.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut ParamValue> {
+        self.params.get_mut(name)
+    }
+
+    pub fn insert(&mut self, name: String, value: ParamValue) -> Option<ParamValue> {
+        self.params.insert(name, value)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.params.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Parameters in alphabetical order
+    pub fn iter(&self) -> btree_map::Iter<String, ParamValue> {
+        self.params.iter()
+    }
+
+    pub fn keys(&self) -> btree_map::Keys<String, ParamValue> {
+        self.params.keys()
+    }
+
+    /// Encoding of the configuration as a sequence of name=value pairs, e.g. in the messages
+    /// sent to the target agent
+    pub fn encode_params(&self) -> String {
+        let mut encoded = String::new();
+        for (name, value) in self.params.iter() {
+            encoded += format!("{}={}|", name, value).as_str();
+        }
+        encoded
+    }
+}
+
+impl FromIterator<(String, ParamValue)> for State {
+    fn from_iter<I: IntoIterator<Item = (String, ParamValue)>>(iter: I) -> State {
+        State { params: iter.into_iter().collect() }
+    }
+}
+
+impl IntoIterator for State {
+    type Item = (String, ParamValue);
+    type IntoIter = btree_map::IntoIter<String, ParamValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.params.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a State {
+    type Item = (&'a String, &'a ParamValue);
+    type IntoIter = btree_map::Iter<'a, String, ParamValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.params.iter()
+    }
+}
+
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.params.iter()).finish()
+    }
+}
+
+/// A configuration is serialized as the map from the names of its parameters to their values
+impl Encodable for State {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        self.params.encode(s)
+    }
+}
+
+impl Decodable for State {
+    fn decode<D: Decoder>(d: &mut D) -> Result<State, D::Error> {
+        Ok(State { params: BTreeMap::decode(d)? })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn state(params: &[(&str, ParamValue)]) -> State {
+        params
+            .iter()
+            .map(|&(name, ref value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    /// Decode the name=value sequence as the target agent does, giving each value the type of the
+    /// corresponding one in the reference configuration
+    fn decode_params(encoded: &str, reference: &State) -> State {
+        encoded
+            .split('|')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let pair: Vec<&str> = p.splitn(2, '=').collect();
+                let value = match *reference.get(pair[0]).unwrap() {
+                    ParamValue::Int(_) => ParamValue::Int(pair[1].parse().unwrap()),
+                    ParamValue::Float(_) => ParamValue::float(pair[1].parse().unwrap()),
+                    ParamValue::Text(_) => ParamValue::Text(pair[1].to_string()),
+                };
+                (pair[0].to_string(), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn floats_are_rounded_to_six_digits() {
+        assert_eq!(ParamValue::float(0.1 + 0.2), ParamValue::float(0.3));
+        assert_eq!(ParamValue::float(1.0000004), ParamValue::float(1.0));
+        assert!(ParamValue::float(1.000001) != ParamValue::float(1.0));
+        assert_eq!(format!("{}", ParamValue::float(2.0)), "2");
+        assert_eq!(format!("{}", ParamValue::float(0.123456789)), "0.123457");
+    }
+
+    #[test]
+    fn values_of_different_types_differ() {
+        assert!(ParamValue::Int(1) != ParamValue::Float(1.0));
+        assert!(ParamValue::Int(1) != ParamValue::Text("1".to_string()));
+        assert_eq!(ParamValue::Text("4".to_string()).as_f64(), Some(4.0));
+        assert_eq!(ParamValue::Text("yes".to_string()).as_f64(), None);
+    }
+
+    #[test]
+    fn equal_states_have_the_same_hash() {
+        let a = state(&[
+            ("MUSL_ETHREADS", ParamValue::Int(2)),
+            ("ratio", ParamValue::float(0.1 + 0.2)),
+        ]);
+        let b = state(&[
+            ("ratio", ParamValue::float(0.3)),
+            ("MUSL_ETHREADS", ParamValue::Int(2)),
+        ]);
+        let c = state(&[
+            ("MUSL_ETHREADS", ParamValue::Int(3)),
+            ("ratio", ParamValue::float(0.3)),
+        ]);
+        assert_eq!(a, b);
+
+        let set: HashSet<State> = vec![a, b, c].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn encoded_params_are_sorted_by_name() {
+        let s = state(&[
+            ("b", ParamValue::float(0.25)),
+            ("c", ParamValue::Text("--with-x=y".to_string())),
+            ("a", ParamValue::Int(1)),
+        ]);
+        assert_eq!(s.encode_params(), "a=1|b=0.25|c=--with-x=y|");
+    }
+
+    #[test]
+    fn encoded_params_round_trip() {
+        let s = state(&[
+            ("MUSL_ETHREADS", ParamValue::Int(4)),
+            ("ratio", ParamValue::float(1.0 / 3.0)),
+            ("scale", ParamValue::float(2.0)),
+            ("sched", ParamValue::Text("fifo".to_string())),
+            ("flags", ParamValue::Text("--with-x=y".to_string())),
+        ]);
+        assert_eq!(decode_params(&s.encode_params(), &s), s);
+    }
+}
@@ -0,0 +1,398 @@
+use State;
+
+
+/// Declarative constraint on the parameters. It is either a comparison between two arithmetic
+/// expressions of the parameters (e.g. MUSL_ETHREADS <= MUSL_STHREADS or
+/// MUSL_SSPINS * 10 < MUSL_ESPINS) or an implication between two comparisons
+/// (e.g. http_ssl_module == 'on' => ssl_buffer_size >= 4000).
+#[derive(Debug, Clone)]
+pub enum Constraint {
+    Compare(Expr, CmpOp, Expr),
+    Implies(Box<Constraint>, Box<Constraint>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Num(f64),
+    Str(String),
+    Param(String),
+    BinOp(Box<Expr>, char, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+/// Value of an expression: the value of a parameter is a number when it is numeric (see
+/// ParamValue::as_f64)
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Num(f64),
+    Str(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Str(String),
+    Op(String),
+}
+
+
+impl Constraint {
+    pub fn parse(text: &str) -> Result<Constraint, String> {
+        let tokens = tokenize(text)?;
+        let mut pos = 0;
+
+        let antecedent = parse_comparison(&tokens, &mut pos)?;
+        let constraint = if tokens.get(pos) == Some(&Token::Op("=>".to_string())) {
+            pos += 1;
+            let consequent = parse_comparison(&tokens, &mut pos)?;
+            Constraint::Implies(Box::new(antecedent), Box::new(consequent))
+        } else {
+            antecedent
+        };
+
+        if pos < tokens.len() {
+            return Err(format!("unexpected {:?} in the constraint {}", tokens[pos], text));
+        }
+        Ok(constraint)
+    }
+
+    /// Whether the state satisfies the constraint. A comparison that cannot be evaluated (e.g.
+    /// an arithmetic operation on a string value) is not satisfied.
+    pub fn is_satisfied(&self, state: &State) -> bool {
+        match *self {
+            Constraint::Compare(ref lhs, op, ref rhs) => {
+                match (lhs.eval(state), rhs.eval(state)) {
+                    (Some(l), Some(r)) => compare(&l, op, &r),
+                    _ => false,
+                }
+            }
+            Constraint::Implies(ref antecedent, ref consequent) => {
+                !antecedent.is_satisfied(state) || consequent.is_satisfied(state)
+            }
+        }
+    }
+
+    /// Names of the parameters the constraint refers to
+    pub fn params(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        match *self {
+            Constraint::Compare(ref lhs, _, ref rhs) => {
+                lhs.params(&mut names);
+                rhs.params(&mut names);
+            }
+            Constraint::Implies(ref antecedent, ref consequent) => {
+                names.extend(antecedent.params());
+                names.extend(consequent.params());
+            }
+        }
+        names
+    }
+}
+
+
+impl Expr {
+    fn eval(&self, state: &State) -> Option<Value> {
+        match *self {
+            Expr::Num(val) => Some(Value::Num(val)),
+            Expr::Str(ref val) => Some(Value::Str(val.clone())),
+            Expr::Param(ref name) => {
+                state.get(name).map(|val| match val.as_f64() {
+                    Some(num) => Value::Num(num),
+                    None => Value::Str(val.to_string()),
+                })
+            }
+            Expr::BinOp(ref lhs, op, ref rhs) => {
+                match (lhs.eval(state), rhs.eval(state)) {
+                    (Some(Value::Num(l)), Some(Value::Num(r))) => {
+                        let res = match op {
+                            '+' => l + r,
+                            '-' => l - r,
+                            '*' => l * r,
+                            _ => l / r,
+                        };
+                        Some(Value::Num(res))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn params(&self, names: &mut Vec<String>) {
+        match *self {
+            Expr::Param(ref name) => names.push(name.clone()),
+            Expr::BinOp(ref lhs, _, ref rhs) => {
+                lhs.params(names);
+                rhs.params(names);
+            }
+            _ => {}
+        }
+    }
+}
+
+
+/// Numbers are compared by value, strings only by equality
+fn compare(lhs: &Value, op: CmpOp, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (&Value::Num(l), &Value::Num(r)) => {
+            match op {
+                CmpOp::Lt => l < r,
+                CmpOp::Le => l <= r,
+                CmpOp::Gt => l > r,
+                CmpOp::Ge => l >= r,
+                CmpOp::Eq => l == r,
+                CmpOp::Ne => l != r,
+            }
+        }
+        _ => {
+            match op {
+                CmpOp::Eq => lhs == rhs,
+                CmpOp::Ne => lhs != rhs,
+                _ => false,
+            }
+        }
+    }
+}
+
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_digit(10) || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_digit(10) || chars[i] == '.') {
+                i += 1;
+            }
+            let num: String = chars[start..i].iter().cloned().collect();
+            match num.parse::<f64>() {
+                Ok(val) => tokens.push(Token::Num(val)),
+                Err(_) => return Err(format!("{} is not a valid number", num)),
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() &&
+                (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().cloned().collect()));
+        } else if c == '\'' || c == '"' {
+            let start = i + 1;
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                i += 1;
+            }
+            if i == chars.len() {
+                return Err(format!("unterminated string in the constraint {}", text));
+            }
+            tokens.push(Token::Str(chars[start..i].iter().cloned().collect()));
+            i += 1;
+        } else {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().cloned().collect();
+            if ["<=", ">=", "==", "!=", "=>"].contains(&two.as_str()) {
+                tokens.push(Token::Op(two));
+                i += 2;
+            } else if "+-*/()<>".contains(c) {
+                tokens.push(Token::Op(c.to_string()));
+                i += 1;
+            } else {
+                return Err(format!("unexpected character {} in the constraint {}", c, text));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+
+fn parse_comparison(tokens: &Vec<Token>, pos: &mut usize) -> Result<Constraint, String> {
+    let lhs = parse_sum(tokens, pos)?;
+
+    let op = match tokens.get(*pos) {
+        Some(&Token::Op(ref op)) => {
+            match op.as_str() {
+                "<" => CmpOp::Lt,
+                "<=" => CmpOp::Le,
+                ">" => CmpOp::Gt,
+                ">=" => CmpOp::Ge,
+                "==" => CmpOp::Eq,
+                "!=" => CmpOp::Ne,
+                _ => return Err(format!("expected a comparison instead of {}", op)),
+            }
+        }
+        other => return Err(format!("expected a comparison instead of {:?}", other)),
+    };
+    *pos += 1;
+
+    let rhs = parse_sum(tokens, pos)?;
+    Ok(Constraint::Compare(lhs, op, rhs))
+}
+
+fn parse_sum(tokens: &Vec<Token>, pos: &mut usize) -> Result<Expr, String> {
+    let mut expr = parse_product(tokens, pos)?;
+    loop {
+        let op = match tokens.get(*pos) {
+            Some(&Token::Op(ref op)) if op == "+" || op == "-" => op.chars().next().unwrap(),
+            _ => return Ok(expr),
+        };
+        *pos += 1;
+        let rhs = parse_product(tokens, pos)?;
+        expr = Expr::BinOp(Box::new(expr), op, Box::new(rhs));
+    }
+}
+
+fn parse_product(tokens: &Vec<Token>, pos: &mut usize) -> Result<Expr, String> {
+    let mut expr = parse_factor(tokens, pos)?;
+    loop {
+        let op = match tokens.get(*pos) {
+            Some(&Token::Op(ref op)) if op == "*" || op == "/" => op.chars().next().unwrap(),
+            _ => return Ok(expr),
+        };
+        *pos += 1;
+        let rhs = parse_factor(tokens, pos)?;
+        expr = Expr::BinOp(Box::new(expr), op, Box::new(rhs));
+    }
+}
+
+fn parse_factor(tokens: &Vec<Token>, pos: &mut usize) -> Result<Expr, String> {
+    let token = match tokens.get(*pos) {
+        Some(token) => token.clone(),
+        None => return Err("unexpected end of the constraint".to_string()),
+    };
+    *pos += 1;
+
+    match token {
+        Token::Num(val) => Ok(Expr::Num(val)),
+        Token::Str(val) => Ok(Expr::Str(val)),
+        Token::Ident(name) => Ok(Expr::Param(name)),
+        Token::Op(ref op) if op == "-" => {
+            let expr = parse_factor(tokens, pos)?;
+            Ok(Expr::BinOp(Box::new(Expr::Num(0.0)), '-', Box::new(expr)))
+        }
+        Token::Op(ref op) if op == "(" => {
+            let expr = parse_sum(tokens, pos)?;
+            if tokens.get(*pos) != Some(&Token::Op(")".to_string())) {
+                return Err("expected )".to_string());
+            }
+            *pos += 1;
+            Ok(expr)
+        }
+        Token::Op(op) => Err(format!("unexpected {}", op)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {State, ParamValue};
+
+    fn state(params: &[(&str, ParamValue)]) -> State {
+        params
+            .iter()
+            .map(|&(name, ref value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    fn holds(text: &str, state: &State) -> bool {
+        Constraint::parse(text).unwrap().is_satisfied(state)
+    }
+
+    #[test]
+    fn products_bind_tighter_than_sums() {
+        let empty = State::new();
+        assert!(holds("2 + 3 * 4 == 14", &empty));
+        assert!(holds("(2 + 3) * 4 == 20", &empty));
+        assert!(holds("10 - 4 - 3 == 3", &empty));
+        assert!(holds("-2 * 3 == 0 - 6", &empty));
+    }
+
+    #[test]
+    fn parameters_are_compared_by_value() {
+        let s = state(&[
+            ("MUSL_ETHREADS", ParamValue::Int(2)),
+            ("MUSL_STHREADS", ParamValue::Int(4)),
+            ("ratio", ParamValue::Float(0.5)),
+        ]);
+        assert!(holds("MUSL_ETHREADS <= MUSL_STHREADS", &s));
+        assert!(!holds("MUSL_ETHREADS > MUSL_STHREADS", &s));
+        assert!(holds("MUSL_ETHREADS * 2 == MUSL_STHREADS", &s));
+        assert!(holds("ratio < 1", &s));
+    }
+
+    #[test]
+    fn division_is_not_truncated() {
+        let s = state(&[("a", ParamValue::Int(7))]);
+        assert!(holds("a / 2 == 3.5", &s));
+        assert!(holds("a / 2 * 2 == a", &s));
+    }
+
+    #[test]
+    fn implication_holds_when_antecedent_is_false() {
+        let on = state(&[
+            ("ssl", ParamValue::Text("on".to_string())),
+            ("size", ParamValue::Int(1000)),
+        ]);
+        let off = state(&[
+            ("ssl", ParamValue::Text("off".to_string())),
+            ("size", ParamValue::Int(1000)),
+        ]);
+        assert!(!holds("ssl == 'on' => size >= 4000", &on));
+        assert!(holds("ssl == 'on' => size >= 4000", &off));
+        assert!(holds("ssl == 'on' => size < 4000", &on));
+    }
+
+    #[test]
+    fn strings_are_only_compared_by_equality() {
+        let s = state(&[("mode", ParamValue::Text("auto".to_string()))]);
+        assert!(holds("mode == 'auto'", &s));
+        assert!(holds("mode != \"manual\"", &s));
+        assert!(!holds("mode < 'b'", &s));
+        assert!(!holds("mode + 1 > 0", &s));
+    }
+
+    #[test]
+    fn numeric_text_values_are_numbers() {
+        let s = state(&[("workers", ParamValue::Text("4".to_string()))]);
+        assert!(holds("workers >= 2", &s));
+        assert!(!holds("workers == 'auto'", &s));
+    }
+
+    #[test]
+    fn unknown_parameters_are_not_satisfied() {
+        assert!(!holds("missing > 0", &State::new()));
+    }
+
+    #[test]
+    fn malformed_constraints_are_rejected() {
+        assert!(Constraint::parse("a <= b $").is_err());
+        assert!(Constraint::parse("a + b").is_err());
+        assert!(Constraint::parse("a <=").is_err());
+        assert!(Constraint::parse("(a + 1 > 2").is_err());
+        assert!(Constraint::parse("mode == 'auto").is_err());
+        assert!(Constraint::parse("a > 1 b").is_err());
+        assert!(Constraint::parse("1.2.3 > 0").is_err());
+    }
+
+    #[test]
+    fn params_lists_the_referred_parameters() {
+        let constraint = Constraint::parse("a == 'x' => b * 2 <= c").unwrap();
+        assert_eq!(constraint.params(), vec!["a", "b", "c"]);
+    }
+}
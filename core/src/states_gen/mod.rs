@@ -1,65 +1,254 @@
+pub mod constraints;
 
-use rand;
 use std::error::Error;
 use std::io::prelude::*;
 use std::path::Path;
 use std::collections::{HashMap, HashSet};
 use rand::Rng;
+use rand::distributions::{IndependentSample, Normal};
 use ansi_term::Colour::{Yellow, Red};
 use parsers::xml_parser::XMLReader;
-use State;
-use shared::{TunerParameter, IntParameter, BoolParameter};
+use {State, ParamValue};
+use NeighborhoodOperator;
+use shared::{self, TunerParameter, TunerRng, IntParameter, FloatParameter, BoolParameter, EnumParameter,
+             Scale};
+use self::constraints::Constraint;
 
 
 #[derive(Clone, Debug)]
 pub struct ParamsConfigurator {
     pub targ_int_params: Vec<IntParameter>,
+    pub targ_float_params: Vec<FloatParameter>,
     pub targ_bool_params: Vec<BoolParameter>,
-    int_params_space_state: HashMap<String, Vec<usize>>,
-    bool_params_space_state: HashMap<String, Vec<String>>,
+    pub targ_enum_params: Vec<EnumParameter>,
+    // Int and float parameters, whose values are the points of a linear or log-scale grid
+    numeric_params_space_state: HashMap<String, Vec<ParamValue>>,
+    // Bool and enum parameters, whose value is a choice among a list of strings
+    choice_params_space_state: HashMap<String, Vec<ParamValue>>,
+    // Activation conditions of the conditional parameters and default values of the parameters,
+    // which the inactive ones always take
+    conditions: HashMap<String, Constraint>,
+    defaults: State,
+    constraints: Vec<Constraint>,
+    // Operator that moves the parameters of a neighborhood and schedule of the fraction of the
+    // parameters it moves
+    operator: NeighborhoodOperator,
+    neighborhood_steps: usize,
+    neighborhood_width: f64,
+    vary_ratio: f64,
+    vary_decrease: f64,
+    seed: Option<u64>,
+    rng: TunerRng,
 }
 
-static initial_decreasing_factor: f64 = 0.6;
+/// Number of draws after which the search of a configuration that satisfies the constraints
+/// gives up
+static max_constraint_draws: usize = 1000;
 
 impl ParamsConfigurator {
-    pub fn new(ip: Vec<IntParameter>, bp: Vec<BoolParameter>) -> ParamsConfigurator {
+    pub fn new(
+        ip: Vec<IntParameter>,
+        fp: Vec<FloatParameter>,
+        bp: Vec<BoolParameter>,
+        ep: Vec<EnumParameter>,
+        conditions: HashMap<String, Constraint>,
+        constraints: Vec<Constraint>,
+        tuner_params: &TunerParameter,
+    ) -> ParamsConfigurator {
+
+        let mut defaults: State = State::new();
+        for int_param in ip.iter() {
+            defaults.insert(int_param.clone().name, ParamValue::Int(int_param.default));
+        }
+        for float_param in fp.iter() {
+            defaults.insert(float_param.clone().name, ParamValue::float(float_param.default));
+        }
+        for bool_param in bp.iter() {
+            if bool_param.default == true {
+                defaults.insert(bool_param.clone().name, ParamValue::Text(bool_param.clone().true_val));
+            } else {
+                defaults.insert(bool_param.clone().name, ParamValue::Text(bool_param.clone().false_val));
+            }
+        }
+        for enum_param in ep.iter() {
+            defaults.insert(enum_param.clone().name, ParamValue::Text(enum_param.clone().default));
+        }
+
+        // The constraints and the conditions can only refer to the parameters to tune
+        for constraint in constraints.iter().chain(conditions.values()) {
+            for name in constraint.params() {
+                if !defaults.contains_key(&name) {
+                    panic!("The constraint {:?} refers to the unknown parameter {}", constraint, name);
+                }
+            }
+        }
+
+        // The default configuration is sent to the agents as it is, hence it has to satisfy the
+        // constraints too
+        for constraint in constraints.iter() {
+            if !constraint.is_satisfied(&defaults) {
+                panic!(
+                    "The default configuration does not satisfy the constraint {:?}",
+                    constraint
+                );
+            }
+        }
+
+        let seed = tuner_params.seed;
+        let mut rng = shared::new_rng(seed, &[]);
 
         //Define the integer parameters space state
-        let mut int_params_ss: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut numeric_params_ss: HashMap<String, Vec<ParamValue>> = HashMap::new();
         for int_param in ip.iter() {
-            int_params_ss.insert(
+            numeric_params_ss.insert(
                 int_param.clone().name,
-                ParamsConfigurator::generate_int_space_state(
-                    int_param.min,
-                    int_param.max,
-                    int_param.step,
+                ParamsConfigurator::generate_numeric_space_state(
+                    int_param.min as f64,
+                    int_param.max as f64,
+                    int_param.scale,
+                    true,
+                    &mut rng,
+                ),
+            );
+        }
+
+        //Define the float parameters space state
+        for float_param in fp.iter() {
+            numeric_params_ss.insert(
+                float_param.clone().name,
+                ParamsConfigurator::generate_numeric_space_state(
+                    float_param.min,
+                    float_param.max,
+                    float_param.scale,
+                    false,
+                    &mut rng,
                 ),
             );
         }
 
         //Define the bool parameters space state
-        let mut bool_params_ss: HashMap<String, Vec<String>> = HashMap::new();
+        let mut choice_params_ss: HashMap<String, Vec<ParamValue>> = HashMap::new();
         for bool_param in bp.iter() {
-            let mut temp_vec: Vec<String> = Vec::new();
-            temp_vec.push(bool_param.clone().true_val);
-            temp_vec.push(bool_param.clone().false_val);
-            bool_params_ss.insert(bool_param.clone().name, temp_vec);
+            let mut temp_vec: Vec<ParamValue> = Vec::new();
+            temp_vec.push(ParamValue::Text(bool_param.clone().true_val));
+            temp_vec.push(ParamValue::Text(bool_param.clone().false_val));
+            choice_params_ss.insert(bool_param.clone().name, temp_vec);
+        }
+
+        //Define the enum parameters space state
+        for enum_param in ep.iter() {
+            choice_params_ss.insert(
+                enum_param.clone().name,
+                enum_param.values.iter().map(|val| ParamValue::Text(val.clone())).collect(),
+            );
         }
 
         ParamsConfigurator {
             targ_int_params: ip,
+            targ_float_params: fp,
             targ_bool_params: bp,
-            int_params_space_state: int_params_ss,
-            bool_params_space_state: bool_params_ss,
+            targ_enum_params: ep,
+            numeric_params_space_state: numeric_params_ss,
+            choice_params_space_state: choice_params_ss,
+            conditions: conditions,
+            defaults: defaults,
+            constraints: constraints,
+            operator: tuner_params.neighborhood.clone(),
+            neighborhood_steps: tuner_params.neighborhood_steps.max(1),
+            neighborhood_width: tuner_params.neighborhood_width,
+            vary_ratio: tuner_params.vary_ratio,
+            vary_decrease: tuner_params.vary_decrease,
+            seed: seed,
+            rng: rng,
         }
     }
 
+    /***
+	Replace the random number generator with the one of the given stream. Each thread that works
+	on its own copy of the configurator (e.g. a worker) must draw from a different stream.
+	***/
+    pub fn reseed(&mut self, stream: &[usize]) {
+        self.rng = shared::new_rng(self.seed, stream);
+    }
+
+    pub fn rng(&mut self) -> &mut TunerRng {
+        &mut self.rng
+    }
+
+    /***
+	Check whether a state satisfies all the constraints on the parameters given in input by the
+	user. The functions that generate new states only return valid ones.
+	***/
+    pub fn is_valid(&self, state: &State) -> bool {
+        self.constraints.iter().all(|c| c.is_satisfied(state))
+    }
+
+    /***
+	Parameters that are active in a state. A parameter without condition is always active, a
+	conditional one is active if all the parameters its condition refers to are active and the
+	condition holds. Conditions can be nested (e.g. a child of a child parameter).
+	***/
+    pub fn active_params(&self, state: &State) -> HashSet<String> {
+        let mut active: HashSet<String> = state
+            .keys()
+            .filter(|name| !self.conditions.contains_key(*name))
+            .cloned()
+            .collect();
+
+        loop {
+            let activated: Vec<String> = self.conditions
+                .iter()
+                .filter(|&(name, condition)| {
+                    !active.contains(name) && state.contains_key(name) &&
+                        condition.params().iter().all(|p| active.contains(p)) &&
+                        condition.is_satisfied(state)
+                })
+                .map(|(name, _)| name.clone())
+                .collect();
+            if activated.is_empty() {
+                return active;
+            }
+            active.extend(activated);
+        }
+    }
+
+    /***
+	Canonical form of a state: the inactive parameters take their default value, so that the
+	states that differ only in inactive parameters are the same state (hence evaluated and cached
+	once). All the functions that generate new states return canonical ones.
+	***/
+    pub fn canonical(&self, state: &State) -> State {
+        if self.conditions.is_empty() {
+            return state.clone();
+        }
+
+        let active = self.active_params(state);
+        state
+            .iter()
+            .map(|(name, value)| if active.contains(name) {
+                (name.clone(), value.clone())
+            } else {
+                (name.clone(), self.defaults.get(name).unwrap().clone())
+            })
+            .collect()
+    }
+
+    /***
+	The active parameters of a state only, i.e., the ones sent to the target agent
+	***/
+    pub fn active_state(&self, state: &State) -> State {
+        let active = self.active_params(state);
+        state
+            .iter()
+            .filter(|&(name, _)| active.contains(name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+
     pub fn get_initial_param_conf(&mut self) -> State {
-        let mut initial_params_state: State = HashMap::new();
+        let initial_params_state: State = self.defaults.clone();
 
         for int_param in self.targ_int_params.iter() {
-            initial_params_state.insert(int_param.clone().name, int_param.default.to_string());
-
             println!(
                 "{} {:?} - {} {:?}",
                 Yellow.paint("Parameter ==> "),
@@ -70,14 +259,17 @@ impl ParamsConfigurator {
 
         }
 
-        for bool_param in self.targ_bool_params.iter() {
-
-            if bool_param.default == true {
-                initial_params_state.insert(bool_param.clone().name, bool_param.clone().true_val);
-            } else {
-                initial_params_state.insert(bool_param.clone().name, bool_param.clone().false_val);
-            }
+        for float_param in self.targ_float_params.iter() {
+            println!(
+                "{} {:?} - {} {:?}",
+                Yellow.paint("Parameter ==> "),
+                float_param.name,
+                Yellow.paint("Default Value ==> "),
+                float_param.default
+            );
+        }
 
+        for bool_param in self.targ_bool_params.iter() {
             println!(
                 "{} {:?} - {} {:?}",
                 Yellow.paint("Parameter ==> "),
@@ -87,6 +279,16 @@ impl ParamsConfigurator {
             );
         }
 
+        for enum_param in self.targ_enum_params.iter() {
+            println!(
+                "{} {:?} - {} {:?}",
+                Yellow.paint("Parameter ==> "),
+                enum_param.name,
+                Yellow.paint("Default Value ==> "),
+                enum_param.default
+            );
+        }
+
 
         println!("{}",Red.paint("*******************************************************************************************************************"));
 
@@ -101,27 +303,67 @@ impl ParamsConfigurator {
             params_name.push(int_param.clone().name);
         }
 
+        for float_param in self.targ_float_params.iter() {
+            params_name.push(float_param.clone().name);
+        }
+
         for bool_param in self.targ_bool_params.iter() {
             params_name.push(bool_param.clone().name);
         }
+
+        for enum_param in self.targ_enum_params.iter() {
+            params_name.push(enum_param.clone().name);
+        }
         return params_name;
     }
 
     /***
-	Private function useful to generate the whole space state for each integer parameter based on the [min:max:step] values
-	given in input by the user.
+	Private function useful to generate the whole space state for each numeric parameter based on the [min:max:step] values
+	given in input by the user. With a percent instead of a step the values form a geometric progression, so that a
+	parameter spanning several orders of magnitude (e.g. a buffer size) is explored evenly on a log scale. The values of
+	an integer parameter are rounded, and the ones that coincide after the rounding are taken once.
 	***/
-    fn generate_int_space_state(lbound: usize, ubound: usize, step: usize) -> Vec<usize> {
-        let mut res_vec = Vec::new();
-        let num_it = (ubound - lbound) / step;
-        for x in 0..num_it {
-            res_vec.push(lbound + (step * x));
-            if x == num_it - 1 {
-                res_vec.push(lbound + (step * (x + 1)));
+    fn generate_numeric_space_state(
+        lbound: f64,
+        ubound: f64,
+        scale: Scale,
+        integer: bool,
+        rng: &mut TunerRng,
+    ) -> Vec<ParamValue> {
+        let mut values: Vec<f64> = Vec::new();
+        // Tolerance on the upper bound for the rounding errors of the floating point arithmetic
+        let ubound = ubound + ubound.abs() * 1e-9;
+        match scale {
+            Scale::Step(step) => {
+                let mut x = 0;
+                while lbound + step * (x as f64) <= ubound {
+                    values.push(lbound + step * (x as f64));
+                    x += 1;
+                }
+            }
+            Scale::Percent(percent) => {
+                let ratio = 1.0 + percent / 100.0;
+                let mut val = lbound;
+                while val <= ubound {
+                    values.push(val);
+                    val *= ratio;
+                }
+            }
+        }
+
+        let mut res_vec: Vec<ParamValue> = Vec::new();
+        for val in values {
+            let val = if integer {
+                ParamValue::Int(val.round() as usize)
+            } else {
+                ParamValue::float(val)
+            };
+            if res_vec.last() != Some(&val) {
+                res_vec.push(val);
             }
         }
         // Randomize the order of vector elements
-        rand::thread_rng().shuffle(&mut res_vec);
+        rng.shuffle(&mut res_vec);
         return res_vec;
     }
 
@@ -131,25 +373,49 @@ impl ParamsConfigurator {
         let mut neighborhoods: Vec<State> = Vec::new();
         neighborhoods.clear();
 
+        // Only the active parameters vary
+        let active = self.active_params(current_state);
 
-        for (param_name, space_state_vec) in self.int_params_space_state.iter() {
+
+        // Parameters are taken in alphabetical order, so that the neighborhoods do not depend on
+        // the order of the HashMap of the space states
+        let mut numeric_names: Vec<&String> = self.numeric_params_space_state
+            .keys()
+            .filter(|name| active.contains(*name))
+            .collect();
+        numeric_names.sort();
+        for param_name in numeric_names {
+            let space_state_vec = self.numeric_params_space_state.get(param_name).unwrap();
 
             for param_values in space_state_vec.iter() {
                 let mut temp = current_state.clone();
-                *(temp).get_mut(param_name).unwrap() = (*param_values).to_string();
+                *(temp).get_mut(param_name).unwrap() = param_values.clone();
                 neighborhoods.push(temp);
             }
         }
 
-        for (param_name, space_state_vec) in self.bool_params_space_state.iter() {
+        let mut choice_names: Vec<&String> = self.choice_params_space_state
+            .keys()
+            .filter(|name| active.contains(*name))
+            .collect();
+        choice_names.sort();
+        for param_name in choice_names {
+            let space_state_vec = self.choice_params_space_state.get(param_name).unwrap();
 
             for param_values in space_state_vec.iter() {
                 let mut temp = current_state.clone();
-                *(temp).get_mut(param_name).unwrap() = (*param_values.clone()).to_string();
+                *(temp).get_mut(param_name).unwrap() = param_values.clone();
                 neighborhoods.push(temp);
             }
         }
 
+        // A change of a parent parameter may deactivate its children
+        let neighborhoods: Vec<State> = neighborhoods
+            .iter()
+            .map(|state| self.canonical(state))
+            .filter(|state| self.is_valid(state))
+            .collect();
+
         println!(
             "Created the vector of Neighborhoods, composed by {:?} parameter configurations",
             neighborhoods.len()
@@ -159,111 +425,284 @@ impl ParamsConfigurator {
     }
 
 
+    /***
+	Function that returns the configurations that differ from the state given in input by a single
+	step of a single parameter: the previous and the next value of the grid of a numeric parameter
+	(hence a multiplicative step for a log-scale one), the other values of a bool or enum parameter.
+	Parameters are taken in alphabetical order.
+	***/
+    pub fn get_neigh_steps(&self, current_state: &State) -> Vec<State> {
+
+        let mut neighborhoods: Vec<State> = Vec::new();
+
+        // Only the active parameters are moved
+        let active = self.active_params(current_state);
+
+        let mut numeric_names: Vec<&String> = self.numeric_params_space_state
+            .keys()
+            .filter(|name| active.contains(*name))
+            .collect();
+        numeric_names.sort();
+        for param_name in numeric_names {
+            let space_state_vec = self.numeric_params_space_state.get(param_name).unwrap();
+            let current_value = match current_state.get(param_name).and_then(|v| v.as_f64()) {
+                Some(val) => val,
+                None => continue,
+            };
+
+            // The current value may not belong to the grid (e.g. a default value), hence the
+            // nearest values below and above it are taken
+            let mut lower: Option<(f64, &ParamValue)> = None;
+            let mut upper: Option<(f64, &ParamValue)> = None;
+            for param_value in space_state_vec.iter() {
+                let val: f64 = param_value.as_f64().unwrap();
+                if val < current_value && lower.map_or(true, |(l, _)| val > l) {
+                    lower = Some((val, param_value));
+                }
+                if val > current_value && upper.map_or(true, |(u, _)| val < u) {
+                    upper = Some((val, param_value));
+                }
+            }
+            for (_, param_value) in lower.into_iter().chain(upper.into_iter()) {
+                let mut temp = current_state.clone();
+                *(temp).get_mut(param_name).unwrap() = param_value.clone();
+                neighborhoods.push(temp);
+            }
+        }
+
+        let mut choice_names: Vec<&String> = self.choice_params_space_state
+            .keys()
+            .filter(|name| active.contains(*name))
+            .collect();
+        choice_names.sort();
+        for param_name in choice_names {
+            let space_state_vec = self.choice_params_space_state.get(param_name).unwrap();
+
+            for param_value in space_state_vec.iter() {
+                if current_state.get(param_name) != Some(param_value) {
+                    let mut temp = current_state.clone();
+                    *(temp).get_mut(param_name).unwrap() = param_value.clone();
+                    neighborhoods.push(temp);
+                }
+            }
+        }
+
+        return neighborhoods
+            .iter()
+            .map(|state| self.canonical(state))
+            .filter(|state| self.is_valid(state))
+            .collect();
+    }
+
+
 
 
     /***
-	Function that returns a neighborhood of the state given in input. The Neighborhood evaluation is performed in 
-	an adaptive way. At the beginning of the Annealing the space of Neighborhoods will be large (60% of the parameters will vary).
-	Then, the more the number of steps executed increase, the more the Neighborhood space gets smaller.   
+	Function that returns a neighborhood of the state given in input. The Neighborhood evaluation is performed in
+	an adaptive way. At the beginning of the Annealing the space of Neighborhoods will be large (vary_ratio of the
+	active parameters will vary, 60% by default). Then, the more the number of steps executed increase, the more the
+	Neighborhood space gets smaller. Each varying parameter is moved by the neighborhood operator, whose moves may
+	depend on the temperature relative to the initial one.
 	***/
     pub fn get_neighborhood(
         &mut self,
         params_state: &State,
         max_anneal_steps: usize,
         current_anneal_step: usize,
+        relative_temp: f64,
     ) -> State {
 
+        // Neighborhoods that do not satisfy the constraints are drawn again
+        for _ in 0..max_constraint_draws {
+            let new_params_state = self.draw_neighborhood(
+                params_state,
+                max_anneal_steps,
+                current_anneal_step,
+                relative_temp,
+            );
+            if self.is_valid(&new_params_state) {
+                return new_params_state;
+            }
+        }
+
+        println!(
+            "{} No neighborhood satisfies the constraints after {} draws, the state is kept",
+            Red.paint("Warning ==> "),
+            max_constraint_draws
+        );
+        return params_state.clone();
+    }
+
+    fn draw_neighborhood(
+        &mut self,
+        params_state: &State,
+        max_anneal_steps: usize,
+        current_anneal_step: usize,
+        relative_temp: f64,
+    ) -> State {
+
+
+        // Evaluate the coefficient with which decrease the size of neighborhood selection (the number of parameters to
+        // vary). The factor starts from vary_ratio and decreases of vary_decrease every period_of_variation, so that it
+        // would reach zero at the end of the annealing. At least one parameter always varies.
+        let decreasing_factor: f64 = if self.vary_decrease > 0.0 {
+            let period_of_variation: f64 = max_anneal_steps as f64 /
+                (self.vary_ratio / self.vary_decrease);
+            self.vary_ratio -
+                (current_anneal_step as f64 / period_of_variation).floor() * self.vary_decrease
+        } else {
+            self.vary_ratio
+        };
+        // Evaluate the number of varying parameters based on factor evaluated before. Only the
+        // active parameters vary
+        let mut active: Vec<String> = self.active_params(params_state).into_iter().collect();
+        let num_params_2_vary = ((active.len() as f64 * decreasing_factor.max(0.0)) as usize).max(1);
+
+        // The varying parameters are drawn at random among the active ones (which are sorted
+        // first, so that the draw does not depend on the order of the HashSet)
+        active.sort();
+        self.rng.shuffle(&mut active);
+        active.truncate(num_params_2_vary);
+
+        let mut new_params_state: State = params_state.clone();
+        for param_name in active {
+            let new_value = self.move_param(&param_name, params_state.get(&param_name).unwrap(), relative_temp);
+            new_params_state.insert(param_name, new_value);
+        }
 
-        // Evaluate the coefficient with which decrease the size of neighborhood selection (the number of parameters to vary). The factor will
-        // decrease every period_of_variation. The initial value of the factor has been set to 0.6. Therefore,
-        // 60% of the parameters will vary at the beginning and then such a value will decrease of 10% every period
-        let period_of_variation: f64 = max_anneal_steps as f64 /
-            ((initial_decreasing_factor as f64) * 10.0);
-        let decreasing_factor: f64 = initial_decreasing_factor -
-            ((current_anneal_step as f64 / period_of_variation).floor()) / 10.0;
-        // Evaluate the number of varying parameters based on factor evaluated before
-        let mut num_params_2_vary = (params_state.len() as f64 * decreasing_factor) as usize;
-
-
-        let mut new_params_state: State = HashMap::new();
-
-
-        //Iterates over the parameters of the current state
-        for (param_name, param_current_value) in params_state.iter() {
-
-            //The current parameter is of integer type. Select a random value from the parameter space state
-            if self.int_params_space_state.contains_key(param_name) {
-                if num_params_2_vary > 0 {
-                    let param_space_state = self.int_params_space_state.get(param_name).unwrap();
-
-                    // If there are values that can be changed take
-                    let new_value = rand::thread_rng().choose(&param_space_state).unwrap();
-                    new_params_state.insert(
-                        param_name.clone().to_string(),
-                        (*new_value).to_string(),
-                    );
-                    num_params_2_vary -= 1;
-                } else {
-                    new_params_state.insert(
-                        param_name.clone().to_string(),
-                        (*param_current_value.clone()).to_string(),
-                    );
+        return self.canonical(&new_params_state);
+    }
+
+
+    /***
+	Move of a single parameter by the neighborhood operator:
+	- uniform: a random value of the whole space state of the parameter
+	- local: a numeric parameter moves up or down of 1 to neighborhood_steps values of its grid
+	- gaussian: a numeric parameter moves of a number of values of its grid drawn from a Gaussian, whose
+	  standard deviation is neighborhood_width times the size of the grid at the initial temperature and
+	  decreases with the temperature
+	With the local and gaussian operators a numeric parameter always moves of at least one value of the grid
+	(in the direction of the scale of the parameter, since a log-scale grid is a geometric progression), a bool
+	parameter is flipped and an enum one takes a different value.
+	***/
+    fn move_param(&mut self, param_name: &str, current_value: &ParamValue, relative_temp: f64) -> ParamValue {
+
+        if let NeighborhoodOperator::uniform = self.operator {
+            return self.get_rand_param_value(param_name);
+        }
+
+        match self.numeric_params_space_state.get(param_name).cloned() {
+            Some(mut grid) => {
+                if grid.len() < 2 {
+                    return current_value.clone();
                 }
-            //The current parameter is of bool type. Select a random value from the parameter space state
-            } else {
-                if num_params_2_vary > 0 {
-                    let param_space_state = self.bool_params_space_state.get(param_name).unwrap();
-
-                    // If there are values that can be changed take
-                    let new_value = rand::thread_rng().choose(&param_space_state).unwrap();
-                    new_params_state.insert(
-                        param_name.clone().to_string(),
-                        (*new_value.clone()).to_string(),
-                    );
-                    num_params_2_vary -= 1;
-                } else {
-                    new_params_state.insert(
-                        param_name.clone().to_string(),
-                        (*param_current_value.clone()).to_string(),
-                    );
+                grid.sort_by(|a, b| {
+                    a.as_f64().unwrap().partial_cmp(&b.as_f64().unwrap()).unwrap()
+                });
+
+                // The current value may not belong to the grid (e.g. a default value), hence the
+                // move starts from the nearest value of the grid
+                let current: f64 = current_value.as_f64().unwrap();
+                let mut index = 0;
+                for (i, val) in grid.iter().enumerate() {
+                    if (val.as_f64().unwrap() - current).abs() <
+                        (grid[index].as_f64().unwrap() - current).abs()
+                    {
+                        index = i;
+                    }
                 }
-            }
 
+                let mut offset: isize = match self.operator {
+                    NeighborhoodOperator::gaussian => {
+                        let std_dev = self.neighborhood_width * relative_temp.max(0.0).min(1.0) *
+                            grid.len() as f64;
+                        if std_dev > 0.0 {
+                            Normal::new(0.0, std_dev).ind_sample(&mut self.rng).round() as isize
+                        } else {
+                            0
+                        }
+                    }
+                    _ => self.rng.gen_range(1, self.neighborhood_steps + 1) as isize,
+                };
+                if offset == 0 || self.operator == NeighborhoodOperator::local {
+                    let magnitude = offset.abs().max(1);
+                    offset = if self.rng.gen() { magnitude } else { -magnitude };
+                }
 
+                // A move beyond the bounds of the grid is reflected
+                let last = grid.len() as isize - 1;
+                let mut new_index = (index as isize + offset).max(0).min(last);
+                if new_index == index as isize {
+                    new_index = (index as isize - offset).max(0).min(last);
+                }
+                grid[new_index as usize].clone()
+            }
+            None => {
+                let others: Vec<ParamValue> = self.choice_params_space_state
+                    .get(param_name)
+                    .unwrap()
+                    .iter()
+                    .filter(|val| *val != current_value)
+                    .cloned()
+                    .collect();
+                match self.rng.choose(&others) {
+                    Some(val) => val.clone(),
+                    None => current_value.clone(),
+                }
+            }
         }
-
-        return new_params_state;
     }
 
 
     /***
-	Function that returns a random state
+	Function that returns a random state. If no random draw satisfies the constraints, the default
+	state (which always satisfies them) is returned.
 	***/
     pub fn get_rand_param(&mut self) -> State {
 
-        let mut new_params_state: State = HashMap::new();
+        // States that do not satisfy the constraints are drawn again
+        for _ in 0..max_constraint_draws {
+            let new_params_state = self.draw_rand_param();
+            if self.is_valid(&new_params_state) {
+                return new_params_state;
+            }
+        }
 
-        // The HashMap iterator provides (key,value) pair in a random order
-        for int_param_name in self.int_params_space_state.keys() {
+        println!(
+            "{} No random configuration satisfies the constraints after {} draws, the default one \
+                  is taken",
+            Red.paint("Warning ==> "),
+            max_constraint_draws
+        );
+        return self.defaults.clone();
+    }
+
+    fn draw_rand_param(&mut self) -> State {
 
-            let param_space_state = self.int_params_space_state.get(int_param_name).unwrap();
-            let new_value = rand::thread_rng().choose(&param_space_state).unwrap();
-            new_params_state.insert(int_param_name.clone().to_string(), (*new_value).to_string());
+        let mut new_params_state: State = State::new();
+
+        // The HashMap iterator provides (key,value) pair in a random order, hence the parameters
+        // are sorted
+        let mut numeric_names: Vec<&String> = self.numeric_params_space_state.keys().collect();
+        numeric_names.sort();
+        for numeric_param_name in numeric_names {
+
+            let param_space_state = self.numeric_params_space_state.get(numeric_param_name).unwrap();
+            let new_value = self.rng.choose(&param_space_state).unwrap();
+            new_params_state.insert(numeric_param_name.clone(), new_value.clone());
         }
 
-        // The HashMap iterator provides (key,value) pair in a random order
-        for bool_param_name in self.bool_params_space_state.keys() {
+        let mut choice_names: Vec<&String> = self.choice_params_space_state.keys().collect();
+        choice_names.sort();
+        for choice_param_name in choice_names {
 
-            let param_space_state = self.bool_params_space_state.get(bool_param_name).unwrap();
-            let new_value = rand::thread_rng().choose(&param_space_state).unwrap();
-            new_params_state.insert(
-                bool_param_name.clone().to_string(),
-                (*new_value.clone()).to_string(),
-            );
+            let param_space_state = self.choice_params_space_state.get(choice_param_name).unwrap();
+            let new_value = self.rng.choose(&param_space_state).unwrap();
+            new_params_state.insert(choice_param_name.clone(), new_value.clone());
         }
 
 
-        return new_params_state;
+        return self.canonical(&new_params_state);
     }
 
 
@@ -278,4 +717,298 @@ impl ParamsConfigurator {
         }
         return res_vec;
     }
+
+    /***
+	Functions useful for the exhaustive grid search. The space state is the cartesian product of
+	the space states of the parameters, whose elements are numbered in mixed radix: parameters are
+	taken in alphabetical order and their values in increasing order.
+	***/
+    pub fn get_space_size(&self) -> usize {
+        let numeric_size = self.numeric_params_space_state.values().fold(1, |acc: usize, v| {
+            acc.saturating_mul(v.len())
+        });
+        self.choice_params_space_state.values().fold(numeric_size, |acc: usize, v| {
+            acc.saturating_mul(v.len())
+        })
+    }
+
+    pub fn get_grid_param(&self, index: usize) -> State {
+        let mut new_params_state: State = State::new();
+        let mut remainder = index;
+
+        let mut numeric_names: Vec<&String> = self.numeric_params_space_state.keys().collect();
+        numeric_names.sort();
+        for numeric_param_name in numeric_names {
+            let mut param_space_state = self.numeric_params_space_state.get(numeric_param_name).unwrap().clone();
+            param_space_state.sort_by(|a, b| {
+                a.as_f64().unwrap().partial_cmp(&b.as_f64().unwrap()).unwrap()
+            });
+            let len = param_space_state.len();
+            new_params_state.insert(numeric_param_name.clone(), param_space_state[remainder % len].clone());
+            remainder /= len;
+        }
+
+        let mut choice_names: Vec<&String> = self.choice_params_space_state.keys().collect();
+        choice_names.sort();
+        for choice_param_name in choice_names {
+            let param_space_state = self.choice_params_space_state.get(choice_param_name).unwrap();
+            let len = param_space_state.len();
+            new_params_state.insert(choice_param_name.clone(), param_space_state[remainder % len].clone());
+            remainder /= len;
+        }
+
+        return new_params_state;
+    }
+
+    pub fn get_rand_param_value(&mut self, param_name: &str) -> ParamValue {
+        match self.numeric_params_space_state.get(param_name) {
+            Some(param_space_state) => {
+                let new_value = self.rng.choose(&param_space_state).unwrap();
+                new_value.clone()
+            }
+            None => {
+                let param_space_state = self.choice_params_space_state.get(param_name).unwrap();
+                let new_value = self.rng.choose(&param_space_state).unwrap();
+                new_value.clone()
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {RepetitionMode, EvaluationMode, EnergyType, CoolingSchedule, SolverVersion,
+         ExchangeMode, StagnationPolicy, AcceptanceTest, CachePolicy, ParameterLevel};
+
+    fn state(params: &[(&str, ParamValue)]) -> State {
+        params
+            .iter()
+            .map(|&(name, ref value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    fn tuner_params(operator: NeighborhoodOperator) -> TunerParameter {
+        TunerParameter {
+            max_step: 100,
+            max_time: None,
+            num_iter: 1,
+            repetitions: RepetitionMode::fixed,
+            max_iter: 1,
+            ci_width: 0.1,
+            evaluation: EvaluationMode::full,
+            min_temp: None,
+            max_temp: None,
+            energy: EnergyType::throughput,
+            objectives: Vec::new(),
+            cooling: CoolingSchedule::exponential,
+            neighborhood: operator,
+            neighborhood_steps: 2,
+            neighborhood_width: 0.5,
+            vary_ratio: 1.0,
+            vary_decrease: 0.0,
+            version: SolverVersion::seqsa,
+            population_size: 1,
+            crossover_rate: 0.0,
+            mutation_rate: 0.0,
+            swap_period: 1,
+            exchange: ExchangeMode::sync,
+            initial_samples: 1,
+            min_fidelity: 1.0,
+            eta: 3,
+            stagnation_policy: StagnationPolicy::stop,
+            max_rejected: None,
+            reheat_ratio: 0.0,
+            acceptance: AcceptanceTest::mean,
+            confidence: 0.95,
+            refine_steps: 0,
+            validation_top: 0,
+            validation_runs: 0,
+            checkpoint: None,
+            checkpoint_period: 1,
+            resume: false,
+            eval_cache: None,
+            cache_policy: CachePolicy::reuse,
+            seed: Some(42),
+        }
+    }
+
+    /// Configurator of a service whose buffer size is only tuned with ssl on, and whose ratio is
+    /// only tuned with a buffer of at least 4000 (a nested condition). The threads grow by 100%.
+    fn configurator(operator: NeighborhoodOperator) -> ParamsConfigurator {
+        let ip = vec![
+            IntParameter {
+                name: "size".to_string(),
+                min: 1000,
+                max: 8000,
+                scale: Scale::Step(1000.0),
+                default: 4000,
+                level: ParameterLevel::runtime,
+            },
+            IntParameter {
+                name: "threads".to_string(),
+                min: 1,
+                max: 64,
+                scale: Scale::Percent(100.0),
+                default: 4,
+                level: ParameterLevel::runtime,
+            },
+        ];
+        let fp = vec![
+            FloatParameter {
+                name: "ratio".to_string(),
+                min: 0.1,
+                max: 1.0,
+                scale: Scale::Step(0.1),
+                default: 0.5,
+                level: ParameterLevel::runtime,
+            },
+        ];
+        let ep = vec![
+            EnumParameter {
+                name: "ssl".to_string(),
+                values: vec!["on".to_string(), "off".to_string()],
+                default: "off".to_string(),
+                level: ParameterLevel::runtime,
+            },
+        ];
+        let mut conditions: HashMap<String, Constraint> = HashMap::new();
+        conditions.insert("size".to_string(), Constraint::parse("ssl == 'on'").unwrap());
+        conditions.insert("ratio".to_string(), Constraint::parse("size >= 4000").unwrap());
+
+        ParamsConfigurator::new(
+            ip,
+            fp,
+            Vec::new(),
+            ep,
+            conditions,
+            Vec::new(),
+            &tuner_params(operator),
+        )
+    }
+
+    fn sample(ssl: &str, size: usize, ratio: f64) -> State {
+        state(&[
+            ("ssl", ParamValue::Text(ssl.to_string())),
+            ("size", ParamValue::Int(size)),
+            ("ratio", ParamValue::float(ratio)),
+            ("threads", ParamValue::Int(8)),
+        ])
+    }
+
+    #[test]
+    fn inactive_params_take_their_default() {
+        let pc = configurator(NeighborhoodOperator::uniform);
+
+        let canonical = pc.canonical(&sample("off", 7000, 0.9));
+        assert_eq!(canonical, sample("off", 4000, 0.5));
+
+        // The ratio is inactive through its nested condition only
+        let canonical = pc.canonical(&sample("on", 2000, 0.9));
+        assert_eq!(canonical, sample("on", 2000, 0.5));
+
+        let all_active = sample("on", 7000, 0.9);
+        assert_eq!(pc.canonical(&all_active), all_active);
+    }
+
+    #[test]
+    fn states_differing_in_inactive_params_are_the_same() {
+        let pc = configurator(NeighborhoodOperator::uniform);
+        let a = pc.canonical(&sample("off", 7000, 0.9));
+        let b = pc.canonical(&sample("off", 2000, 0.3));
+        assert_eq!(a, b);
+        assert_eq!(a.encode_params(), b.encode_params());
+    }
+
+    #[test]
+    fn inactive_params_are_not_sent_to_the_agent() {
+        let pc = configurator(NeighborhoodOperator::uniform);
+
+        let sent = pc.active_state(&sample("off", 7000, 0.9));
+        assert_eq!(
+            sent,
+            state(&[
+                ("ssl", ParamValue::Text("off".to_string())),
+                ("threads", ParamValue::Int(8)),
+            ])
+        );
+
+        let sent = pc.active_state(&sample("on", 2000, 0.9));
+        assert!(sent.contains_key("size"));
+        assert!(!sent.contains_key("ratio"));
+
+        let all_active = sample("on", 7000, 0.9);
+        assert_eq!(pc.active_state(&all_active), all_active);
+    }
+
+    fn grid_values(pc: &mut ParamsConfigurator, name: &str, from: f64, temp: f64) -> Vec<f64> {
+        let current = if name == "ratio" {
+            ParamValue::float(from)
+        } else {
+            ParamValue::Int(from as usize)
+        };
+        (0..200)
+            .map(|_| pc.move_param(name, &current, temp).as_f64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn local_moves_are_reflected_at_the_bounds() {
+        let mut pc = configurator(NeighborhoodOperator::local);
+
+        // From the minimum and the maximum the moves only go inwards
+        for val in grid_values(&mut pc, "size", 1000.0, 1.0) {
+            assert!(val == 2000.0 || val == 3000.0, "{}", val);
+        }
+        for val in grid_values(&mut pc, "size", 8000.0, 1.0) {
+            assert!(val == 7000.0 || val == 6000.0, "{}", val);
+        }
+    }
+
+    #[test]
+    fn local_moves_follow_the_scale() {
+        let mut pc = configurator(NeighborhoodOperator::local);
+
+        // At most neighborhood_steps values of the grid away, and never still
+        for val in grid_values(&mut pc, "size", 4000.0, 1.0) {
+            assert!([2000.0, 3000.0, 5000.0, 6000.0].contains(&val), "{}", val);
+        }
+        for val in grid_values(&mut pc, "ratio", 0.5, 1.0) {
+            let steps = ((val - 0.5) / 0.1).round();
+            assert!((val - 0.5 - steps * 0.1).abs() < 1e-6, "{}", val);
+            assert!(steps.abs() >= 1.0 && steps.abs() <= 2.0, "{}", val);
+        }
+        // A log-scale parameter moves by a factor of (100 + percent)%
+        for val in grid_values(&mut pc, "threads", 8.0, 1.0) {
+            assert!([2.0, 4.0, 16.0, 32.0].contains(&val), "{}", val);
+        }
+    }
+
+    #[test]
+    fn gaussian_moves_stay_on_the_grid_within_bounds() {
+        let mut pc = configurator(NeighborhoodOperator::gaussian);
+
+        for &from in [1000.0, 4000.0, 8000.0].iter() {
+            for val in grid_values(&mut pc, "size", from, 1.0) {
+                assert!(val >= 1000.0 && val <= 8000.0, "{}", val);
+                assert!(val % 1000.0 == 0.0 && val != from, "{}", val);
+            }
+        }
+        for val in grid_values(&mut pc, "threads", 64.0, 1.0) {
+            assert!(val >= 1.0 && val < 64.0 && (val as usize).is_power_of_two(), "{}", val);
+        }
+    }
+
+    #[test]
+    fn gaussian_moves_shrink_to_one_step_when_cold() {
+        let mut pc = configurator(NeighborhoodOperator::gaussian);
+
+        for val in grid_values(&mut pc, "size", 4000.0, 0.0) {
+            assert!(val == 3000.0 || val == 5000.0, "{}", val);
+        }
+        for val in grid_values(&mut pc, "threads", 1.0, 0.0) {
+            assert_eq!(val, 2.0);
+        }
+    }
 }